@@ -0,0 +1,315 @@
+use std::{borrow::Borrow, io::Result, ops::Range};
+
+use crate::{
+    camera::Camera, color::Color, matrix::Matrix, point::Point, quaternion::Quaternion,
+    utils::Float, vector::Vector, world::World,
+};
+
+/// How the value moves between two keyframes
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Cubic Bézier from (0, 0) to (1, 1) through the two control points, time along x and
+    /// progress along y, like `cubic-bezier` in CSS
+    Bezier {
+        x1: Float,
+        y1: Float,
+        x2: Float,
+        y2: Float,
+    },
+}
+
+#[allow(dead_code)]
+impl Easing {
+    /// Starts and ends slowly
+    pub const EASE_IN_OUT: Easing = Easing::Bezier {
+        x1: 0.42,
+        y1: 0.0,
+        x2: 0.58,
+        y2: 1.0,
+    };
+
+    /// Progress between the two keyframes for a time t in [0, 1]
+    pub fn apply(&self, t: Float) -> Float {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::Bezier { x1, y1, x2, y2 } => {
+                let curve = |a: Float, b: Float, s: Float| {
+                    3.0 * a * s * (1.0 - s).powi(2) + 3.0 * b * s * s * (1.0 - s) + s.powi(3)
+                };
+                // x grows with s as long as x1 and x2 are in [0, 1], so bisection finds it
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..32 {
+                    let middle = (low + high) / 2.0;
+                    if curve(x1, x2, middle) < t {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                curve(y1, y2, (low + high) / 2.0)
+            }
+        }
+    }
+}
+
+/// Values that can be blended, t = 0 gives `self` and t = 1 gives `other`
+pub trait Interpolate {
+    fn interpolate(&self, other: &Self, t: Float) -> Self;
+}
+
+impl Interpolate for Float {
+    fn interpolate(&self, other: &Float, t: Float) -> Float {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Color, t: Float) -> Color {
+        &(self * (1.0 - t)) + &(other * t)
+    }
+}
+
+impl Interpolate for Point {
+    fn interpolate(&self, other: &Point, t: Float) -> Point {
+        self + &(&(other - self) * t)
+    }
+}
+
+impl Interpolate for Vector {
+    fn interpolate(&self, other: &Vector, t: Float) -> Vector {
+        &(self * (1.0 - t)) + &(other * t)
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(&self, other: &Quaternion, t: Float) -> Quaternion {
+        self.slerp(other, t)
+    }
+}
+
+/// Transforms are blended part by part so rotations turn instead of shrinking through the
+/// middle, matrices that can't be decomposed (shear) fall back to `Matrix::lerp`
+impl Interpolate for Matrix {
+    fn interpolate(&self, other: &Matrix, t: Float) -> Matrix {
+        match (self.decompose(), other.decompose()) {
+            (Ok(mut from), Ok(to)) => {
+                from.translation = from.translation.interpolate(&to.translation, t);
+                from.rotation = from.rotation.interpolate(&to.rotation, t);
+                from.scale = from.scale.interpolate(&to.scale, t);
+                from.to_matrix()
+            }
+            _ => self.lerp(other, t),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Keyframe<T> {
+    pub frame: Float,
+    pub value: T,
+    /// Used on the way from this keyframe to the next one
+    pub easing: Easing,
+}
+
+/// Value changing over the frames, held still before the first and after the last keyframe
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    /// Sorted by frame
+    pub keyframes: Vec<Keyframe<T>>,
+}
+
+#[allow(dead_code)]
+impl<T: Interpolate + Clone> Track<T> {
+    /// A track needs a value from the start, this is its first keyframe
+    pub fn new(frame: Float, value: T) -> Self {
+        Track {
+            keyframes: vec![Keyframe {
+                frame,
+                value,
+                easing: Easing::Linear,
+            }],
+        }
+    }
+
+    /// Adds a keyframe, `easing` is used until the following one. A keyframe on the same
+    /// frame as an existing one replaces it
+    pub fn key(mut self, frame: Float, value: T, easing: Easing) -> Self {
+        let keyframe = Keyframe {
+            frame,
+            value,
+            easing,
+        };
+        match self.keyframes.iter().position(|k| k.frame >= frame) {
+            Some(index) if self.keyframes[index].frame == frame => self.keyframes[index] = keyframe,
+            Some(index) => self.keyframes.insert(index, keyframe),
+            None => self.keyframes.push(keyframe),
+        }
+        self
+    }
+
+    pub fn value_at(&self, frame: Float) -> T {
+        let next = self.keyframes.iter().position(|k| k.frame > frame);
+        match next {
+            Some(0) => self.keyframes[0].value.clone(),
+            None => self.keyframes[self.keyframes.len() - 1].value.clone(),
+            Some(index) => {
+                let (from, to) = (&self.keyframes[index - 1], &self.keyframes[index]);
+                let t = (frame - from.frame) / (to.frame - from.frame);
+                from.value.interpolate(&to.value, from.easing.apply(t))
+            }
+        }
+    }
+}
+
+/// Path of a numbered frame: the run of `#` in the pattern becomes the frame number padded
+/// with zeros to its length, without `#` the number goes before the extension as `_0001`
+#[allow(dead_code)]
+pub fn frame_path(pattern: &str, frame: usize) -> String {
+    match pattern.find('#') {
+        Some(start) => {
+            let width = pattern[start..].chars().take_while(|c| *c == '#').count();
+            format!(
+                "{}{:0width$}{}",
+                &pattern[..start],
+                frame,
+                &pattern[start + width..],
+                width = width
+            )
+        }
+        None => {
+            let (stem, extension) = match pattern.rsplit_once('.') {
+                Some((stem, extension)) if !stem.is_empty() && !extension.contains('/') => {
+                    (stem, format!(".{}", extension))
+                }
+                _ => (pattern, String::new()),
+            };
+            format!("{}_{:04}{}", stem, frame, extension)
+        }
+    }
+}
+
+/// Renders every frame of the range to its own file (see `frame_path`) and returns the
+/// paths. `scene` gives the world and camera of a frame, it can hand out the same world
+/// every time when only the camera moves
+#[allow(dead_code)]
+pub fn render_sequence<W: Borrow<World>>(
+    frames: Range<usize>,
+    pattern: &str,
+    mut scene: impl FnMut(usize) -> (W, Camera),
+) -> Result<Vec<String>> {
+    let mut paths = vec![];
+    for frame in frames {
+        let (world, camera) = scene(frame);
+        let path = frame_path(pattern, frame);
+        camera.render(world.borrow()).save(&path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        light::PointLight, sphere::Sphere, transform::Transform, utils::consts::PI, utils::EPSILON,
+    };
+
+    #[test]
+    fn should_ease_between_keyframes() {
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert_eq!(Easing::Linear.apply(2.0), 1.0);
+        let ease = Easing::EASE_IN_OUT;
+        assert!(ease.apply(0.0).abs() < EPSILON);
+        assert!((ease.apply(0.5) - 0.5).abs() < 0.001);
+        assert!((ease.apply(1.0) - 1.0).abs() < EPSILON);
+        assert!(ease.apply(0.1) < 0.1 && ease.apply(0.9) > 0.9);
+    }
+
+    #[test]
+    fn should_interpolate_a_track() {
+        let track = Track::new(10.0, 1.0).key(30.0, 5.0, Easing::Linear).key(
+            20.0,
+            3.0,
+            Easing::EASE_IN_OUT,
+        );
+        assert_eq!(track.value_at(0.0), 1.0);
+        assert_eq!(track.value_at(15.0), 2.0);
+        assert!((track.value_at(25.0) - 4.0).abs() < 0.001);
+        assert!(track.value_at(21.0) < 3.2);
+        assert_eq!(track.value_at(100.0), 5.0);
+        let replaced = track.key(30.0, 7.0, Easing::Linear);
+        assert_eq!(replaced.keyframes.len(), 3);
+        assert_eq!(replaced.value_at(30.0), 7.0);
+    }
+
+    #[test]
+    fn should_turn_transforms_instead_of_shrinking_them() {
+        let from = Transform::identity().translate(0.0, 0.0, 0.0).build();
+        let to = Transform::identity()
+            .scale(2.0, 2.0, 2.0)
+            .rotate_y(PI)
+            .translate(4.0, 0.0, 0.0)
+            .build();
+        let halfway = Track::new(0.0, from)
+            .key(10.0, to, Easing::Linear)
+            .value_at(5.0);
+        let expected = Transform::identity()
+            .scale(1.5, 1.5, 1.5)
+            .rotate_y(PI / 2.0)
+            .translate(2.0, 0.0, 0.0)
+            .build();
+        assert!(
+            halfway == expected
+                || halfway
+                    == Transform::identity()
+                        .scale(1.5, 1.5, 1.5)
+                        .rotate_y(-PI / 2.0)
+                        .translate(2.0, 0.0, 0.0)
+                        .build()
+        );
+    }
+
+    #[test]
+    fn should_number_the_frames() {
+        assert_eq!(frame_path("out/frame_####.png", 7), "out/frame_0007.png");
+        assert_eq!(frame_path("f##.ppm", 123), "f123.ppm");
+        assert_eq!(frame_path("spin.png", 1), "spin_0001.png");
+        assert_eq!(frame_path("./renders/spin", 12), "./renders/spin_0012");
+    }
+
+    #[test]
+    fn should_render_a_sequence() {
+        let directory = std::env::temp_dir().join(format!("sequence-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let pattern = directory.join("frame_##.ppm");
+        let mut world = World::new();
+        world.add_object(Box::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None)));
+        world.lights.push(Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        let distance = Track::new(1.0, 5.0).key(3.0, 10.0, Easing::Linear);
+        let paths = render_sequence(1..4, pattern.to_str().unwrap(), |frame| {
+            let from = Point::new(0.0, 0.0, -distance.value_at(frame as Float));
+            let view = Matrix::view_transform(
+                &from,
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            );
+            (&world, Camera::new(4, 4, PI / 3.0, Some(view)))
+        })
+        .unwrap();
+        assert_eq!(paths.len(), 3);
+        assert!(paths[2].ends_with("frame_03.ppm"));
+        for path in paths.iter() {
+            assert!(std::fs::read_to_string(path).unwrap().starts_with("P3"));
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    point::Point,
+    texture::{sample_image, Filter, UvMapping, Wrap},
+    utils::Float,
+    vector::Vector,
+};
+
+/// What rays see when they miss every object, it also lights the scene in the path tracer
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum Background {
+    Color(Color),
+    /// Blends from `bottom` straight down to `top` straight up
+    Gradient {
+        top: Color,
+        bottom: Color,
+    },
+    /// Latitude-longitude image, usually HDR, the center of the image is +z
+    Equirectangular(Arc<Canvas>),
+    /// One image per face ordered +x, -x, +y, -y, +z, -z, each seen from inside the cube
+    /// with +y up (the up and down faces have -z and +z up)
+    CubeMap([Arc<Canvas>; 6]),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Color::new(0.0, 0.0, 0.0))
+    }
+}
+
+#[allow(dead_code)]
+impl Background {
+    pub fn color_in(&self, direction: &Vector) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Gradient { top, bottom } => {
+                let t = 0.5 * (direction.normalize().y + 1.0);
+                &(bottom * (1.0 - t)) + &(top * t)
+            }
+            Background::Equirectangular(image) => {
+                let (u, v) = UvMapping::Spherical
+                    .map(&Point::new(direction.x, direction.y, direction.z), None);
+                sample_image(image, u, v, Filter::Bilinear, Wrap::Repeat)
+            }
+            Background::CubeMap(faces) => {
+                let (face, u, v) = cube_face(direction);
+                sample_image(&faces[face], u, v, Filter::Bilinear, Wrap::Clamp)
+            }
+        }
+    }
+}
+
+/// Index of the face hit by the direction and the texture coordinates on it
+fn cube_face(direction: &Vector) -> (usize, Float, Float) {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (abs_x, abs_y, abs_z) = (x.abs(), y.abs(), z.abs());
+    // (face, horizontal, vertical, major axis)
+    let (face, s, t, major) = if abs_x >= abs_y && abs_x >= abs_z {
+        if x > 0.0 {
+            (0, -z, y, abs_x)
+        } else {
+            (1, z, y, abs_x)
+        }
+    } else if abs_y >= abs_z {
+        if y > 0.0 {
+            (2, x, -z, abs_y)
+        } else {
+            (3, x, z, abs_y)
+        }
+    } else if z > 0.0 {
+        (4, x, y, abs_z)
+    } else {
+        (5, -x, y, abs_z)
+    };
+    if major == 0.0 {
+        return (4, 0.5, 0.5);
+    }
+    (face, 0.5 * (s / major + 1.0), 0.5 * (t / major + 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_image(color: Color) -> Arc<Canvas> {
+        let mut canvas = Canvas::new(2, 2);
+        for y in 0..2 {
+            for x in 0..2 {
+                canvas.write_pixel(&color, x, y);
+            }
+        }
+        Arc::new(canvas)
+    }
+
+    #[test]
+    fn should_default_to_black() {
+        let background = Background::default();
+        assert_eq!(
+            background.color_in(&Vector::new(0.0, 1.0, 0.0)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn should_blend_a_vertical_gradient() {
+        let background = Background::Gradient {
+            top: Color::new(0.0, 0.0, 1.0),
+            bottom: Color::new(1.0, 1.0, 1.0),
+        };
+        assert_eq!(
+            background.color_in(&Vector::new(0.0, 2.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            background.color_in(&Vector::new(0.0, -1.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            background.color_in(&Vector::new(1.0, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn should_look_up_an_equirectangular_image() {
+        // the edges of the image are behind (-z) and painted red, the middle (+z) is green
+        let mut canvas = Canvas::new(4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                let color = if x == 0 || x == 3 {
+                    Color::new(1.0, 0.0, 0.0)
+                } else {
+                    Color::new(0.0, 1.0, 0.0)
+                };
+                canvas.write_pixel(&color, x, y);
+            }
+        }
+        let background = Background::Equirectangular(Arc::new(canvas));
+        assert_eq!(
+            background.color_in(&Vector::new(0.0, 0.0, 1.0)),
+            Color::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            background.color_in(&Vector::new(0.0, 0.0, -1.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn should_pick_the_face_of_a_cube_map() {
+        let colors = [
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 0.0),
+            Color::new(0.0, 1.0, 1.0),
+            Color::new(1.0, 0.0, 1.0),
+        ];
+        let background = Background::CubeMap(colors.map(flat_image));
+        let directions = [
+            Vector::new(1.0, 0.2, -0.3),
+            Vector::new(-1.0, 0.5, 0.5),
+            Vector::new(0.1, 3.0, 0.0),
+            Vector::new(0.0, -1.0, 0.9),
+            Vector::new(0.5, -0.5, 1.0),
+            Vector::new(0.0, 0.0, -1.0),
+        ];
+        for (direction, color) in directions.iter().zip(colors.iter()) {
+            assert_eq!(background.color_in(direction), *color);
+        }
+    }
+
+    #[test]
+    fn should_map_the_cube_face_coordinates() {
+        assert_eq!(cube_face(&Vector::new(0.0, 0.0, 1.0)), (4, 0.5, 0.5));
+        assert_eq!(cube_face(&Vector::new(-1.0, 1.0, 1.0)), (1, 1.0, 1.0));
+        assert_eq!(cube_face(&Vector::new(1.0, -0.5, -1.0)), (0, 1.0, 0.25));
+        assert_eq!(cube_face(&Vector::new(0.5, 1.0, -1.0)), (2, 0.75, 1.0));
+    }
+}
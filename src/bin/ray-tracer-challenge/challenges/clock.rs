@@ -0,0 +1,26 @@
+use ray_tracer_challenge::canvas::Canvas;
+use ray_tracer_challenge::color::Color;
+use ray_tracer_challenge::point::Point;
+use ray_tracer_challenge::transform::Transform;
+use ray_tracer_challenge::utils::{consts::PI, Float};
+
+#[allow(dead_code)]
+pub fn clock_drawing() -> Canvas {
+    let mut canvas = Canvas::new(1000, 1000);
+    let clock_width = 200.0;
+    let ticks = 12.0;
+    let angle_step = (2.0 * PI) / ticks;
+    let white = Color::new(1.0, 1.0, 1.0);
+
+    for i in 0..ticks as i32 {
+        let p = Point::new(clock_width, 0.0, 0.0);
+        let transform = Transform::identity()
+            .rotate_z(angle_step * i as Float)
+            .translate(500.0, 500.0, 0.0)
+            .build();
+        let new_point = &transform * &p;
+        canvas.fill_circle((new_point.x as i32, new_point.y as i32), 5, &white, 1.0);
+    }
+    canvas.fill_circle((500, 500), 3, &white, 1.0);
+    canvas
+}
@@ -0,0 +1,3 @@
+pub mod clock;
+pub mod projectile;
+pub mod silhouette;
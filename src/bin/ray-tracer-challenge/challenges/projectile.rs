@@ -0,0 +1,32 @@
+use ray_tracer_challenge::canvas::Canvas;
+use ray_tracer_challenge::color::Color;
+use ray_tracer_challenge::physics::{Force, Integration, Particle, Simulation};
+use ray_tracer_challenge::point::Point;
+use ray_tracer_challenge::vector::Vector;
+
+#[allow(dead_code)]
+pub fn projectile_drawing() -> Canvas {
+    let mut canvas = Canvas::new(350, 350);
+
+    let mut projectile = Particle::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.7, 1.8, 0.0));
+    // gravity and wind, one tick per time unit
+    let simulation = Simulation::new(
+        vec![
+            Force::Acceleration(Vector::new(0.0, -0.09, 0.0)),
+            Force::Acceleration(Vector::new(-0.01, 0.0, 0.0)),
+        ],
+        Integration::Euler,
+        1.0,
+    );
+
+    let to_canvas = |point: &Point| (point.x * 15.0, 350.0 - point.y * 15.0);
+    let red = Color::new(1.0, 0.2, 0.2);
+    while projectile.position.y >= 0.0 {
+        let next = simulation.step(&projectile, None);
+        let (x, y) = to_canvas(&projectile.position);
+        canvas.draw_line_smooth((x, y), to_canvas(&next.position), &red, 0.5);
+        canvas.fill_rect(x as i32 - 1, y as i32 - 1, 3, 3, &red, 1.0);
+        projectile = next;
+    }
+    canvas
+}
@@ -1,18 +1,18 @@
-use crate::{
-    canvas::Canvas, matrix::Matrix, point::Point, ray::Ray, sphere::Sphere, utils::paint_square,
+use ray_tracer_challenge::{
+    canvas::Canvas, color::Color, matrix::Matrix, point::Point, ray::Ray, sphere::Sphere,
     vector::Vector,
 };
-use std::f32::consts::PI;
+use ray_tracer_challenge::utils::{consts::PI, Float};
 
+#[allow(dead_code)]
 fn create_ray(x: usize, y: usize, origin: &Point) -> Ray {
-    let pixel_point = Point::new(x as f32, y as f32, 0.0);
+    let pixel_point = Point::new(x as Float, y as Float, 0.0);
     let direction = (&pixel_point - origin).normalize();
-    let ray = Ray::new(*origin, direction);
-    ray
+    Ray::new(*origin, direction)
 }
 
 #[allow(dead_code)]
-pub fn draw_sphere_silhouette() {
+pub fn draw_sphere_silhouette() -> Canvas {
     let mut canvas = Canvas::new(1000, 1000);
     let ray_num = 200;
     let sphere = Sphere::new(
@@ -20,47 +20,50 @@ pub fn draw_sphere_silhouette() {
         200.0,
         Some(Matrix::translation_3d(500.0, 500.0, 0.0)),
     );
-    let angle_step = (PI / 2.0) / ray_num as f32;
+    let angle_step = (PI / 2.0) / ray_num as Float;
+    let gray = Color::new(0.5, 0.5, 0.5);
+    let red = Color::new(1.0, 0.0, 0.0);
     for i in 0..(ray_num + 1) {
         let original_ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
-        let ray_rotation_transform = Matrix::rotate_z_3d(-(i as f32 * angle_step));
+        let ray_rotation_transform = Matrix::rotate_z_3d(-(i as Float * angle_step));
         let ray = original_ray.transform(&ray_rotation_transform);
-        paint_square(
-            &mut canvas,
+        canvas.blend_pixel(
+            &gray,
+            1.0,
             (ray.direction.x * 300.0) as i32,
             (ray.direction.y * 300.0) as i32,
-            0,
         );
         let intersections = sphere.intersections(&ray);
-        match sphere.hit(&intersections) {
-            Some(intersection) => {
-                let p = &ray.origin + &(&ray.direction * intersection.t);
-                paint_square(&mut canvas, p.x as i32, p.y as i32, 0);
-            }
-            None => (),
+        if let Some(intersection) = sphere.hit(&intersections) {
+            let p = &ray.origin + &(&ray.direction * intersection.t);
+            canvas.blend_pixel(&red, 1.0, p.x as i32, p.y as i32);
         }
     }
 
     let sphere_origin = &sphere.transform * &sphere.origin;
-    paint_square(
-        &mut canvas,
-        sphere_origin.x as i32,
-        sphere_origin.y as i32,
-        5,
+    canvas.fill_rect(
+        sphere_origin.x as i32 - 5,
+        sphere_origin.y as i32 - 5,
+        11,
+        11,
+        &red,
+        1.0,
     );
-    canvas.to_ppm();
+    canvas
 }
 
-pub fn draw_sphere() {
+#[allow(dead_code)]
+pub fn draw_sphere() -> Canvas {
     let canvas_width: usize = 500;
     let mut canvas = Canvas::new(canvas_width as u16, canvas_width as u16);
-    let ray_origin = Point::new(canvas_width as f32 / 2.0, canvas_width as f32 / 2.0, 420.0);
+    let red = Color::new(1.0, 0.0, 0.0);
+    let ray_origin = Point::new(canvas_width as Float / 2.0, canvas_width as Float / 2.0, 420.0);
     let sphere = Sphere::new(
         Point::new(0.0, 0.0, 0.0),
         200.0,
         Some(Matrix::translation_3d(
-            canvas_width as f32 / 2.0,
-            canvas_width as f32 / 2.0,
+            canvas_width as Float / 2.0,
+            canvas_width as Float / 2.0,
             0.0,
         )),
     );
@@ -68,14 +71,11 @@ pub fn draw_sphere() {
         for c_i in 0..canvas_width {
             let ray = create_ray(c_i, r_i, &ray_origin);
             let intersections = sphere.intersections(&ray);
-            match sphere.hit(&intersections) {
-                Some(intersection) => {
-                    let p = &ray.origin + &(&ray.direction * intersection.t);
-                    paint_square(&mut canvas, p.x as i32, p.y as i32, 0);
-                }
-                None => (),
+            if let Some(intersection) = sphere.hit(&intersections) {
+                let p = &ray.origin + &(&ray.direction * intersection.t);
+                canvas.blend_pixel(&red, 1.0, p.x as i32, p.y as i32);
             }
         }
     }
-    canvas.to_ppm();
+    canvas
 }
@@ -0,0 +1,374 @@
+use std::{fmt, io, num::NonZeroUsize, thread};
+
+use ray_tracer_challenge::{
+    animation::{render_sequence, Easing, Track},
+    camera::Camera,
+    canvas::Canvas,
+    matrix::Matrix,
+    scene::{load_scene, SceneError},
+    utils::{consts::PI, Float},
+    world::World,
+};
+
+use crate::challenges::{
+    clock::clock_drawing, projectile::projectile_drawing, silhouette::draw_sphere_silhouette,
+};
+
+pub const USAGE: &str = "\
+usage:
+  ray-tracer-challenge render <scene.json> [options]
+  ray-tracer-challenge clock [-o <file>]
+  ray-tracer-challenge projectile [-o <file>]
+  ray-tracer-challenge silhouette [-o <file>]
+
+options:
+  -o, --output <file>   image to write, .png or .ppm (default: <command>.png)
+  --width <pixels>      overrides the width of the scene camera
+  --height <pixels>     overrides the height of the scene camera
+  --samples <count>     rays per pixel
+  --threads <count>     render threads (default: every available core)
+  --seed <number>       makes the random sampling reproducible
+  --frames <count>      renders a turntable, the camera goes once around the y axis and
+                        each frame gets a number in the output name (frame_####.png)
+  -h, --help            shows this message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Render {
+        scene: String,
+        options: RenderOptions,
+    },
+    Clock {
+        output: String,
+    },
+    Projectile {
+        output: String,
+    },
+    Silhouette {
+        output: String,
+    },
+    Help,
+}
+
+/// Settings given on the command line, the ones left out come from the scene file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub output: String,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples: Option<usize>,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    pub frames: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    /// The arguments don't make sense, the usage is shown with the message
+    Usage(String),
+    Scene(SceneError),
+    Io(io::Error),
+}
+
+impl CliError {
+    /// 2 for bad arguments and 1 for everything that fails afterwards
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => 2,
+            CliError::Scene(_) | CliError::Io(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Scene(error) => write!(f, "{}", error),
+            CliError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<SceneError> for CliError {
+    fn from(error: SceneError) -> Self {
+        CliError::Scene(error)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        CliError::Io(error)
+    }
+}
+
+/// Arguments without the program name
+pub fn parse_args(args: &[String]) -> Result<Command, CliError> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err(usage("missing command")),
+    };
+    if command == "-h" || command == "--help" || command == "help" {
+        return Ok(Command::Help);
+    }
+
+    let mut positional = vec![];
+    let mut options = RenderOptions {
+        output: format!("{}.png", command),
+        width: None,
+        height: None,
+        samples: None,
+        threads: None,
+        seed: None,
+        frames: None,
+    };
+    let mut render_only = None;
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || {
+            rest.next()
+                .ok_or_else(|| usage(&format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => options.output = value()?.clone(),
+            "--width" => options.width = Some(pixels(arg, value()?)?),
+            "--height" => options.height = Some(pixels(arg, value()?)?),
+            "--samples" => options.samples = Some(positive(arg, value()?)?),
+            "--threads" => options.threads = Some(positive(arg, value()?)?),
+            "--frames" => options.frames = Some(positive(arg, value()?)?),
+            "--seed" => {
+                let seed = value()?;
+                options.seed =
+                    Some(seed.parse().map_err(|_| {
+                        usage(&format!("--seed expects a whole number, got {}", seed))
+                    })?)
+            }
+            _ if arg.starts_with('-') => return Err(usage(&format!("unknown option {}", arg))),
+            _ => positional.push(arg.clone()),
+        }
+        if !matches!(arg.as_str(), "-o" | "--output") && arg.starts_with("--") {
+            render_only = Some(arg.clone());
+        }
+    }
+
+    if command != "render" {
+        if let Some(option) = render_only {
+            return Err(usage(&format!("{} only applies to render", option)));
+        }
+        if let Some(extra) = positional.first() {
+            return Err(usage(&format!("unexpected argument {}", extra)));
+        }
+    }
+    let output = options.output.clone();
+    match command {
+        "render" => match positional.as_slice() {
+            [scene] => Ok(Command::Render {
+                scene: scene.clone(),
+                options,
+            }),
+            [] => Err(usage("render needs a scene file")),
+            [_, extra, ..] => Err(usage(&format!("unexpected argument {}", extra))),
+        },
+        "clock" => Ok(Command::Clock { output }),
+        "projectile" => Ok(Command::Projectile { output }),
+        "silhouette" => Ok(Command::Silhouette { output }),
+        _ => Err(usage(&format!("unknown command {}", command))),
+    }
+}
+
+pub fn run(command: Command) -> Result<(), CliError> {
+    let (canvas, output): (Canvas, String) = match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Command::Render { scene, options } => {
+            let (world, camera) = prepare(&scene, &options)?;
+            if let Some(frames) = options.frames {
+                return turntable(&world, &camera, frames, &options.output);
+            }
+            (camera.render(&world), options.output)
+        }
+        Command::Clock { output } => (clock_drawing(), output),
+        Command::Projectile { output } => (projectile_drawing(), output),
+        Command::Silhouette { output } => (draw_sphere_silhouette(), output),
+    };
+    canvas.save(&output)?;
+    println!("wrote {}", output);
+    Ok(())
+}
+
+/// The scene with the camera changed by the options
+fn prepare(path: &str, options: &RenderOptions) -> Result<(World, Camera), CliError> {
+    let scene = load_scene(path)?;
+    let mut camera = scene.camera;
+    if options.width.is_some() || options.height.is_some() {
+        camera = camera.resized(
+            options.width.unwrap_or(camera.hsize),
+            options.height.unwrap_or(camera.vsize),
+        );
+    }
+    if let Some(samples) = options.samples {
+        camera.samples = samples;
+    }
+    camera.threads = match options.threads {
+        Some(threads) => threads,
+        None => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+    };
+    camera.seed = options.seed;
+    Ok((scene.world, camera))
+}
+
+/// One turn of the camera around the y axis of the world, the last frame stops a step
+/// before the first one so the sequence loops
+fn turntable(world: &World, camera: &Camera, frames: usize, output: &str) -> Result<(), CliError> {
+    let angle = Track::new(0.0, 0.0).key(frames as Float, 2.0 * PI, Easing::Linear);
+    let paths = render_sequence(1..frames + 1, output, |frame| {
+        let mut camera = camera.clone();
+        let turn = Matrix::rotate_y_3d(-angle.value_at((frame - 1) as Float));
+        camera.set_transform(camera.transform() * &turn);
+        (world, camera)
+    })?;
+    for path in paths {
+        println!("wrote {}", path);
+    }
+    Ok(())
+}
+
+fn usage(message: &str) -> CliError {
+    CliError::Usage(message.to_string())
+}
+
+fn positive(option: &str, value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(usage(&format!(
+            "{} expects a number greater than 0, got {}",
+            option, value
+        ))),
+    }
+}
+
+/// A positive size that fits the canvas
+fn pixels(option: &str, value: &str) -> Result<usize, CliError> {
+    match positive(option, value)? {
+        number if number <= usize::from(u16::MAX) => Ok(number),
+        _ => Err(usage(&format!(
+            "{} can't be larger than {}, got {}",
+            option,
+            u16::MAX,
+            value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, CliError> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_args(&args)
+    }
+
+    fn usage_message(args: &str) -> String {
+        match parse(args) {
+            Err(CliError::Usage(message)) => message,
+            other => panic!("expected a usage error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_parse_a_render() {
+        let command =
+            parse("render scenes/example.json -o out.ppm --width 64 --samples 4 --seed 9").unwrap();
+        assert_eq!(
+            command,
+            Command::Render {
+                scene: "scenes/example.json".to_string(),
+                options: RenderOptions {
+                    output: "out.ppm".to_string(),
+                    width: Some(64),
+                    height: None,
+                    samples: Some(4),
+                    threads: None,
+                    seed: Some(9),
+                    frames: None,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn should_parse_a_turntable() {
+        match parse("render a.json --frames 24 -o spin_###.png").unwrap() {
+            Command::Render { options, .. } => {
+                assert_eq!(options.frames, Some(24));
+                assert_eq!(options.output, "spin_###.png");
+            }
+            other => panic!("expected a render, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_name_the_output_after_the_command() {
+        assert_eq!(
+            parse("clock").unwrap(),
+            Command::Clock {
+                output: "clock.png".to_string()
+            }
+        );
+        assert_eq!(
+            parse("silhouette --output s.ppm").unwrap(),
+            Command::Silhouette {
+                output: "s.ppm".to_string()
+            }
+        );
+        assert_eq!(parse("projectile -h").unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn should_reject_bad_arguments() {
+        assert_eq!(usage_message(""), "missing command");
+        assert_eq!(usage_message("draw"), "unknown command draw");
+        assert_eq!(usage_message("render"), "render needs a scene file");
+        assert_eq!(usage_message("render a.json -o"), "-o needs a value");
+        assert_eq!(
+            usage_message("render a.json --fast"),
+            "unknown option --fast"
+        );
+        assert_eq!(
+            usage_message("render a.json --threads 0"),
+            "--threads expects a number greater than 0, got 0"
+        );
+        assert_eq!(
+            usage_message("render a.json --width 70000 --height 2"),
+            "--width can't be larger than 65535, got 70000"
+        );
+        assert_eq!(
+            usage_message("render a.json --height 0"),
+            "--height expects a number greater than 0, got 0"
+        );
+        assert_eq!(
+            usage_message("clock --samples 3"),
+            "--samples only applies to render"
+        );
+        assert_eq!(usage_message("clock extra"), "unexpected argument extra");
+        assert_eq!(
+            usage_message("projectile --frames 10"),
+            "--frames only applies to render"
+        );
+        assert_eq!(CliError::Usage(String::new()).exit_code(), 2);
+    }
+
+    #[test]
+    fn should_report_missing_scenes() {
+        let command = parse("render does/not/exist.json").unwrap();
+        let error = run(command).unwrap_err();
+        assert!(matches!(error, CliError::Scene(SceneError::Io(_))));
+        assert_eq!(error.exit_code(), 1);
+    }
+}
@@ -0,0 +1,17 @@
+use std::{env, process};
+
+use cli::{parse_args, run, CliError, USAGE};
+
+mod challenges;
+mod cli;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = parse_args(&args).and_then(run) {
+        eprintln!("error: {}", error);
+        if let CliError::Usage(_) = error {
+            eprintln!("\n{}", USAGE);
+        }
+        process::exit(error.exit_code());
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{matrix::Matrix, point::Point, ray::Ray, utils::Float};
+
+/// Axis-aligned bounding box
+#[derive(Debug, Copy, Clone)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+#[allow(dead_code)]
+impl Bounds {
+    pub fn new(min: Point, max: Point) -> Self {
+        Bounds { min, max }
+    }
+
+    /// A box that contains nothing, adding any point to it makes it valid
+    pub fn empty() -> Self {
+        Bounds {
+            min: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: Point::new(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn add_point(&mut self, point: &Point) {
+        self.min = Point::new(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Point::new(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn merge(&self, other: &Bounds) -> Bounds {
+        if other.is_empty() {
+            return *self;
+        }
+        let mut bounds = *self;
+        bounds.add_point(&other.min);
+        bounds.add_point(&other.max);
+        bounds
+    }
+
+    /// Grown by `amount` on every side
+    pub fn padded(&self, amount: Float) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        Bounds::new(
+            Point::new(
+                self.min.x - amount,
+                self.min.y - amount,
+                self.min.z - amount,
+            ),
+            Point::new(
+                self.max.x + amount,
+                self.max.y + amount,
+                self.max.z + amount,
+            ),
+        )
+    }
+
+    pub fn contains_point(&self, point: &Point) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+            && point.z >= self.min.z
+            && point.z <= self.max.z
+    }
+
+    pub fn corners(&self) -> [Point; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point::new(min.x, min.y, min.z),
+            Point::new(min.x, min.y, max.z),
+            Point::new(min.x, max.y, min.z),
+            Point::new(min.x, max.y, max.z),
+            Point::new(max.x, min.y, min.z),
+            Point::new(max.x, min.y, max.z),
+            Point::new(max.x, max.y, min.z),
+            Point::new(max.x, max.y, max.z),
+        ]
+    }
+
+    pub fn centroid(&self) -> Point {
+        Point::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> Float {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let (dx, dy, dz) = self.extent();
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    pub fn extent(&self) -> (Float, Float, Float) {
+        (
+            self.max.x - self.min.x,
+            self.max.y - self.min.y,
+            self.max.z - self.min.z,
+        )
+    }
+
+    /// 0 for x, 1 for y and 2 for z
+    pub fn longest_axis(&self) -> usize {
+        let (dx, dy, dz) = self.extent();
+        if dx >= dy && dx >= dz {
+            0
+        } else if dy >= dz {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test, only tells whether the ray crosses the box at all
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let (x_min, x_max) = check_axis(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (y_min, y_max) = check_axis(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        let (z_min, z_max) = check_axis(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+        let t_min = x_min.max(y_min).max(z_min);
+        let t_max = x_max.min(y_max).min(z_max);
+        t_min <= t_max && t_max >= 0.0
+    }
+
+    /// Transforms the eight corners and returns the box that contains all of them
+    pub fn transform(&self, matrix: &Matrix) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        let mut bounds = Bounds::empty();
+        for corner in self.corners().iter() {
+            bounds.add_point(&(matrix * corner));
+        }
+        bounds
+    }
+}
+
+pub fn axis_value(point: &Point, axis: usize) -> Float {
+    match axis {
+        0 => point.x,
+        1 => point.y,
+        _ => point.z,
+    }
+}
+
+fn check_axis(origin: Float, direction: Float, min: Float, max: Float) -> (Float, Float) {
+    let t_min = (min - origin) / direction;
+    let t_max = (max - origin) / direction;
+    if t_min > t_max {
+        (t_max, t_min)
+    } else {
+        (t_min, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::consts::PI;
+
+    use super::*;
+    use crate::{utils::EPSILON, vector::Vector};
+
+    #[test]
+    fn should_create_an_empty_bounds() {
+        let bounds = Bounds::empty();
+        assert!(bounds.is_empty());
+        assert_eq!(bounds.min.x, Float::INFINITY);
+        assert_eq!(bounds.max.x, Float::NEG_INFINITY);
+    }
+
+    #[test]
+    fn should_add_points_to_bounds() {
+        let mut bounds = Bounds::empty();
+        bounds.add_point(&Point::new(-5.0, 2.0, 0.0));
+        bounds.add_point(&Point::new(7.0, 0.0, -3.0));
+        assert!(!bounds.is_empty());
+        assert_eq!(bounds.min.x, -5.0);
+        assert_eq!(bounds.min.y, 0.0);
+        assert_eq!(bounds.min.z, -3.0);
+        assert_eq!(bounds.max.x, 7.0);
+        assert_eq!(bounds.max.y, 2.0);
+        assert_eq!(bounds.max.z, 0.0);
+    }
+
+    #[test]
+    fn should_merge_bounds() {
+        let b1 = Bounds::new(Point::new(-5.0, -2.0, 0.0), Point::new(7.0, 4.0, 4.0));
+        let b2 = Bounds::new(Point::new(8.0, -7.0, -2.0), Point::new(14.0, 2.0, 8.0));
+        let merged = b1.merge(&b2);
+        assert_eq!(merged.min.x, -5.0);
+        assert_eq!(merged.min.y, -7.0);
+        assert_eq!(merged.min.z, -2.0);
+        assert_eq!(merged.max.x, 14.0);
+        assert_eq!(merged.max.y, 4.0);
+        assert_eq!(merged.max.z, 8.0);
+
+        let merged = b1.merge(&Bounds::empty());
+        assert_eq!(merged.min.x, -5.0);
+        assert_eq!(merged.max.x, 7.0);
+    }
+
+    #[test]
+    fn should_check_if_contains_point() {
+        let bounds = Bounds::new(Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0));
+        assert!(bounds.contains_point(&Point::new(5.0, -2.0, 0.0)));
+        assert!(bounds.contains_point(&Point::new(8.0, 1.0, 3.0)));
+        assert!(!bounds.contains_point(&Point::new(3.0, 0.0, 3.0)));
+        assert!(!bounds.contains_point(&Point::new(8.0, 1.0, 8.0)));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn should_transform_the_bounds() {
+        let bounds = Bounds::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let matrix = &Matrix::rotate_x_3d(PI / 4.0) * &Matrix::rotate_y_3d(PI / 4.0);
+        let transformed = bounds.transform(&matrix);
+        assert!((transformed.min.x - -1.41421).abs() < EPSILON);
+        assert!((transformed.min.y - -1.70711).abs() < EPSILON);
+        assert!((transformed.min.z - -1.70711).abs() < EPSILON);
+        assert!((transformed.max.x - 1.41421).abs() < EPSILON);
+        assert!((transformed.max.y - 1.70711).abs() < EPSILON);
+        assert!((transformed.max.z - 1.70711).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_get_the_centroid_and_surface_area() {
+        let bounds = Bounds::new(Point::new(-1.0, 0.0, 2.0), Point::new(3.0, 2.0, 3.0));
+        let centroid = bounds.centroid();
+        assert_eq!(centroid.x, 1.0);
+        assert_eq!(centroid.y, 1.0);
+        assert_eq!(centroid.z, 2.5);
+        assert_eq!(
+            bounds.surface_area(),
+            2.0 * (4.0 * 2.0 + 2.0 * 1.0 + 1.0 * 4.0)
+        );
+        assert_eq!(bounds.longest_axis(), 0);
+        assert_eq!(Bounds::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn should_intersect_ray_with_bounds() {
+        let bounds = Bounds::new(Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0));
+        let cases = [
+            (
+                Point::new(15.0, 1.0, 2.0),
+                Vector::new(-1.0, 0.0, 0.0),
+                true,
+            ),
+            (
+                Point::new(-5.0, -1.0, 4.0),
+                Vector::new(1.0, 0.0, 0.0),
+                true,
+            ),
+            (Point::new(7.0, 6.0, 5.0), Vector::new(0.0, -1.0, 0.0), true),
+            (Point::new(9.0, 0.0, 3.0), Vector::new(0.0, 0.0, 1.0), true),
+            (
+                Point::new(8.0, 2.0, 12.0),
+                Vector::new(0.0, 0.0, -1.0),
+                true,
+            ),
+            (
+                Point::new(9.0, -1.0, -8.0),
+                Vector::new(0.0, 0.5, 1.0),
+                true,
+            ),
+            (
+                Point::new(9.0, -1.0, -8.0),
+                Vector::new(0.0, 0.0, -1.0),
+                false,
+            ),
+            (
+                Point::new(4.0, 0.0, 9.0),
+                Vector::new(0.0, 0.0, -1.0),
+                false,
+            ),
+            (
+                Point::new(8.0, 6.0, -1.0),
+                Vector::new(0.0, -1.0, 0.0),
+                false,
+            ),
+            (
+                Point::new(12.0, 5.0, 4.0),
+                Vector::new(1.0, 0.0, 0.0),
+                false,
+            ),
+        ];
+        for (origin, direction, expected) in cases {
+            let ray = Ray::new(origin, direction.normalize());
+            assert_eq!(bounds.intersects(&ray), expected);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use crate::{
+    object::Object,
+    pattern::Pattern,
+    point::Point,
+    texture::{ImageTexture, UvMapping},
+    utils::Float,
+    vector::Vector,
+};
+
+/// Step used to differentiate height fields and texture coordinates, in world units
+const DELTA: Float = 0.001;
+
+/// Fine surface detail that only changes the shading normal, not the geometry
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum NormalMap {
+    /// Height field read from the brightness of a pattern, the normal leans away from
+    /// the slope, `scale` sets how steep the bumps are
+    Bump {
+        height: Arc<dyn Pattern>,
+        scale: Float,
+    },
+    /// Tangent-space normal map, red goes along increasing u, green along increasing v
+    /// and blue out of the surface
+    Image(ImageTexture),
+}
+
+#[allow(dead_code)]
+impl NormalMap {
+    /// Shading normal at a point in world space, `normal` is the geometric one and `time`
+    /// places moving objects
+    pub fn perturb(
+        &self,
+        object: &dyn Object,
+        point: &Point,
+        normal: &Vector,
+        uv: Option<(Float, Float)>,
+        time: Float,
+    ) -> Vector {
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        match self {
+            NormalMap::Bump { height, scale } => {
+                let height_at = |offset: &Vector| {
+                    let color = height.pattern_at_object(object, &(point + offset), uv, time);
+                    (color.r + color.g + color.b) / 3.0
+                };
+                let slope = |direction: &Vector| {
+                    let step = direction * DELTA;
+                    (height_at(&step) - height_at(&-&step)) / (2.0 * DELTA)
+                };
+                let gradient = &(&tangent * slope(&tangent)) + &(&bitangent * slope(&bitangent));
+                (normal - &(&gradient * *scale)).normalize()
+            }
+            NormalMap::Image(texture) => {
+                let (u_direction, v_direction) =
+                    texture_frame(texture, object, point, uv, time, &tangent, &bitangent);
+                let color = texture.pattern_at_object(object, point, uv, time);
+                let (x, y, z) = (
+                    2.0 * color.r - 1.0,
+                    2.0 * color.g - 1.0,
+                    2.0 * color.b - 1.0,
+                );
+                (&(&(&u_direction * x) + &(&v_direction * y)) + &(normal * z)).normalize()
+            }
+        }
+    }
+}
+
+/// Directions in the tangent plane along which u and v grow. Coordinates stored on the
+/// shape give them from its vertices, other mappings are found by moving the point a
+/// little and looking at how the mapping changes. Falls back to an arbitrary basis when
+/// the mapping doesn't change
+fn texture_frame(
+    texture: &ImageTexture,
+    object: &dyn Object,
+    point: &Point,
+    uv: Option<(Float, Float)>,
+    time: Float,
+    tangent: &Vector,
+    bitangent: &Vector,
+) -> (Vector, Vector) {
+    if texture.mapping == UvMapping::Surface && uv.is_some() {
+        if let Some(directions) = object.uv_directions(time) {
+            return directions;
+        }
+    }
+    let uv_at = |offset: &Vector| {
+        let pattern_point = texture.mapping_point(object, &(point + offset), time);
+        texture.mapping.map(&pattern_point, uv)
+    };
+    let (u0, v0) = uv_at(&Vector::new(0.0, 0.0, 0.0));
+    let (u_t, v_t) = uv_at(&(tangent * DELTA));
+    let (u_b, v_b) = uv_at(&(bitangent * DELTA));
+    // differences across the seam of a wrapping mapping are brought back near zero
+    let difference = |a: Float, b: Float| {
+        let d = a - b;
+        (d - d.round()) / DELTA
+    };
+    let (du_t, dv_t) = (difference(u_t, u0), difference(v_t, v0));
+    let (du_b, dv_b) = (difference(u_b, u0), difference(v_b, v0));
+
+    let determinant = du_t * dv_b - du_b * dv_t;
+    if determinant.abs() < 1e-6 {
+        return (*tangent, *bitangent);
+    }
+    // inverse of the jacobian of the mapping in the (tangent, bitangent) basis
+    let u_direction = &(tangent * (dv_b / determinant)) + &(bitangent * (-dv_t / determinant));
+    let v_direction = &(tangent * (-du_b / determinant)) + &(bitangent * (du_t / determinant));
+    (u_direction.normalize(), v_direction.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canvas::Canvas, color::Color, matrix::Matrix, pattern::GradientPattern, sphere::Sphere,
+        texture::Filter, triangle::Triangle, utils::EPSILON,
+    };
+
+    fn flat_normal_map(color: Color) -> NormalMap {
+        mapped_normal_map(color, UvMapping::Planar)
+    }
+
+    fn mapped_normal_map(color: Color, mapping: UvMapping) -> NormalMap {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.write_pixel(&color, 0, 0);
+        let mut texture = ImageTexture::new(Arc::new(canvas), mapping, None);
+        texture.filter = Filter::Nearest;
+        NormalMap::Image(texture)
+    }
+
+    fn assert_vector(actual: &Vector, x: Float, y: Float, z: Float) {
+        assert!((actual.x - x).abs() < 0.001);
+        assert!((actual.y - y).abs() < 0.001);
+        assert!((actual.z - z).abs() < 0.001);
+    }
+
+    #[test]
+    fn should_keep_the_normal_on_a_flat_height_field() {
+        let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let bump = NormalMap::Bump {
+            height: Arc::new(GradientPattern::new(gray, gray, None)),
+            scale: 1.0,
+        };
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let perturbed = bump.perturb(&sphere, &Point::new(0.0, 1.0, 0.0), &normal, None, 0.0);
+        assert_vector(&perturbed, 0.0, 1.0, 0.0);
+    }
+
+    #[test]
+    fn should_lean_away_from_the_slope() {
+        let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        // the height grows by 1 per unit along x
+        let bump = NormalMap::Bump {
+            height: Arc::new(GradientPattern::new(
+                Color::new(0.0, 0.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+                None,
+            )),
+            scale: 1.0,
+        };
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let perturbed = bump.perturb(&sphere, &Point::new(0.5, 1.0, 0.0), &normal, None, 0.0);
+        let half = (2.0 as Float).sqrt() / 2.0;
+        assert_vector(&perturbed, -half, half, 0.0);
+        assert!((perturbed.magnitude() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_keep_the_normal_with_a_neutral_normal_map() {
+        let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        let map = flat_normal_map(Color::new(0.5, 0.5, 1.0));
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let perturbed = map.perturb(&sphere, &Point::new(0.2, 1.0, 0.3), &normal, None, 0.0);
+        assert_vector(&perturbed, 0.0, 1.0, 0.0);
+    }
+
+    #[test]
+    fn should_follow_the_texture_directions() {
+        // planar mapping, u grows along x and v along z
+        let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let point = Point::new(0.2, 1.0, 0.3);
+        let half = (2.0 as Float).sqrt() / 2.0;
+        let map = flat_normal_map(Color::new(0.5 + 0.5 * half, 0.5, 0.5 + 0.5 * half));
+        assert_vector(
+            &map.perturb(&sphere, &point, &normal, None, 0.0),
+            half,
+            half,
+            0.0,
+        );
+        let map = flat_normal_map(Color::new(0.5, 1.0, 0.5));
+        assert_vector(
+            &map.perturb(&sphere, &point, &normal, None, 0.0),
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        // rotating the object rotates the frame with it
+        let turned = Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::rotate_y_3d(crate::utils::consts::PI / 2.0)),
+        );
+        let map = flat_normal_map(Color::new(1.0, 0.5, 0.5));
+        assert_vector(
+            &map.perturb(&turned, &point, &normal, None, 0.0),
+            0.0,
+            0.0,
+            -1.0,
+        );
+    }
+    #[test]
+    fn should_follow_the_coordinates_of_a_triangle() {
+        // u grows along z and v along x, moving the point doesn't change the coordinates
+        let mut triangle = Triangle::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            None,
+        );
+        triangle.uvs = Some([(0.0, 0.0), (0.0, 1.0), (1.0, 0.0)]);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let point = Point::new(0.2, 0.0, 0.3);
+        let uv = Some((0.3, 0.2));
+        let map = mapped_normal_map(Color::new(1.0, 0.5, 0.5), UvMapping::Surface);
+        assert_vector(
+            &map.perturb(&triangle, &point, &normal, uv, 0.0),
+            0.0,
+            0.0,
+            1.0,
+        );
+        let map = mapped_normal_map(Color::new(0.5, 1.0, 0.5), UvMapping::Surface);
+        assert_vector(
+            &map.perturb(&triangle, &point, &normal, uv, 0.0),
+            1.0,
+            0.0,
+            0.0,
+        );
+
+        // the directions follow the transform of the triangle
+        triangle.transform = Matrix::rotate_y_3d(crate::utils::consts::PI / 2.0);
+        let map = mapped_normal_map(Color::new(1.0, 0.5, 0.5), UvMapping::Surface);
+        assert_vector(
+            &map.perturb(&triangle, &point, &normal, uv, 0.0),
+            1.0,
+            0.0,
+            0.0,
+        );
+    }
+}
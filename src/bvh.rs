@@ -0,0 +1,343 @@
+use crate::{
+    bounds::{axis_value, Bounds},
+    intersection::{sort_intersections, Intersection},
+    object::Object,
+    ray::Ray,
+    utils::Float,
+};
+
+const MAX_LEAF_SIZE: usize = 4;
+const SAH_BUCKETS: usize = 12;
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SplitMethod {
+    /// Splits at the median centroid of the longest axis
+    Median,
+    /// Surface area heuristic evaluated over a fixed number of buckets
+    Sah,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct BvhStats {
+    pub depth: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_leaf_size: usize,
+    pub object_count: usize,
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf {
+        bounds: Bounds,
+        objects: Vec<usize>,
+    },
+    Branch {
+        bounds: Bounds,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+/// Bounding volume hierarchy over a slice of objects, the objects are referenced by
+/// index so the owner (world or group) keeps them
+#[derive(Debug)]
+pub struct Bvh {
+    root: Option<Node>,
+    pub stats: BvhStats,
+}
+
+#[allow(dead_code)]
+impl Bvh {
+    pub fn build(objects: &[Box<dyn Object>], method: SplitMethod) -> Self {
+        let items: Vec<(usize, Bounds)> = objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| (index, object.parent_space_bounds()))
+            .collect();
+        let mut stats = BvhStats {
+            object_count: items.len(),
+            ..Default::default()
+        };
+        let root = if items.is_empty() {
+            None
+        } else {
+            Some(build_node(items, method, 1, &mut stats))
+        };
+        Bvh { root, stats }
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        match &self.root {
+            Some(Node::Leaf { bounds, .. }) | Some(Node::Branch { bounds, .. }) => *bounds,
+            None => Bounds::empty(),
+        }
+    }
+
+    /// Updates the boxes after the objects moved, keeping the tree. Cheaper than building
+    /// it again, but the tree can get slower if the objects move far from each other
+    pub fn refit(&mut self, objects: &[Box<dyn Object>]) {
+        if let Some(root) = &mut self.root {
+            refit_node(root, objects);
+        }
+    }
+
+    /// `objects` must be the same slice the hierarchy was built from
+    pub fn intersect<'a>(
+        &self,
+        objects: &'a [Box<dyn Object>],
+        ray: &Ray,
+    ) -> Vec<Intersection<&'a dyn Object>> {
+        let mut intersections = vec![];
+        if let Some(root) = &self.root {
+            intersect_node(root, objects, ray, &mut intersections);
+        }
+        sort_intersections(&mut intersections);
+        intersections
+    }
+}
+
+fn intersect_node<'a>(
+    node: &Node,
+    objects: &'a [Box<dyn Object>],
+    ray: &Ray,
+    intersections: &mut Vec<Intersection<&'a dyn Object>>,
+) {
+    match node {
+        Node::Leaf {
+            bounds,
+            objects: indices,
+        } => {
+            if bounds.intersects(ray) {
+                for index in indices {
+                    intersections.append(&mut objects[*index].intersect(ray));
+                }
+            }
+        }
+        Node::Branch {
+            bounds,
+            left,
+            right,
+        } => {
+            if bounds.intersects(ray) {
+                intersect_node(left, objects, ray, intersections);
+                intersect_node(right, objects, ray, intersections);
+            }
+        }
+    }
+}
+
+fn refit_node(node: &mut Node, objects: &[Box<dyn Object>]) -> Bounds {
+    match node {
+        Node::Leaf {
+            bounds,
+            objects: indices,
+        } => {
+            *bounds = indices.iter().fold(Bounds::empty(), |acc, index| {
+                acc.merge(&objects[*index].parent_space_bounds())
+            });
+            *bounds
+        }
+        Node::Branch {
+            bounds,
+            left,
+            right,
+        } => {
+            *bounds = refit_node(left, objects).merge(&refit_node(right, objects));
+            *bounds
+        }
+    }
+}
+
+fn make_leaf(items: Vec<(usize, Bounds)>, bounds: Bounds, stats: &mut BvhStats) -> Node {
+    stats.leaf_count += 1;
+    stats.max_leaf_size = stats.max_leaf_size.max(items.len());
+    Node::Leaf {
+        bounds,
+        objects: items.into_iter().map(|(index, _)| index).collect(),
+    }
+}
+
+fn build_node(
+    mut items: Vec<(usize, Bounds)>,
+    method: SplitMethod,
+    depth: usize,
+    stats: &mut BvhStats,
+) -> Node {
+    stats.node_count += 1;
+    stats.depth = stats.depth.max(depth);
+
+    let bounds = items
+        .iter()
+        .fold(Bounds::empty(), |acc, (_, b)| acc.merge(b));
+    if items.len() <= MAX_LEAF_SIZE {
+        return make_leaf(items, bounds, stats);
+    }
+
+    let mut centroid_bounds = Bounds::empty();
+    for (_, b) in items.iter() {
+        centroid_bounds.add_point(&b.centroid());
+    }
+    let axis = centroid_bounds.longest_axis();
+    let (axis_min, axis_max) = (
+        axis_value(&centroid_bounds.min, axis),
+        axis_value(&centroid_bounds.max, axis),
+    );
+    // every centroid is in the same place, there's no way to split them
+    if axis_max - axis_min <= 0.0 {
+        return make_leaf(items, bounds, stats);
+    }
+
+    items.sort_by(|(_, a), (_, b)| {
+        axis_value(&a.centroid(), axis).total_cmp(&axis_value(&b.centroid(), axis))
+    });
+    let split_index = match method {
+        SplitMethod::Median => items.len() / 2,
+        SplitMethod::Sah => match sah_split(&items, &bounds, axis, axis_min, axis_max) {
+            Some(index) => index,
+            None => return make_leaf(items, bounds, stats),
+        },
+    };
+
+    let right_items = items.split_off(split_index);
+    let left = build_node(items, method, depth + 1, stats);
+    let right = build_node(right_items, method, depth + 1, stats);
+    Node::Branch {
+        bounds,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+/// Returns the index to split the (sorted by centroid) items at, or None when keeping
+/// them in a single leaf is cheaper
+fn sah_split(
+    items: &[(usize, Bounds)],
+    bounds: &Bounds,
+    axis: usize,
+    axis_min: Float,
+    axis_max: Float,
+) -> Option<usize> {
+    let bucket_of = |b: &Bounds| {
+        let offset = (axis_value(&b.centroid(), axis) - axis_min) / (axis_max - axis_min);
+        ((offset * SAH_BUCKETS as Float) as usize).min(SAH_BUCKETS - 1)
+    };
+
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bucket_bounds = [Bounds::empty(); SAH_BUCKETS];
+    for (_, b) in items.iter() {
+        let bucket = bucket_of(b);
+        counts[bucket] += 1;
+        bucket_bounds[bucket] = bucket_bounds[bucket].merge(b);
+    }
+
+    let total_area = bounds.surface_area();
+    let mut best: Option<(Float, usize)> = None;
+    for split in 1..SAH_BUCKETS {
+        let (mut left, mut right) = (Bounds::empty(), Bounds::empty());
+        let (mut left_count, mut right_count) = (0, 0);
+        for bucket in 0..split {
+            left = left.merge(&bucket_bounds[bucket]);
+            left_count += counts[bucket];
+        }
+        for bucket in split..SAH_BUCKETS {
+            right = right.merge(&bucket_bounds[bucket]);
+            right_count += counts[bucket];
+        }
+        if left_count == 0 || right_count == 0 {
+            continue;
+        }
+        let cost = 0.125
+            + (left_count as Float * left.surface_area()
+                + right_count as Float * right.surface_area())
+                / total_area;
+        if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+            best = Some((cost, left_count));
+        }
+    }
+
+    match best {
+        Some((cost, left_count)) if cost < items.len() as Float => Some(left_count),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{matrix::Matrix, point::Point, sphere::Sphere, vector::Vector};
+
+    fn spheres_in_a_row(count: usize) -> Vec<Box<dyn Object>> {
+        (0..count)
+            .map(|i| {
+                Box::new(Sphere::new(
+                    Point::new(0.0, 0.0, 0.0),
+                    1.0,
+                    Some(Matrix::translation_3d(i as Float * 3.0, 0.0, 0.0)),
+                )) as Box<dyn Object>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_build_an_empty_bvh() {
+        let bvh = Bvh::build(&[], SplitMethod::Sah);
+        assert_eq!(bvh.stats, BvhStats::default());
+        assert!(bvh.bounds().is_empty());
+    }
+
+    #[test]
+    fn should_keep_few_objects_in_a_single_leaf() {
+        let objects = spheres_in_a_row(3);
+        let bvh = Bvh::build(&objects, SplitMethod::Median);
+        assert_eq!(bvh.stats.depth, 1);
+        assert_eq!(bvh.stats.node_count, 1);
+        assert_eq!(bvh.stats.leaf_count, 1);
+        assert_eq!(bvh.stats.max_leaf_size, 3);
+        assert_eq!(bvh.stats.object_count, 3);
+    }
+
+    #[test]
+    fn should_subdivide_with_median_split() {
+        let objects = spheres_in_a_row(16);
+        let bvh = Bvh::build(&objects, SplitMethod::Median);
+        assert_eq!(bvh.stats.depth, 3);
+        assert_eq!(bvh.stats.node_count, 7);
+        assert_eq!(bvh.stats.leaf_count, 4);
+        assert_eq!(bvh.stats.max_leaf_size, 4);
+        let bounds = bvh.bounds();
+        assert_eq!(bounds.min.x, -1.0);
+        assert_eq!(bounds.max.x, 46.0);
+    }
+
+    #[test]
+    fn should_subdivide_with_sah_split() {
+        let objects = spheres_in_a_row(64);
+        let bvh = Bvh::build(&objects, SplitMethod::Sah);
+        assert!(bvh.stats.depth > 1);
+        assert!(bvh.stats.max_leaf_size <= MAX_LEAF_SIZE);
+        assert_eq!(bvh.stats.node_count, 2 * bvh.stats.leaf_count - 1);
+    }
+
+    #[test]
+    fn should_intersect_the_same_objects_as_a_linear_search() {
+        let objects = spheres_in_a_row(32);
+        for method in [SplitMethod::Median, SplitMethod::Sah] {
+            let bvh = Bvh::build(&objects, method);
+            let ray = Ray::new(Point::new(30.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+            let intersections = bvh.intersect(&objects, &ray);
+            assert_eq!(intersections.len(), 2);
+            assert_eq!(intersections[0].t, 4.0);
+            assert_eq!(intersections[1].t, 6.0);
+
+            let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+            let intersections = bvh.intersect(&objects, &ray);
+            assert_eq!(intersections.len(), 64);
+            assert_eq!(intersections[0].t, 4.0);
+
+            let ray = Ray::new(Point::new(-5.0, 5.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+            assert_eq!(bvh.intersect(&objects, &ray).len(), 0);
+        }
+    }
+}
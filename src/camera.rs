@@ -0,0 +1,607 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    error::{RayTracerError, Result},
+    integrator::Integrator,
+    matrix::Matrix,
+    object::Object,
+    point::Point,
+    ray::Ray,
+    utils::{consts::PI, random_float, random_in_unit_disk, seed_random, Float},
+    world::World,
+};
+
+/// Camera looking towards -z from the origin, the canvas is one unit in front of it.
+/// With an `aperture` greater than 0 it behaves like a thin lens: rays start on a disk of
+/// that diameter and only the objects at `focal_distance` stay sharp.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub hsize: usize,
+    pub vsize: usize,
+    pub field_of_view: Float,
+    /// Private so it always has an inverse, see `try_set_transform`
+    transform: Matrix,
+    pub aperture: Float,
+    pub focal_distance: Float,
+    /// Rays traced per pixel, when more than 1 they are spread randomly inside the pixel
+    /// and along the exposure time so moving objects get blurred
+    pub samples: usize,
+    pub integrator: Integrator,
+    /// Rows are shared between this many threads while rendering
+    pub threads: usize,
+    /// Makes the random samples reproducible, each row gets its own sequence so the image
+    /// doesn't depend on the number of threads
+    pub seed: Option<u64>,
+    half_width: Float,
+    half_height: Float,
+    pixel_size: Float,
+}
+
+#[allow(dead_code)]
+impl Camera {
+    pub fn new(
+        hsize: usize,
+        vsize: usize,
+        field_of_view: Float,
+        transform: Option<Matrix>,
+    ) -> Self {
+        match Camera::try_new(hsize, vsize, field_of_view, transform) {
+            Ok(camera) => camera,
+            Err(error) => panic!("Camera::new {}", error),
+        }
+    }
+
+    /// Rejects an empty image or one wider or taller than 65535 pixels, a field of view
+    /// outside (0, PI) and transforms without an inverse
+    pub fn try_new(
+        hsize: usize,
+        vsize: usize,
+        field_of_view: Float,
+        transform: Option<Matrix>,
+    ) -> Result<Self> {
+        if hsize == 0 || vsize == 0 {
+            return Err(RayTracerError::InvalidArgument(format!(
+                "the image can't be empty ({}x{})",
+                hsize, vsize
+            )));
+        }
+        // the canvas stores its size in 16 bits
+        if hsize > usize::from(u16::MAX) || vsize > usize::from(u16::MAX) {
+            return Err(RayTracerError::InvalidArgument(format!(
+                "the image can't be larger than {} pixels on a side ({}x{})",
+                u16::MAX,
+                hsize,
+                vsize
+            )));
+        }
+        if !(field_of_view > 0.0 && field_of_view < PI) {
+            return Err(RayTracerError::InvalidArgument(format!(
+                "the field of view must be between 0 and PI, got {}",
+                field_of_view
+            )));
+        }
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        if !t.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "camera" });
+        }
+        let half_view = (field_of_view / 2.0).tan();
+        let aspect = hsize as Float / vsize as Float;
+        let (half_width, half_height) = if aspect >= 1.0 {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+        Ok(Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: t,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            samples: 1,
+            integrator: Integrator::default(),
+            threads: 1,
+            seed: None,
+            half_width,
+            half_height,
+            pixel_size: (half_width * 2.0) / hsize as Float,
+        })
+    }
+
+    /// Same camera with another image size, the field of view is kept
+    pub fn resized(&self, hsize: usize, vsize: usize) -> Camera {
+        Camera {
+            aperture: self.aperture,
+            focal_distance: self.focal_distance,
+            samples: self.samples,
+            integrator: self.integrator,
+            threads: self.threads,
+            seed: self.seed,
+            ..Camera::new(
+                hsize,
+                vsize,
+                self.field_of_view,
+                Some(self.transform.clone()),
+            )
+        }
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    /// Moves the camera, panics when the transform has no inverse (see
+    /// `try_set_transform`)
+    pub fn set_transform(&mut self, transform: Matrix) {
+        if let Err(error) = self.try_set_transform(transform) {
+            panic!("Camera::set_transform {}", error);
+        }
+    }
+
+    /// Rejects transforms without an inverse, like `try_new`
+    pub fn try_set_transform(&mut self, transform: Matrix) -> Result<()> {
+        if !transform.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "camera" });
+        }
+        self.transform = transform;
+        Ok(())
+    }
+
+    pub fn pixel_size(&self) -> Float {
+        self.pixel_size
+    }
+
+    /// Ray through the center of the pixel, ignoring the lens
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_for_sample(
+            &self.transform.inverse(),
+            px,
+            py,
+            (0.5, 0.5),
+            (0.0, 0.0),
+            0.0,
+        )
+    }
+
+    /// `offset` is the position inside the pixel in [0, 1), `lens` a point inside the
+    /// unit disk that is scaled by the aperture
+    fn ray_for_sample(
+        &self,
+        inverse: &Matrix,
+        px: usize,
+        py: usize,
+        offset: (Float, Float),
+        lens: (Float, Float),
+        time: Float,
+    ) -> Ray {
+        let (dx, dy) = offset;
+        let x_offset = (px as Float + dx) * self.pixel_size;
+        let y_offset = (py as Float + dy) * self.pixel_size;
+        let world_x = self.half_width - x_offset;
+        let world_y = self.half_height - y_offset;
+
+        let pixel = Point::new(world_x, world_y, -1.0);
+        let lens_radius = self.aperture / 2.0;
+        let (origin, target) = if lens_radius > 0.0 {
+            let focal_point = Point::new(
+                world_x * self.focal_distance,
+                world_y * self.focal_distance,
+                -self.focal_distance,
+            );
+            let lens_point = Point::new(lens.0 * lens_radius, lens.1 * lens_radius, 0.0);
+            (lens_point, focal_point)
+        } else {
+            (Point::new(0.0, 0.0, 0.0), pixel)
+        };
+
+        let origin = inverse * &origin;
+        let target = inverse * &target;
+        let direction = (&target - &origin).normalize();
+        Ray::with_time(origin, direction, time)
+    }
+
+    /// Every ray used to render the pixel, a single one through the center for a pinhole
+    /// camera without supersampling
+    pub fn rays_for_pixel(&self, px: usize, py: usize) -> Vec<Ray> {
+        let inverse = self.transform.inverse();
+        self.sample_rays(&inverse, px, py)
+    }
+
+    fn sample_rays(&self, inverse: &Matrix, px: usize, py: usize) -> Vec<Ray> {
+        if self.samples <= 1 && self.aperture <= 0.0 {
+            return vec![self.ray_for_sample(inverse, px, py, (0.5, 0.5), (0.0, 0.0), 0.0)];
+        }
+        (0..self.samples.max(1))
+            .map(|_| {
+                let (offset, time) = if self.samples > 1 {
+                    ((random_float(), random_float()), random_float())
+                } else {
+                    ((0.5, 0.5), 0.0)
+                };
+                self.ray_for_sample(inverse, px, py, offset, random_in_unit_disk(), time)
+            })
+            .collect()
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
+        self.render_pass(world, 0)
+    }
+
+    /// `pass` changes the seed of the rows so progressive passes don't repeat each other
+    fn render_pass(&self, world: &World, pass: usize) -> Canvas {
+        let mut canvas = Canvas::new(self.hsize as u16, self.vsize as u16);
+        let inverse = self.transform.inverse();
+        let emitters = world.emitters();
+        let next_row = AtomicUsize::new(0);
+        let rows: Vec<(usize, Vec<Color>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.clamp(1, self.vsize.max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut rows = vec![];
+                        loop {
+                            let y = next_row.fetch_add(1, Ordering::Relaxed);
+                            if y >= self.vsize {
+                                return rows;
+                            }
+                            rows.push((y, self.render_row(world, &emitters, &inverse, pass, y)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .expect("Camera::render a render thread panicked")
+                })
+                .collect()
+        });
+        for (y, row) in rows.iter() {
+            for (x, color) in row.iter().enumerate() {
+                canvas.write_pixel(color, x, *y);
+            }
+        }
+        canvas
+    }
+
+    fn render_row(
+        &self,
+        world: &World,
+        emitters: &[&dyn Object],
+        inverse: &Matrix,
+        pass: usize,
+        y: usize,
+    ) -> Vec<Color> {
+        if let Some(seed) = self.seed {
+            seed_random(seed ^ ((pass as u64) << 32 | y as u64));
+        }
+        (0..self.hsize)
+            .map(|x| {
+                let rays = self.sample_rays(inverse, x, y);
+                let mut color = Color::new(0.0, 0.0, 0.0);
+                for ray in rays.iter() {
+                    color = &color + &self.integrator.radiance_with(world, emitters, ray);
+                }
+                &color * (1.0 / rays.len() as Float)
+            })
+            .collect()
+    }
+
+    /// Renders `passes` times and accumulates the result, `on_pass` gets the average of
+    /// the passes done so far so the image can be shown while it converges
+    pub fn render_progressive<F>(&self, world: &World, passes: usize, mut on_pass: F) -> Canvas
+    where
+        F: FnMut(usize, &Canvas),
+    {
+        let mut sum = Canvas::new(self.hsize as u16, self.vsize as u16);
+        let mut image = Canvas::new(self.hsize as u16, self.vsize as u16);
+        for pass in 1..=passes {
+            let pass_image = self.render_pass(world, pass);
+            for y in 0..self.vsize {
+                for x in 0..self.hsize {
+                    let total = &sum.get_pixel(x, y) + &pass_image.get_pixel(x, y);
+                    sum.write_pixel(&total, x, y);
+                    image.write_pixel(&(&total * (1.0 / pass as Float)), x, y);
+                }
+            }
+            on_pass(pass, &image);
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::consts::PI;
+
+    use super::*;
+    use crate::{
+        light::PointLight, material::Material, sphere::Sphere, utils::EPSILON, vector::Vector,
+        world::default_world,
+    };
+
+    #[test]
+    fn should_create_a_camera() {
+        let camera = Camera::new(160, 120, PI / 2.0, None);
+        assert_eq!(camera.hsize, 160);
+        assert_eq!(camera.vsize, 120);
+        assert_eq!(camera.field_of_view, PI / 2.0);
+        assert_eq!(camera.transform(), &Matrix::identity(4));
+        assert_eq!(camera.aperture, 0.0);
+        assert_eq!(camera.samples, 1);
+    }
+
+    #[test]
+    fn should_get_the_pixel_size() {
+        let camera = Camera::new(200, 125, PI / 2.0, None);
+        assert!((camera.pixel_size() - 0.01).abs() < EPSILON);
+        let camera = Camera::new(125, 200, PI / 2.0, None);
+        assert!((camera.pixel_size() - 0.01).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_create_a_ray_through_the_center_of_the_canvas() {
+        let camera = Camera::new(201, 101, PI / 2.0, None);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert!(ray.origin.x.abs() < EPSILON);
+        assert!(ray.origin.y.abs() < EPSILON);
+        assert!(ray.origin.z.abs() < EPSILON);
+        assert!(ray.direction.x.abs() < EPSILON);
+        assert!(ray.direction.y.abs() < EPSILON);
+        assert!((ray.direction.z - -1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_create_a_ray_through_a_corner_of_the_canvas() {
+        let camera = Camera::new(201, 101, PI / 2.0, None);
+        let ray = camera.ray_for_pixel(0, 0);
+        assert!((ray.direction.x - 0.66519).abs() < EPSILON);
+        assert!((ray.direction.y - 0.33259).abs() < EPSILON);
+        assert!((ray.direction.z - -0.66851).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_create_a_ray_when_the_camera_is_transformed() {
+        let camera = Camera::new(
+            201,
+            101,
+            PI / 2.0,
+            Some(&Matrix::rotate_y_3d(PI / 4.0) * &Matrix::translation_3d(0.0, -2.0, 5.0)),
+        );
+        let ray = camera.ray_for_pixel(100, 50);
+        assert!(ray.origin.x.abs() < EPSILON);
+        assert!((ray.origin.y - 2.0).abs() < EPSILON);
+        assert!((ray.origin.z - -5.0).abs() < EPSILON);
+        assert!((ray.direction.x - (2.0 as Float).sqrt() / 2.0).abs() < EPSILON);
+        assert!(ray.direction.y.abs() < EPSILON);
+        assert!((ray.direction.z - -((2.0 as Float).sqrt()) / 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_render_the_world() {
+        let world = default_world();
+        let camera = Camera::new(
+            11,
+            11,
+            PI / 2.0,
+            Some(Matrix::view_transform(
+                &Point::new(0.0, 0.0, -5.0),
+                &Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 1.0, 0.0),
+            )),
+        );
+        let image = camera.render(&world);
+        let color = image.get_pixel(5, 5);
+        assert!((color.r - 0.38066).abs() < 0.0001);
+        assert!((color.g - 0.47583).abs() < 0.0001);
+        assert!((color.b - 0.2855).abs() < 0.0001);
+    }
+
+    #[test]
+    fn should_spread_supersamples_inside_the_pixel() {
+        let mut camera = Camera::new(10, 10, PI / 2.0, None);
+        camera.samples = 16;
+        let rays = camera.rays_for_pixel(0, 0);
+        assert_eq!(rays.len(), 16);
+        assert!(rays.iter().any(|ray| ray.time > 0.0));
+        assert!(rays.iter().all(|ray| ray.time < 1.0));
+        let pixel_edge = camera.half_width - camera.pixel_size();
+        for ray in rays.iter() {
+            // the canvas is at z = -1 so the direction can be projected on it
+            let x = ray.direction.x / -ray.direction.z;
+            let y = ray.direction.y / -ray.direction.z;
+            assert!(x <= camera.half_width + EPSILON && x >= pixel_edge - EPSILON);
+            assert!(y <= camera.half_height + EPSILON && y >= pixel_edge - EPSILON);
+        }
+    }
+
+    #[test]
+    fn should_focus_lens_rays_on_the_focal_plane() {
+        let mut camera = Camera::new(11, 11, PI / 2.0, None);
+        camera.aperture = 0.5;
+        camera.focal_distance = 5.0;
+        let rays = camera.rays_for_pixel(5, 5);
+        assert_eq!(rays.len(), 1);
+        for _ in 0..20 {
+            let ray = &camera.rays_for_pixel(5, 5)[0];
+            assert!(ray.origin.z.abs() < EPSILON);
+            assert!((ray.origin.x.powi(2) + ray.origin.y.powi(2)).sqrt() <= 0.25 + EPSILON);
+            // every ray crosses the focal plane through the same point
+            let t = -5.0 / ray.direction.z;
+            let focus = ray.position(t);
+            assert!(focus.x.abs() < 0.0001);
+            assert!(focus.y.abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn should_blur_out_of_focus_objects() {
+        let world = default_world();
+        let transform = Matrix::view_transform(
+            &Point::new(0.0, 0.0, -5.0),
+            &Point::new(0.0, 0.0, 0.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        let mut camera = Camera::new(11, 11, PI / 2.0, Some(transform));
+        // just outside of the silhouette of the sphere
+        assert_eq!(
+            world.color_at(&camera.ray_for_pixel(5, 3)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+
+        camera.aperture = 4.0;
+        camera.focal_distance = 100.0;
+        camera.samples = 64;
+        let rays = camera.rays_for_pixel(5, 3);
+        let hits = rays
+            .iter()
+            .filter(|ray| world.color_at(ray).g > 0.0)
+            .count();
+        assert!(hits > 0 && hits < rays.len());
+    }
+
+    #[test]
+    fn should_blur_moving_objects() {
+        let mut world = World::new();
+        world.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 0.5, None);
+        sphere.end_transform = Some(Matrix::translation_3d(3.0, 0.0, 0.0));
+        world.add_object(Box::new(sphere));
+        let transform = Matrix::view_transform(
+            &Point::new(0.0, 0.0, -5.0),
+            &Point::new(0.0, 0.0, 0.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        let mut camera = Camera::new(11, 11, PI / 2.0, Some(transform));
+        // the pixel in the middle of the path is only covered for part of the exposure
+        let middle = camera.ray_for_pixel(8, 5);
+        assert_eq!(world.color_at(&middle), Color::new(0.0, 0.0, 0.0));
+        camera.samples = 64;
+        let rays = camera.rays_for_pixel(8, 5);
+        let hits = rays
+            .iter()
+            .filter(|ray| world.color_at(ray).r > 0.0)
+            .count();
+        assert!(hits > 0 && hits < rays.len());
+    }
+
+    #[test]
+    fn should_render_with_the_path_tracer() {
+        let mut world = World::new();
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material = Material {
+            color: Color::new(0.0, 0.0, 0.0),
+            emission: Color::new(1.0, 0.5, 0.25),
+            ..Default::default()
+        };
+        world.add_object(Box::new(sphere));
+        let transform = Matrix::view_transform(
+            &Point::new(0.0, 0.0, -5.0),
+            &Point::new(0.0, 0.0, 0.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        let mut camera = Camera::new(11, 11, PI / 2.0, Some(transform));
+        camera.integrator = Integrator::PathTracer { max_depth: 4 };
+        let image = camera.render(&world);
+        assert_eq!(image.get_pixel(5, 5), Color::new(1.0, 0.5, 0.25));
+        assert_eq!(image.get_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_accumulate_progressive_passes() {
+        let world = default_world();
+        let transform = Matrix::view_transform(
+            &Point::new(0.0, 0.0, -5.0),
+            &Point::new(0.0, 0.0, 0.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        let camera = Camera::new(5, 5, PI / 2.0, Some(transform));
+        let mut passes_seen = vec![];
+        let image = camera.render_progressive(&world, 3, |pass, image| {
+            passes_seen.push(pass);
+            assert_eq!(image.width, 5);
+        });
+        assert_eq!(passes_seen, vec![1, 2, 3]);
+        // the whitted render is deterministic so the average doesn't change
+        assert_eq!(image.get_pixel(2, 2), camera.render(&world).get_pixel(2, 2));
+    }
+
+    #[test]
+    fn should_render_the_same_image_with_a_seed_on_any_number_of_threads() {
+        let world = default_world();
+        let transform = Matrix::view_transform(
+            &Point::new(0.0, 0.0, -5.0),
+            &Point::new(0.0, 0.0, 0.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        let mut camera = Camera::new(9, 7, PI / 2.0, Some(transform));
+        camera.integrator = Integrator::PathTracer { max_depth: 3 };
+        camera.samples = 2;
+        camera.seed = Some(11);
+        let single = camera.render(&world);
+        camera.threads = 3;
+        let parallel = camera.render(&world);
+        assert_eq!(single.pixels, parallel.pixels);
+        camera.seed = Some(12);
+        assert_ne!(camera.render(&world).pixels, single.pixels);
+    }
+
+    #[test]
+    fn should_keep_the_settings_when_resized() {
+        let mut camera = Camera::new(
+            160,
+            120,
+            PI / 2.0,
+            Some(Matrix::translation_3d(1.0, 0.0, 0.0)),
+        );
+        camera.samples = 4;
+        camera.seed = Some(3);
+        let resized = camera.resized(320, 240);
+        assert_eq!((resized.hsize, resized.vsize), (320, 240));
+        assert_eq!(resized.samples, 4);
+        assert_eq!(resized.seed, Some(3));
+        assert_eq!(resized.transform(), camera.transform());
+        assert!((resized.pixel_size() - camera.pixel_size() / 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_reject_a_transform_without_inverse() {
+        let mut camera = Camera::new(10, 10, PI / 2.0, None);
+        let error = camera
+            .try_set_transform(Matrix::scaling_3d(1.0, 0.0, 1.0))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            RayTracerError::NonInvertibleTransform { object: "camera" }
+        );
+        assert_eq!(camera.transform(), &Matrix::identity(4));
+        camera.set_transform(Matrix::translation_3d(0.0, 1.0, 0.0));
+        assert_eq!(camera.transform(), &Matrix::translation_3d(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn should_reject_invalid_cameras() {
+        assert!(Camera::try_new(0, 10, PI / 2.0, None).is_err());
+        assert!(Camera::try_new(10, 10, PI, None).is_err());
+        assert!(Camera::try_new(70_000, 2, PI / 2.0, None).is_err());
+        assert!(Camera::try_new(2, 65_536, PI / 2.0, None).is_err());
+        assert!(Camera::try_new(65_535, 1, PI / 2.0, None).is_ok());
+        assert_eq!(
+            Camera::try_new(10, 10, PI / 2.0, Some(Matrix::new(4, 4))).unwrap_err(),
+            RayTracerError::NonInvertibleTransform { object: "camera" }
+        );
+    }
+}
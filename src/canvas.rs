@@ -1,11 +1,17 @@
+use std::io::{Error, ErrorKind, Result};
+
 use crate::color::Color;
-use crate::utils::write_to_file;
+use crate::png::encode_png;
+use crate::utils::{write_to_file, Float};
 
 #[derive(Debug)]
 pub struct Canvas {
     pub width: u16,
     pub height: u16,
     pub pixels: Vec<Vec<Color>>,
+    /// Coverage of each pixel from 0 (transparent) to 1 (opaque), laid out like `pixels`.
+    /// The colors are not multiplied by it
+    pub alpha: Vec<Vec<Float>>,
 }
 
 #[allow(dead_code)]
@@ -15,23 +21,62 @@ impl Canvas {
             width: w,
             height: h,
             pixels: vec![vec![Color::new(0.0, 0.0, 0.0); usize::from(w)]; usize::from(h)],
+            alpha: vec![vec![1.0; usize::from(w)]; usize::from(h)],
+        }
+    }
+
+    /// Fully transparent, to compose layers on
+    pub fn transparent(w: u16, h: u16) -> Self {
+        Canvas {
+            alpha: vec![vec![0.0; usize::from(w)]; usize::from(h)],
+            ..Canvas::new(w, h)
         }
     }
 
+    /// The pixel becomes opaque
     pub fn write_pixel(&mut self, color: &Color, x: usize, y: usize) {
         if x >= self.width as usize || y >= self.height as usize {
             return;
         }
         self.pixels[y][x] = *color;
+        self.alpha[y][x] = 1.0;
+    }
+
+    pub fn write_alpha(&mut self, alpha: Float, x: usize, y: usize) {
+        if x >= self.width as usize || y >= self.height as usize {
+            return;
+        }
+        self.alpha[y][x] = alpha.clamp(0.0, 1.0);
     }
 
+    /// Clamped to the edges like `get_pixel`, an empty canvas is transparent
+    pub fn get_alpha(&self, x: usize, y: usize) -> Float {
+        if self.width == 0 || self.height == 0 {
+            return 0.0;
+        }
+        let x_coord = x.min(usize::from(self.width - 1));
+        let y_coord = y.min(usize::from(self.height - 1));
+        self.alpha[y_coord][x_coord]
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.alpha.iter().flatten().all(|alpha| *alpha >= 1.0)
+    }
+
+    /// Coordinates outside the canvas are clamped to the closest edge, an empty canvas is
+    /// black everywhere
     pub fn get_pixel(&self, x: usize, y: usize) -> Color {
-        let x_coord = x.clamp(0, usize::from(self.width - 1));
-        let y_coord = y.clamp(0, usize::from(self.height - 1));
+        if self.width == 0 || self.height == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let x_coord = x.min(usize::from(self.width - 1));
+        let y_coord = y.min(usize::from(self.height - 1));
         self.pixels[y_coord][x_coord]
     }
 
-    pub fn to_ppm(&self) {
+    /// Plain text PPM (P3), colors are clamped to [0, 1]. PPM has no alpha, transparent
+    /// pixels keep their color
+    pub fn ppm(&self) -> String {
         let mut s = String::new();
         s.push_str("P3\n");
         s.push_str(&format!("{} {}\n", self.width, self.height));
@@ -39,14 +84,34 @@ impl Canvas {
         for row in self.pixels.iter() {
             for pixel in row.iter() {
                 s.push_str(&pixel.scale(255.0).to_string());
-                s.push_str(" ");
+                s.push(' ');
             }
-            s.push_str("\n");
+            s.push('\n');
+        }
+        s
+    }
+
+    pub fn to_ppm(&self, path: &str) -> Result<()> {
+        write_to_file(path, self.ppm().as_bytes())
+    }
+
+    pub fn to_png(&self, path: &str) -> Result<()> {
+        write_to_file(path, &encode_png(self))
+    }
+
+    /// Writes a PNG or a PPM depending on the extension of the path
+    pub fn save(&self, path: &str) -> Result<()> {
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("png") => self.to_png(path),
+            Some("ppm") => self.to_ppm(path),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} doesn't end in .png or .ppm", path),
+            )),
         }
-        match write_to_file("test.ppm", &s) {
-            Ok(_) => println!("File written successfully"),
-            Err(e) => println!("{:?}", e),
-        };
     }
 }
 
@@ -89,4 +154,39 @@ mod tests {
         assert_eq!(p.g, 0.5);
         assert_eq!(p.b, 0.3);
     }
+
+    #[test]
+    fn should_read_black_from_an_empty_canvas() {
+        let c = Canvas::new(0, 0);
+        assert_eq!(c.get_pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+        let c = Canvas::new(3, 0);
+        assert_eq!(c.get_pixel(2, 5), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_track_the_alpha_of_each_pixel() {
+        let mut c = Canvas::transparent(3, 2);
+        assert_eq!(c.get_alpha(1, 1), 0.0);
+        assert!(!c.is_opaque());
+        c.write_alpha(0.5, 1, 1);
+        assert_eq!(c.get_alpha(5, 5), 0.0);
+        assert_eq!(c.get_alpha(1, 1), 0.5);
+        c.write_pixel(&Color::new(1.0, 0.0, 0.0), 1, 1);
+        assert_eq!(c.get_alpha(1, 1), 1.0);
+        assert!(Canvas::new(3, 2).is_opaque());
+    }
+
+    #[test]
+    fn should_build_a_ppm() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(&Color::new(1.5, 0.0, 0.5), 0, 0);
+        assert_eq!(c.ppm(), "P3\n2 1\n255\n255 0 127 0 0 0 \n");
+    }
+
+    #[test]
+    fn should_refuse_unknown_extensions() {
+        let c = Canvas::new(1, 1);
+        let error = c.save("image.jpg").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
 }
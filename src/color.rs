@@ -1,34 +1,33 @@
+use crate::utils::{Float, EPSILON};
+use std::fmt;
 use std::ops::Add;
 use std::ops::Mul;
 use std::ops::Sub;
 
 #[derive(Debug, Copy)]
 pub struct Color {
-    pub r: f32,
-    pub g: f32,
-    pub b: f32,
+    pub r: Float,
+    pub g: Float,
+    pub b: Float,
 }
 
 impl Color {
-    pub fn new(r: f32, g: f32, b: f32) -> Self {
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
         Color { r, g, b }
     }
 
-    pub fn scale(&self, factor: f32) -> Color {
+    pub fn scale(&self, factor: Float) -> Color {
         Color {
             r: (self.r * factor).clamp(0.0, factor),
             g: (self.g * factor).clamp(0.0, factor),
             b: (self.b * factor).clamp(0.0, factor),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!(
-            "{} {} {}",
-            self.r as i64,
-            self.g as i64,
-            self.b as i64,
-        )
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.r as i64, self.g as i64, self.b as i64)
     }
 }
 
@@ -56,10 +55,10 @@ impl Sub<&Color> for &Color {
     }
 }
 
-impl Mul<f32> for &Color {
+impl Mul<Float> for &Color {
     type Output = Color;
 
-    fn mul(self, scalar: f32) -> Self::Output {
+    fn mul(self, scalar: Float) -> Self::Output {
         Color {
             r: self.r * scalar,
             g: self.g * scalar,
@@ -86,6 +85,14 @@ impl Clone for Color {
     }
 }
 
+impl PartialEq for Color {
+    fn eq(&self, other: &Color) -> bool {
+        (self.r - other.r).abs() < EPSILON
+            && (self.g - other.g).abs() < EPSILON
+            && (self.b - other.b).abs() < EPSILON
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
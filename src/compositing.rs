@@ -0,0 +1,466 @@
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    error::{RayTracerError, Result},
+    utils::consts::PI,
+    utils::{Float, EPSILON},
+};
+
+/// Porter-Duff operators, the source is what gets put on the destination
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Composite {
+    Clear,
+    Source,
+    Destination,
+    /// The usual layering, the source on top
+    SourceOver,
+    DestinationOver,
+    /// The source where the destination is, like a mask
+    SourceIn,
+    DestinationIn,
+    SourceOut,
+    DestinationOut,
+    SourceAtop,
+    DestinationAtop,
+    Xor,
+}
+
+#[allow(dead_code)]
+impl Composite {
+    /// How much of the source and of the destination is kept
+    fn factors(&self, source_alpha: Float, destination_alpha: Float) -> (Float, Float) {
+        match self {
+            Composite::Clear => (0.0, 0.0),
+            Composite::Source => (1.0, 0.0),
+            Composite::Destination => (0.0, 1.0),
+            Composite::SourceOver => (1.0, 1.0 - source_alpha),
+            Composite::DestinationOver => (1.0 - destination_alpha, 1.0),
+            Composite::SourceIn => (destination_alpha, 0.0),
+            Composite::DestinationIn => (0.0, source_alpha),
+            Composite::SourceOut => (1.0 - destination_alpha, 0.0),
+            Composite::DestinationOut => (0.0, 1.0 - source_alpha),
+            Composite::SourceAtop => (destination_alpha, 1.0 - source_alpha),
+            Composite::DestinationAtop => (1.0 - destination_alpha, source_alpha),
+            Composite::Xor => (1.0 - destination_alpha, 1.0 - source_alpha),
+        }
+    }
+
+    /// Color and alpha of one pixel, from the source and destination colors and alphas
+    pub fn apply(
+        &self,
+        source: &Color,
+        source_alpha: Float,
+        destination: &Color,
+        destination_alpha: Float,
+    ) -> (Color, Float) {
+        let (source_alpha, destination_alpha) = (
+            source_alpha.clamp(0.0, 1.0),
+            destination_alpha.clamp(0.0, 1.0),
+        );
+        let (from_source, from_destination) = self.factors(source_alpha, destination_alpha);
+        let (source_part, destination_part) = (
+            source_alpha * from_source,
+            destination_alpha * from_destination,
+        );
+        let alpha = source_part + destination_part;
+        if alpha < EPSILON {
+            return (Color::new(0.0, 0.0, 0.0), 0.0);
+        }
+        let color = &(source * source_part) + &(destination * destination_part);
+        (&color * (1.0 / alpha), alpha)
+    }
+}
+
+/// Filters for `Canvas::resize`
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Resampling {
+    /// Closest pixel, keeps hard edges
+    Nearest,
+    Bilinear,
+    /// Lanczos with 3 lobes, the sharpest of the three but it can ring around edges
+    Lanczos,
+}
+
+#[allow(dead_code)]
+impl Resampling {
+    /// Distance past which a source pixel doesn't count
+    fn support(&self) -> Float {
+        match self {
+            Resampling::Nearest => 0.5,
+            Resampling::Bilinear => 1.0,
+            Resampling::Lanczos => 3.0,
+        }
+    }
+
+    fn weight(&self, distance: Float) -> Float {
+        let distance = distance.abs();
+        match self {
+            Resampling::Nearest => 1.0,
+            Resampling::Bilinear => (1.0 - distance).max(0.0),
+            Resampling::Lanczos if distance < EPSILON => 1.0,
+            Resampling::Lanczos if distance < 3.0 => {
+                let x = PI * distance;
+                3.0 * x.sin() * (x / 3.0).sin() / (x * x)
+            }
+            Resampling::Lanczos => 0.0,
+        }
+    }
+
+    /// Source pixels and their weights for each target pixel, the weights add up to 1. When
+    /// shrinking the filter is stretched so every source pixel is taken into account
+    fn contributions(&self, source: usize, target: usize) -> Vec<Vec<(usize, Float)>> {
+        let scale = source as Float / target as Float;
+        (0..target)
+            .map(|t| {
+                let center = (t as Float + 0.5) * scale;
+                if *self == Resampling::Nearest {
+                    return vec![((center as usize).min(source - 1), 1.0)];
+                }
+                let stretch = scale.max(1.0);
+                let radius = self.support() * stretch;
+                let first = (center - 0.5 - radius).ceil() as i64;
+                let last = (center - 0.5 + radius).floor() as i64;
+                let mut weights: Vec<(usize, Float)> = (first..=last)
+                    .map(|s| {
+                        let distance = (s as Float + 0.5 - center) / stretch;
+                        (
+                            s.clamp(0, source as i64 - 1) as usize,
+                            self.weight(distance),
+                        )
+                    })
+                    .collect();
+                let total: Float = weights.iter().map(|(_, weight)| weight).sum();
+                for (_, weight) in weights.iter_mut() {
+                    *weight /= total;
+                }
+                weights
+            })
+            .collect()
+    }
+}
+
+/// Color multiplied by alpha, and alpha. Filters work on these so transparent pixels don't
+/// bleed their color into the opaque ones
+type Premultiplied = [Float; 4];
+
+#[allow(dead_code)]
+impl Canvas {
+    /// The part of the canvas starting at (x, y), panics if it goes past the edges
+    pub fn crop(&self, x: u16, y: u16, width: u16, height: u16) -> Canvas {
+        match self.try_crop(x, y, width, height) {
+            Ok(canvas) => canvas,
+            Err(error) => panic!("Canvas::crop {}", error),
+        }
+    }
+
+    pub fn try_crop(&self, x: u16, y: u16, width: u16, height: u16) -> Result<Canvas> {
+        if u32::from(x) + u32::from(width) > u32::from(self.width)
+            || u32::from(y) + u32::from(height) > u32::from(self.height)
+        {
+            return Err(RayTracerError::InvalidArgument(format!(
+                "a {}x{} crop at ({}, {}) doesn't fit in a {}x{} canvas",
+                width, height, x, y, self.width, self.height
+            )));
+        }
+        let (x, y) = (usize::from(x), usize::from(y));
+        let (w, h) = (usize::from(width), usize::from(height));
+        Ok(Canvas {
+            width,
+            height,
+            pixels: self.pixels[y..y + h]
+                .iter()
+                .map(|row| row[x..x + w].to_vec())
+                .collect(),
+            alpha: self.alpha[y..y + h]
+                .iter()
+                .map(|row| row[x..x + w].to_vec())
+                .collect(),
+        })
+    }
+
+    /// Resampled to a new size, one axis after the other. An empty canvas gives a
+    /// transparent one
+    pub fn resize(&self, width: u16, height: u16, filter: Resampling) -> Canvas {
+        let mut resized = Canvas::transparent(width, height);
+        if self.width == 0 || self.height == 0 || width == 0 || height == 0 {
+            return resized;
+        }
+        let source: Vec<Vec<Premultiplied>> = self
+            .pixels
+            .iter()
+            .zip(self.alpha.iter())
+            .map(|(row, alphas)| {
+                row.iter()
+                    .zip(alphas.iter())
+                    .map(|(c, a)| [c.r * a, c.g * a, c.b * a, *a])
+                    .collect()
+            })
+            .collect();
+        let blend = |samples: &mut dyn Iterator<Item = (&Premultiplied, Float)>| {
+            samples.fold([0.0; 4], |mut total, (sample, weight)| {
+                for (channel, value) in total.iter_mut().zip(sample.iter()) {
+                    *channel += value * weight;
+                }
+                total
+            })
+        };
+
+        let columns = filter.contributions(usize::from(self.width), usize::from(width));
+        let wide: Vec<Vec<Premultiplied>> = source
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|weights| blend(&mut weights.iter().map(|(s, w)| (&row[*s], *w))))
+                    .collect()
+            })
+            .collect();
+        let rows = filter.contributions(usize::from(self.height), usize::from(height));
+        for (y, weights) in rows.iter().enumerate() {
+            let pixels = resized.pixels[y]
+                .iter_mut()
+                .zip(resized.alpha[y].iter_mut());
+            for (x, (pixel, alpha)) in pixels.enumerate() {
+                let [r, g, b, a] = blend(&mut weights.iter().map(|(s, w)| (&wide[*s][x], *w)));
+                if a > EPSILON {
+                    *pixel = Color::new(r / a, g / a, b / a);
+                }
+                // the weights only add up to 1 within rounding, opaque should stay opaque
+                *alpha = if a > 1.0 - EPSILON { 1.0 } else { a.max(0.0) };
+            }
+        }
+        resized
+    }
+
+    /// Copies `source` with its top left corner at (x, y), alpha included. Whatever falls
+    /// outside of the canvas is dropped
+    pub fn blit(&mut self, source: &Canvas, x: i32, y: i32) {
+        self.composite(source, x, y, Composite::Source);
+    }
+
+    /// Combines `source` placed at (x, y) with the canvas. Only the pixels under the source
+    /// change, even for operators like `SourceIn` that would clear the rest
+    pub fn composite(&mut self, source: &Canvas, x: i32, y: i32, operator: Composite) {
+        for (row, (colors, alphas)) in source.pixels.iter().zip(source.alpha.iter()).enumerate() {
+            let target_y = y + row as i32;
+            if target_y < 0 || target_y >= i32::from(self.height) {
+                continue;
+            }
+            for (col, (color, alpha)) in colors.iter().zip(alphas.iter()).enumerate() {
+                let target_x = x + col as i32;
+                if target_x < 0 || target_x >= i32::from(self.width) {
+                    continue;
+                }
+                let (tx, ty) = (target_x as usize, target_y as usize);
+                let (color, alpha) =
+                    operator.apply(color, *alpha, &self.pixels[ty][tx], self.alpha[ty][tx]);
+                self.pixels[ty][tx] = color;
+                self.alpha[ty][tx] = alpha;
+            }
+        }
+    }
+
+    /// Grid of canvases in reading order, each centered in a cell as big as the largest one
+    /// with `spacing` pixels around the cells, panics if the sheet is larger than 65535
+    /// pixels on a side (see `try_contact_sheet`)
+    pub fn contact_sheet(
+        canvases: &[&Canvas],
+        columns: usize,
+        spacing: u16,
+        background: &Color,
+    ) -> Canvas {
+        match Canvas::try_contact_sheet(canvases, columns, spacing, background) {
+            Ok(canvas) => canvas,
+            Err(error) => panic!("Canvas::contact_sheet {}", error),
+        }
+    }
+
+    pub fn try_contact_sheet(
+        canvases: &[&Canvas],
+        columns: usize,
+        spacing: u16,
+        background: &Color,
+    ) -> Result<Canvas> {
+        let columns = columns.clamp(1, canvases.len().max(1));
+        let rows = canvases.len().div_ceil(columns);
+        let cell_width = usize::from(canvases.iter().map(|c| c.width).max().unwrap_or(0));
+        let cell_height = usize::from(canvases.iter().map(|c| c.height).max().unwrap_or(0));
+        let spacing = usize::from(spacing);
+        let size = |count: usize, cell: usize| {
+            count
+                .checked_mul(cell)
+                .zip((count + 1).checked_mul(spacing))
+                .and_then(|(cells, spaces)| cells.checked_add(spaces))
+                .and_then(|size| u16::try_from(size).ok())
+        };
+        let (width, height) = match (size(columns, cell_width), size(rows, cell_height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => {
+                return Err(RayTracerError::InvalidArgument(format!(
+                    "a sheet of {} canvases in {} columns is over {} pixels on a side",
+                    canvases.len(),
+                    columns,
+                    u16::MAX
+                )))
+            }
+        };
+        let mut sheet = Canvas::new(width, height);
+        for row in sheet.pixels.iter_mut() {
+            row.fill(*background);
+        }
+        // every cell is inside the sheet, so the offsets fit in an i32
+        for (i, canvas) in canvases.iter().enumerate() {
+            let (column, row) = (i % columns, i / columns);
+            let x = spacing
+                + column * (cell_width + spacing)
+                + (cell_width - usize::from(canvas.width)) / 2;
+            let y = spacing
+                + row * (cell_height + spacing)
+                + (cell_height - usize::from(canvas.height)) / 2;
+            sheet.composite(canvas, x as i32, y as i32, Composite::SourceOver);
+        }
+        Ok(sheet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u16, height: u16) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..usize::from(height) {
+            for x in 0..usize::from(width) {
+                let value = x as Float / width as Float;
+                canvas.write_pixel(&Color::new(value, 1.0 - value, 0.5), x, y);
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn should_apply_porter_duff_operators() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let (color, alpha) = Composite::SourceOver.apply(&red, 0.5, &blue, 1.0);
+        assert_eq!(color, Color::new(0.5, 0.0, 0.5));
+        assert_eq!(alpha, 1.0);
+        let (color, alpha) = Composite::SourceOver.apply(&red, 0.5, &blue, 0.5);
+        assert_eq!(alpha, 0.75);
+        assert_eq!(color, Color::new(2.0 / 3.0, 0.0, 1.0 / 3.0));
+        let (color, alpha) = Composite::SourceIn.apply(&red, 1.0, &blue, 0.25);
+        assert_eq!((color, alpha), (red, 0.25));
+        assert_eq!(Composite::SourceOut.apply(&red, 1.0, &blue, 1.0).1, 0.0);
+        assert_eq!(Composite::Xor.apply(&red, 1.0, &blue, 1.0).1, 0.0);
+        let (color, alpha) = Composite::DestinationAtop.apply(&red, 1.0, &blue, 0.5);
+        assert_eq!(alpha, 1.0);
+        assert_eq!(color, Color::new(0.5, 0.0, 0.5));
+    }
+
+    #[test]
+    fn should_crop_inside_the_canvas() {
+        let canvas = gradient(10, 5);
+        let cropped = canvas.crop(2, 1, 3, 4);
+        assert_eq!((cropped.width, cropped.height), (3, 4));
+        assert_eq!(cropped.get_pixel(0, 0), canvas.get_pixel(2, 1));
+        assert_eq!(cropped.pixels.len(), 4);
+        assert!(canvas.try_crop(8, 0, 3, 1).is_err());
+        assert!(canvas.try_crop(0, 0, 10, 5).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Canvas::crop")]
+    fn should_panic_on_crops_past_the_edges() {
+        gradient(4, 4).crop(0, 2, 4, 3);
+    }
+
+    #[test]
+    fn should_resize_with_each_filter() {
+        let canvas = gradient(8, 4);
+        for filter in [
+            Resampling::Nearest,
+            Resampling::Bilinear,
+            Resampling::Lanczos,
+        ] {
+            let larger = canvas.resize(16, 8, filter);
+            assert_eq!((larger.width, larger.height), (16, 8));
+            assert!(larger.is_opaque());
+            // the middle of a gradient stays in the middle
+            let middle = &(&larger.get_pixel(7, 4) + &larger.get_pixel(8, 4)) * 0.5;
+            assert!((middle.r - 0.5).abs() < 0.07, "{:?}", filter);
+            let smaller = canvas.resize(2, 1, filter);
+            assert!((smaller.get_pixel(0, 0).b - 0.5).abs() < 0.001);
+        }
+        let mut block = Canvas::new(2, 1);
+        block.write_pixel(&Color::new(1.0, 1.0, 1.0), 1, 0);
+        let nearest = block.resize(4, 1, Resampling::Nearest);
+        assert_eq!(nearest.get_pixel(1, 0).r, 0.0);
+        assert_eq!(nearest.get_pixel(2, 0).r, 1.0);
+        let bilinear = block.resize(4, 1, Resampling::Bilinear);
+        assert!((bilinear.get_pixel(1, 0).r - 0.25).abs() < EPSILON);
+        assert_eq!(
+            Canvas::new(0, 3).resize(2, 2, Resampling::Lanczos).alpha[0][0],
+            0.0
+        );
+    }
+
+    #[test]
+    fn should_not_bleed_transparent_colors_when_resizing() {
+        let mut canvas = Canvas::transparent(2, 1);
+        canvas.write_pixel(&Color::new(1.0, 0.0, 0.0), 0, 0);
+        canvas.pixels[0][1] = Color::new(0.0, 1.0, 0.0);
+        let resized = canvas.resize(1, 1, Resampling::Bilinear);
+        assert_eq!(resized.get_pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert!((resized.get_alpha(0, 0) - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_blit_and_composite_at_an_offset() {
+        let mut layer = Canvas::transparent(2, 2);
+        layer.write_pixel(&Color::new(1.0, 0.0, 0.0), 0, 0);
+        let mut target = Canvas::new(3, 3);
+        target.blit(&layer, 2, -1);
+        assert_eq!(target.get_pixel(2, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(target.get_alpha(2, 0), 0.0);
+        assert_eq!(target.get_alpha(1, 1), 1.0);
+        let mut target = Canvas::new(3, 3);
+        target.composite(&layer, 1, 1, Composite::SourceOver);
+        assert_eq!(target.get_pixel(1, 1), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(target.get_alpha(2, 2), 1.0);
+        assert!(target.is_opaque());
+    }
+
+    #[test]
+    fn should_lay_out_a_contact_sheet() {
+        let renders = [gradient(4, 4), gradient(2, 2), gradient(4, 4)];
+        let white = Color::new(1.0, 1.0, 1.0);
+        let sheet = Canvas::contact_sheet(&[&renders[0], &renders[1], &renders[2]], 2, 1, &white);
+        assert_eq!((sheet.width, sheet.height), (11, 11));
+        assert_eq!(sheet.get_pixel(0, 0), white);
+        assert_eq!(sheet.get_pixel(1, 1), renders[0].get_pixel(0, 0));
+        // the small one is centered in its cell
+        assert_eq!(sheet.get_pixel(6, 1), white);
+        assert_eq!(sheet.get_pixel(7, 2), renders[1].get_pixel(0, 0));
+        assert_eq!(sheet.get_pixel(1, 6), renders[2].get_pixel(0, 0));
+        assert_eq!(sheet.get_pixel(6, 6), white);
+    }
+    #[test]
+    fn should_reject_contact_sheets_larger_than_a_canvas() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let tall = Canvas::new(1, 40000);
+        let error = Canvas::try_contact_sheet(&[&tall, &tall], 1, 0, &white).unwrap_err();
+        assert!(matches!(error, RayTracerError::InvalidArgument(_)));
+        let wide = Canvas::new(u16::MAX, 1);
+        assert!(Canvas::try_contact_sheet(&[&wide], 1, 1, &white).is_err());
+        assert!(Canvas::try_contact_sheet(&[&wide], 1, 0, &white).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Canvas::contact_sheet")]
+    fn should_panic_on_contact_sheets_larger_than_a_canvas() {
+        let wide = Canvas::new(40000, 1);
+        Canvas::contact_sheet(&[&wide, &wide], 2, 0, &Color::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    intersection::Intersection,
+    object::Object,
+    point::Point,
+    ray::Ray,
+    utils::{Float, EPSILON},
+    vector::Vector,
+};
+
+/// Precomputed values of a hit used for shading
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Computations<'a> {
+    pub t: Float,
+    /// Time of the ray, shadow rays are cast at the same moment
+    pub time: Float,
+    pub object: &'a dyn Object,
+    pub point: Point,
+    /// Point slightly above the surface to avoid self shadowing
+    pub over_point: Point,
+    pub eyev: Vector,
+    pub normalv: Vector,
+    pub inside: bool,
+    /// Texture coordinates carried by the intersection
+    pub uv: Option<(Float, Float)>,
+}
+
+#[allow(dead_code)]
+impl<'a> Computations<'a> {
+    pub fn prepare(intersection: &Intersection<&'a dyn Object>, ray: &Ray) -> Self {
+        let point = ray.position(intersection.t);
+        let eyev = -&ray.direction;
+        let mut geometric = intersection.object.normal_at_time(&point, ray.time);
+        let inside = geometric.dot_product(&eyev) < 0.0;
+        let mut normalv = match &intersection.object.material().normal_map {
+            Some(map) => map.perturb(
+                intersection.object,
+                &point,
+                &geometric,
+                intersection.uv,
+                ray.time,
+            ),
+            None => geometric,
+        };
+        if inside {
+            geometric = -&geometric;
+            normalv = -&normalv;
+        }
+        // the offset follows the real surface, a bumped normal could push it back inside
+        let over_point = &point + &(&geometric * EPSILON);
+        Computations {
+            t: intersection.t,
+            time: ray.time,
+            object: intersection.object,
+            point,
+            over_point,
+            eyev,
+            normalv,
+            inside,
+            uv: intersection.uv,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bump::NormalMap, color::Color, matrix::Matrix, pattern::GradientPattern, sphere::Sphere,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn should_precompute_the_state_of_an_intersection() {
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        let intersection = Intersection::new(4.0, &sphere as &dyn Object);
+        let comps = Computations::prepare(&intersection, &ray);
+        assert_eq!(comps.t, 4.0);
+        assert_eq!(comps.point.z, -1.0);
+        assert_eq!(comps.eyev.z, -1.0);
+        assert_eq!(comps.normalv.z, -1.0);
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn should_flip_the_normal_when_the_hit_is_inside() {
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        let intersection = Intersection::new(1.0, &sphere as &dyn Object);
+        let comps = Computations::prepare(&intersection, &ray);
+        assert_eq!(comps.point.z, 1.0);
+        assert_eq!(comps.eyev.z, -1.0);
+        assert_eq!(comps.normalv.z, -1.0);
+        assert!(comps.inside);
+    }
+
+    #[test]
+    fn should_offset_the_over_point() {
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let sphere = Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::translation_3d(0.0, 0.0, 1.0)),
+        );
+        let intersection = Intersection::new(5.0, &sphere as &dyn Object);
+        let comps = Computations::prepare(&intersection, &ray);
+        assert!(comps.over_point.z < -EPSILON / 2.0);
+        assert!(comps.point.z > comps.over_point.z);
+    }
+
+    #[test]
+    fn should_shade_with_the_bumped_normal() {
+        let ray = Ray::new(Point::new(0.5, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material.normal_map = Some(NormalMap::Bump {
+            height: Arc::new(GradientPattern::new(
+                Color::new(0.0, 0.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+                Some(Matrix::scaling_3d(4.0, 4.0, 4.0)),
+            )),
+            scale: 4.0,
+        });
+        let t = 5.0 - (0.75 as Float).sqrt();
+        let intersection = Intersection::new(t, &sphere as &dyn Object);
+        let comps = Computations::prepare(&intersection, &ray);
+        let geometric = sphere.normal_at(&comps.point);
+        assert!(comps.normalv.x < geometric.x - 0.1);
+        assert!((comps.normalv.magnitude() - 1.0).abs() < EPSILON);
+        let expected = &comps.point + &(&geometric * EPSILON);
+        assert!((comps.over_point.x - expected.x).abs() < 1e-6);
+        assert!((comps.over_point.y - expected.y).abs() < 1e-6);
+    }
+}
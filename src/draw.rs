@@ -0,0 +1,428 @@
+use crate::{canvas::Canvas, color::Color, compositing::Composite, utils::Float};
+
+/// 2D drawing on the canvas. Coordinates are signed and can go past the edges, only the
+/// pixels inside get painted. `alpha` mixes the color over what is already there: 1 covers
+/// it and 0.5 is half and half
+#[allow(dead_code)]
+impl Canvas {
+    pub fn blend_pixel(&mut self, color: &Color, alpha: Float, x: i32, y: i32) {
+        if x < 0 || y < 0 || x >= i32::from(self.width) || y >= i32::from(self.height) {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let (blended, coverage) =
+            Composite::SourceOver.apply(color, alpha, &self.pixels[y][x], self.alpha[y][x]);
+        self.pixels[y][x] = blended;
+        self.alpha[y][x] = coverage;
+    }
+
+    /// One pixel wide line with Bresenham's algorithm, both ends included
+    pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: &Color, alpha: Float) {
+        for (x, y) in self.line_pixels(from, to) {
+            self.blend_pixel(color, alpha, x, y);
+        }
+    }
+
+    /// Anti-aliased line with Xiaolin Wu's algorithm, each pixel gets the part of the line
+    /// that covers it
+    pub fn draw_line_smooth(
+        &mut self,
+        from: (Float, Float),
+        to: (Float, Float),
+        color: &Color,
+        alpha: Float,
+    ) {
+        let (mut x0, mut y0) = from;
+        let (mut x1, mut y1) = to;
+        // walk along the longest axis, one pixel per step
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            (x0, y0, x1, y1) = (y0, x0, y1, x1);
+        }
+        if x0 > x1 {
+            (x0, y0, x1, y1) = (x1, y1, x0, y0);
+        }
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+        // only the columns on the canvas are walked, the ends can be far outside
+        let columns = if steep { self.height } else { self.width };
+        // the casts saturate, so pixels far outside stay outside
+        let mut plot = |x: Float, y: Float, coverage: Float| {
+            if steep {
+                self.blend_pixel(color, alpha * coverage, y as i32, x as i32);
+            } else {
+                self.blend_pixel(color, alpha * coverage, x as i32, y as i32);
+            }
+        };
+        let fract = |v: Float| v - v.floor();
+
+        // the ends only cover part of their column
+        let x_start = x0.round();
+        let y_start = y0 + gradient * (x_start - x0);
+        let gap = 1.0 - fract(x0 + 0.5);
+        plot(x_start, y_start.floor(), (1.0 - fract(y_start)) * gap);
+        plot(x_start, y_start.floor() + 1.0, fract(y_start) * gap);
+
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let gap = fract(x1 + 0.5);
+        plot(x_end, y_end.floor(), (1.0 - fract(y_end)) * gap);
+        plot(x_end, y_end.floor() + 1.0, fract(y_end) * gap);
+
+        let mut x = (x_start + 1.0).max(0.0);
+        let last = x_end.min(Float::from(columns));
+        while x < last {
+            let y = y_start + gradient * (x - x_start);
+            plot(x, y.floor(), 1.0 - fract(y));
+            plot(x, y.floor() + 1.0, fract(y));
+            x += 1.0;
+        }
+    }
+
+    /// Rectangle with its top left corner at (x, y), nothing for a width or height <= 0
+    pub fn fill_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        color: &Color,
+        alpha: Float,
+    ) {
+        let right = x.saturating_add(width).min(i32::from(self.width));
+        let bottom = y.saturating_add(height).min(i32::from(self.height));
+        for row in y.max(0)..bottom {
+            for col in x.max(0)..right {
+                self.blend_pixel(color, alpha, col, row);
+            }
+        }
+    }
+
+    /// Outline with the midpoint circle algorithm. Only the steps that land on the
+    /// canvas are computed, so huge circles cost as much as the canvas is large
+    pub fn draw_circle(&mut self, center: (i32, i32), radius: i32, color: &Color, alpha: Float) {
+        if radius < 0 {
+            return;
+        }
+        let (cx, cy) = (i64::from(center.0), i64::from(center.1));
+        let radius = i64::from(radius);
+        let (width, height) = (i64::from(self.width), i64::from(self.height));
+        // each step y paints the 8 points at (±x, ±y) and (±y, ±x) around the center
+        let mut steps: Vec<i64> = [(cy, height), (cx, width)]
+            .into_iter()
+            .flat_map(|(c, size)| [(-c, size - 1 - c), (c - (size - 1), c)])
+            .flat_map(|(low, high)| low.max(0)..=high.min(radius))
+            .collect();
+        steps.sort_unstable();
+        steps.dedup();
+        let mut pixels = vec![];
+        for y in steps {
+            let x = midpoint_x(radius, y);
+            if x < y {
+                continue;
+            }
+            for (dx, dy) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                if let Some(pixel) = self.canvas_pixel(cx + dx, cy + dy) {
+                    pixels.push(pixel);
+                }
+            }
+        }
+        self.blend_pixels(pixels, color, alpha);
+    }
+
+    /// Every pixel whose center is at most `radius` away from the center
+    pub fn fill_circle(&mut self, center: (i32, i32), radius: i32, color: &Color, alpha: Float) {
+        if radius < 0 {
+            return;
+        }
+        let (cx, cy) = (i64::from(center.0), i64::from(center.1));
+        let radius = i64::from(radius);
+        let top = (cy - radius).max(0);
+        let bottom = (cy + radius).min(i64::from(self.height) - 1);
+        for y in top..=bottom {
+            let dy = y - cy;
+            let half = (radius * radius - dy * dy).isqrt();
+            let left = (cx - half).max(0);
+            let right = (cx + half).min(i64::from(self.width) - 1);
+            for x in left..=right {
+                self.blend_pixel(color, alpha, x as i32, y as i32);
+            }
+        }
+    }
+
+    /// Lines between the points, closed back to the first one
+    pub fn draw_polygon(&mut self, points: &[(i32, i32)], color: &Color, alpha: Float) {
+        let mut pixels = vec![];
+        for (i, from) in points.iter().enumerate() {
+            let to = points[(i + 1) % points.len()];
+            pixels.append(&mut self.line_pixels(*from, to));
+        }
+        self.blend_pixels(pixels, color, alpha);
+    }
+
+    /// Inside of the polygon with the even-odd rule, a pixel is painted when its center is
+    /// inside
+    pub fn fill_polygon(&mut self, points: &[(i32, i32)], color: &Color, alpha: Float) {
+        if points.len() < 3 {
+            return;
+        }
+        let top = points.iter().map(|p| p.1).min().unwrap_or(0).max(0);
+        let bottom = points
+            .iter()
+            .map(|p| p.1)
+            .max()
+            .unwrap_or(0)
+            .min(i32::from(self.height) - 1);
+        for row in top..=bottom {
+            let center = row as Float + 0.5;
+            let mut crossings: Vec<Float> = vec![];
+            for (i, from) in points.iter().enumerate() {
+                let to = points[(i + 1) % points.len()];
+                let (y0, y1) = (from.1 as Float, to.1 as Float);
+                // half open so a vertex on the scanline is counted once
+                if (y0 <= center) != (y1 <= center) {
+                    let t = (center - y0) / (y1 - y0);
+                    let dx = i64::from(to.0) - i64::from(from.0);
+                    crossings.push(from.0 as Float + t * dx as Float);
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil() as i32;
+                let end = (span[1] - 0.5).ceil() as i32;
+                self.fill_rect(start, row, end.saturating_sub(start), 1, color, alpha);
+            }
+        }
+    }
+
+    /// Blends each pixel once even if it's listed several times, so shapes made of
+    /// overlapping pieces don't get darker where they meet
+    fn blend_pixels(&mut self, mut pixels: Vec<(i32, i32)>, color: &Color, alpha: Float) {
+        pixels.sort_unstable();
+        pixels.dedup();
+        for (x, y) in pixels {
+            self.blend_pixel(color, alpha, x, y);
+        }
+    }
+
+    fn canvas_pixel(&self, x: i64, y: i64) -> Option<(i32, i32)> {
+        let inside =
+            (0..i64::from(self.width)).contains(&x) && (0..i64::from(self.height)).contains(&y);
+        inside.then_some((x as i32, y as i32))
+    }
+
+    /// Pixels of the Bresenham line that land on the canvas, only the steps along the
+    /// long axis inside the canvas are walked so lines from far outside cost nothing.
+    /// Step k moves the short axis by k * short / long rounded half up, the same pixels
+    /// as the incremental version
+    fn line_pixels(&self, from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+        let (x0, y0) = (i64::from(from.0), i64::from(from.1));
+        let (dx, dy) = (i64::from(to.0) - x0, i64::from(to.1) - y0);
+        // (along, across) are the long and short axes
+        let x_major = dx.abs() >= dy.abs();
+        let ((along, long, size), (across, short)) = if x_major {
+            ((x0, dx, self.width), (y0, dy))
+        } else {
+            ((y0, dy, self.height), (x0, dx))
+        };
+        let size = i64::from(size);
+        let (first, last) = if long >= 0 {
+            (-along, size - 1 - along)
+        } else {
+            (along - (size - 1), along)
+        };
+        let mut pixels = vec![];
+        for k in first.max(0)..=last.min(long.abs()) {
+            // i128 as 2 * k * short goes past i64 for lines billions of pixels long
+            let moved = match long.abs() {
+                0 => 0,
+                length => {
+                    ((2 * i128::from(k) * i128::from(short.abs()) + i128::from(length))
+                        / (2 * i128::from(length))) as i64
+                }
+            };
+            let (a, b) = (along + long.signum() * k, across + short.signum() * moved);
+            let (x, y) = if x_major { (a, b) } else { (b, a) };
+            if let Some(pixel) = self.canvas_pixel(x, y) {
+                pixels.push(pixel);
+            }
+        }
+        pixels
+    }
+}
+
+/// The `x` of the midpoint circle algorithm at step `y`, the closest to the circle.
+/// Same as the incremental version with `x` decreasing while `y` goes up
+fn midpoint_x(radius: i64, y: i64) -> i64 {
+    let squared = radius * radius - y * y;
+    let x = squared.isqrt();
+    if x * x + x < squared {
+        x + 1
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn painted(canvas: &Canvas) -> Vec<(usize, usize)> {
+        let mut pixels = vec![];
+        for (y, row) in canvas.pixels.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                if pixel.r > 0.0 {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn should_blend_with_the_existing_color() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(&Color::new(0.0, 0.0, 1.0), 0, 0);
+        canvas.blend_pixel(&Color::new(1.0, 0.0, 0.0), 0.25, 0, 0);
+        assert_eq!(canvas.get_pixel(0, 0), Color::new(0.25, 0.0, 0.75));
+        canvas.blend_pixel(&Color::new(1.0, 1.0, 1.0), 1.0, -1, 0);
+        canvas.blend_pixel(&Color::new(1.0, 1.0, 1.0), 1.0, 2, 0);
+        assert_eq!(canvas.get_pixel(1, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_draw_lines_in_every_direction() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut canvas = Canvas::new(5, 5);
+        canvas.draw_line((4, 4), (0, 0), &red, 1.0);
+        assert_eq!(
+            painted(&canvas),
+            vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 4)]
+        );
+        let mut canvas = Canvas::new(5, 5);
+        canvas.draw_line((0, 1), (4, 2), &red, 1.0);
+        assert_eq!(painted(&canvas).len(), 5);
+        // mostly off the canvas, only the visible part is drawn
+        let mut canvas = Canvas::new(5, 5);
+        canvas.draw_line((-10, 2), (2, 2), &red, 1.0);
+        assert_eq!(painted(&canvas), vec![(0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn should_clip_huge_lines_to_the_canvas() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut canvas = Canvas::new(5, 3);
+        canvas.draw_line((-1_000_000_000, 1), (1_000_000_000, 1), &red, 1.0);
+        assert_eq!(
+            painted(&canvas),
+            vec![(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]
+        );
+        let mut canvas = Canvas::new(3, 3);
+        canvas.draw_line((i32::MIN, i32::MIN), (i32::MAX, i32::MAX), &red, 1.0);
+        assert_eq!(painted(&canvas), vec![(0, 0), (1, 1), (2, 2)]);
+        let mut canvas = Canvas::new(5, 5);
+        canvas.draw_line((-1_000_000_000, -5), (1_000_000_000, -5), &red, 1.0);
+        canvas.draw_polygon(
+            &[
+                (-2_000_000_000, 10),
+                (0, 2_000_000_000),
+                (2_000_000_000, 10),
+            ],
+            &red,
+            1.0,
+        );
+        canvas.draw_line_smooth((-1e9, -5.0), (1e9, -5.0), &red, 1.0);
+        assert!(painted(&canvas).is_empty());
+    }
+
+    #[test]
+    fn should_spread_smooth_lines_over_two_pixels() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.draw_line_smooth((1.0, 1.0), (8.0, 4.5), &Color::new(1.0, 1.0, 1.0), 1.0);
+        for x in 2..8 {
+            let column: Float = (0..10).map(|y| canvas.get_pixel(x, y).r).sum();
+            assert!((column - 1.0).abs() < 0.001, "column {} got {}", x, column);
+        }
+        let mut straight = Canvas::new(10, 3);
+        straight.draw_line_smooth((0.0, 1.0), (9.0, 1.0), &Color::new(1.0, 1.0, 1.0), 1.0);
+        assert_eq!(straight.get_pixel(5, 1), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(straight.get_pixel(5, 2), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_clip_rectangles() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.fill_rect(-2, 2, 4, 10, &Color::new(1.0, 0.0, 0.0), 1.0);
+        assert_eq!(painted(&canvas), vec![(0, 2), (1, 2), (0, 3), (1, 3)]);
+        canvas.fill_rect(1, 1, 0, 5, &Color::new(0.0, 1.0, 0.0), 1.0);
+        assert_eq!(canvas.get_pixel(1, 1), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_draw_circles_once_per_pixel() {
+        let mut canvas = Canvas::new(11, 11);
+        canvas.draw_circle((5, 5), 4, &Color::new(1.0, 0.0, 0.0), 0.5);
+        let pixels = painted(&canvas);
+        assert!(pixels.contains(&(9, 5)) && pixels.contains(&(5, 1)));
+        assert!(!pixels.contains(&(5, 5)));
+        assert!(pixels
+            .iter()
+            .all(|(x, y)| canvas.get_pixel(*x, *y).r == 0.5));
+        let mut filled = Canvas::new(11, 11);
+        filled.fill_circle((5, 5), 4, &Color::new(1.0, 0.0, 0.0), 1.0);
+        // close to PI * r^2
+        assert!((painted(&filled).len() as i32 - 50).abs() <= 2);
+    }
+
+    #[test]
+    fn should_only_draw_the_visible_part_of_huge_circles() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut canvas = Canvas::new(5, 5);
+        canvas.draw_circle((-1_000_000_000, 0), 100, &red, 1.0);
+        canvas.fill_circle((-1_000_000_000, 0), 100, &red, 1.0);
+        // the whole canvas is inside the outline
+        canvas.draw_circle((2, 2), i32::MAX, &red, 1.0);
+        assert!(painted(&canvas).is_empty());
+
+        canvas.fill_circle((2, 2), i32::MAX, &red, 1.0);
+        assert_eq!(painted(&canvas).len(), 25);
+
+        // so large that the outline is a straight line through the canvas
+        let mut canvas = Canvas::new(5, 5);
+        canvas.draw_circle((-1_000_000_000, 2), 1_000_000_002, &red, 1.0);
+        assert_eq!(
+            painted(&canvas),
+            vec![(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)]
+        );
+    }
+
+    #[test]
+    fn should_fill_polygons() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.fill_polygon(
+            &[(0, 0), (8, 0), (8, 8), (0, 8)],
+            &Color::new(1.0, 0.0, 0.0),
+            1.0,
+        );
+        assert_eq!(painted(&canvas).len(), 64);
+        let mut triangle = Canvas::new(10, 10);
+        triangle.fill_polygon(&[(0, 0), (8, 0), (0, 8)], &Color::new(1.0, 0.0, 0.0), 1.0);
+        assert!((painted(&triangle).len() as i32 - 32).abs() <= 4);
+        let mut outline = Canvas::new(10, 10);
+        outline.draw_polygon(&[(1, 1), (6, 1), (6, 6)], &Color::new(1.0, 0.0, 0.0), 0.5);
+        let pixels = painted(&outline);
+        assert!(pixels
+            .iter()
+            .all(|(x, y)| outline.get_pixel(*x, *y).r == 0.5));
+        assert!(pixels.contains(&(3, 3)));
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+/// Everything the `try_` constructors and operations can reject
+#[derive(Debug, Clone, PartialEq)]
+pub enum RayTracerError {
+    /// The determinant is 0 (or not a number), so there is no inverse
+    SingularMatrix,
+    /// Determinants and inverses only exist for square matrices
+    NotSquare { rows: usize, cols: usize },
+    /// A matrix was given values with another shape, as (rows, cols)
+    DimensionMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// The transform of an object can't be inverted, rays couldn't be brought to its space
+    NonInvertibleTransform { object: &'static str },
+    /// An argument outside of what the constructor accepts
+    InvalidArgument(String),
+}
+
+impl fmt::Display for RayTracerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RayTracerError::SingularMatrix => write!(f, "the matrix doesn't have an inverse"),
+            RayTracerError::NotSquare { rows, cols } => {
+                write!(f, "the matrix isn't square ({}x{})", rows, cols)
+            }
+            RayTracerError::DimensionMismatch { expected, found } => write!(
+                f,
+                "expected a {}x{} matrix, found {}x{}",
+                expected.0, expected.1, found.0, found.1
+            ),
+            RayTracerError::NonInvertibleTransform { object } => {
+                write!(f, "the transform of the {} can't be inverted", object)
+            }
+            RayTracerError::InvalidArgument(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RayTracerError {}
+
+pub type Result<T> = std::result::Result<T, RayTracerError>;
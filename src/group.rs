@@ -0,0 +1,303 @@
+use crate::{
+    bounds::Bounds,
+    bvh::{Bvh, BvhStats, SplitMethod},
+    error::{RayTracerError, Result},
+    intersection::{sort_intersections, Intersection},
+    material::Material,
+    matrix::Matrix,
+    object::Object,
+    point::Point,
+    ray::Ray,
+    triangle::Triangle,
+    vector::Vector,
+};
+
+/// Collection of objects that are transformed together.
+/// Children are stored with the group transform already applied, so the group itself
+/// always lives in the space of its parent and its own transform stays the identity.
+#[derive(Debug)]
+pub struct Group {
+    children: Vec<Box<dyn Object>>,
+    transform: Matrix,
+    group_transform: Matrix,
+    material: Material,
+    bvh: Option<Bvh>,
+    /// Bounds of the children, kept up to date so rays can skip the group cheaply
+    bounds: Bounds,
+}
+
+#[allow(dead_code)]
+impl Group {
+    pub fn new(transform: Option<Matrix>) -> Self {
+        match Group::try_new(transform) {
+            Ok(group) => group,
+            Err(error) => panic!("Group::new {}", error),
+        }
+    }
+
+    /// Rejects transforms without an inverse, the children couldn't be intersected
+    pub fn try_new(transform: Option<Matrix>) -> Result<Self> {
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        if !t.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "group" });
+        }
+        Ok(Group {
+            children: vec![],
+            transform: Matrix::identity(4),
+            group_transform: t,
+            material: Material::default(),
+            bvh: None,
+            bounds: Bounds::empty(),
+        })
+    }
+
+    /// Triangle mesh from a list of vertices and faces indexing into it, every triangle
+    /// shares the material so the whole mesh can be turned into a light
+    pub fn mesh(
+        vertices: &[Point],
+        faces: &[[usize; 3]],
+        material: Material,
+        transform: Option<Matrix>,
+    ) -> Self {
+        match Group::try_mesh(vertices, faces, material, transform) {
+            Ok(group) => group,
+            Err(error) => panic!("Group::mesh {}", error),
+        }
+    }
+
+    /// Rejects faces referencing missing vertices or with their vertices on a line, and
+    /// transforms without an inverse
+    pub fn try_mesh(
+        vertices: &[Point],
+        faces: &[[usize; 3]],
+        material: Material,
+        transform: Option<Matrix>,
+    ) -> Result<Self> {
+        let mut group = Group::try_new(transform)?;
+        for face in faces.iter() {
+            if face.iter().any(|index| *index >= vertices.len()) {
+                return Err(RayTracerError::InvalidArgument(format!(
+                    "face {:?} references a missing vertex",
+                    face
+                )));
+            }
+            let mut triangle = Triangle::try_new(
+                vertices[face[0]],
+                vertices[face[1]],
+                vertices[face[2]],
+                None,
+            )?;
+            triangle.material = material.clone();
+            group.add_child(Box::new(triangle));
+        }
+        Ok(group)
+    }
+
+    pub fn add_child(&mut self, mut child: Box<dyn Object>) {
+        child.apply_transform(&self.group_transform);
+        self.bounds = self.bounds.merge(&child.parent_space_bounds());
+        self.children.push(child);
+        self.bvh = None;
+    }
+
+    /// Subdivides the children, it has to be called again after adding new ones
+    pub fn build_bvh(&mut self, method: SplitMethod) -> BvhStats {
+        let bvh = Bvh::build(&self.children, method);
+        let stats = bvh.stats;
+        self.bvh = Some(bvh);
+        stats
+    }
+
+    pub fn bvh_stats(&self) -> Option<BvhStats> {
+        self.bvh.as_ref().map(|bvh| bvh.stats)
+    }
+}
+
+impl Object for Group {
+    fn normal_at(&self, _point: &Point) -> Vector {
+        panic!("Group::normal_at the normal must be computed on the children");
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object>> {
+        if let Some(bvh) = &self.bvh {
+            return bvh.intersect(&self.children, ray);
+        }
+        if !self.bounds.intersects(ray) {
+            return vec![];
+        }
+        let mut intersections = vec![];
+        for child in self.children.iter() {
+            intersections.append(&mut child.intersect(ray));
+        }
+        sort_intersections(&mut intersections);
+        intersections
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    /// Shading always uses the material of the child that was hit
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn apply_transform(&mut self, matrix: &Matrix) {
+        self.group_transform = matrix * &self.group_transform;
+        for child in self.children.iter_mut() {
+            child.apply_transform(matrix);
+        }
+        self.bounds = self.children.iter().fold(Bounds::empty(), |acc, child| {
+            acc.merge(&child.parent_space_bounds())
+        });
+        // the children moved together, the same tree still splits them well
+        if let Some(bvh) = &mut self.bvh {
+            bvh.refit(&self.children);
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn children(&self) -> &[Box<dyn Object>] {
+        &self.children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sphere::Sphere, utils::Float};
+
+    #[test]
+    fn should_create_an_empty_group() {
+        let group = Group::new(None);
+        assert_eq!(group.children().len(), 0);
+        assert!(group.bounds().is_empty());
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(group.intersect(&ray).len(), 0);
+    }
+
+    #[test]
+    fn should_apply_the_group_transform_to_children() {
+        let mut group = Group::new(Some(Matrix::scaling_3d(2.0, 2.0, 2.0)));
+        group.add_child(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::translation_3d(5.0, 0.0, 0.0)),
+        )));
+        let ray = Ray::new(Point::new(10.0, 0.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = group.intersect(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].t, 8.0);
+        assert_eq!(intersections[1].t, 12.0);
+
+        let bounds = group.bounds();
+        assert_eq!(bounds.min.x, 8.0);
+        assert_eq!(bounds.max.x, 12.0);
+    }
+
+    #[test]
+    fn should_apply_nested_group_transforms() {
+        let mut inner = Group::new(Some(Matrix::scaling_3d(2.0, 2.0, 2.0)));
+        inner.add_child(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::translation_3d(5.0, 0.0, 0.0)),
+        )));
+        let mut outer = Group::new(Some(Matrix::translation_3d(0.0, 0.0, 10.0)));
+        outer.add_child(Box::new(inner));
+        let ray = Ray::new(Point::new(10.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = outer.intersect(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].t, 8.0);
+        assert_eq!(intersections[1].t, 12.0);
+    }
+
+    #[test]
+    fn should_intersect_through_the_bvh() {
+        let mut group = Group::new(None);
+        for i in 0..20 {
+            group.add_child(Box::new(Sphere::new(
+                Point::new(0.0, 0.0, 0.0),
+                1.0,
+                Some(Matrix::translation_3d(0.0, i as Float * 3.0, 0.0)),
+            )));
+        }
+        assert!(group.bvh_stats().is_none());
+        let stats = group.build_bvh(SplitMethod::Sah);
+        assert_eq!(stats.object_count, 20);
+        assert_eq!(group.bvh_stats(), Some(stats));
+
+        let ray = Ray::new(Point::new(0.0, 30.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = group.intersect(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].t, 4.0);
+
+        group.add_child(Box::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None)));
+        assert!(group.bvh_stats().is_none());
+    }
+
+    #[test]
+    fn should_keep_the_bvh_when_moved_by_a_parent() {
+        let mut group = Group::new(None);
+        for i in 0..20 {
+            group.add_child(Box::new(Sphere::new(
+                Point::new(0.0, 0.0, 0.0),
+                1.0,
+                Some(Matrix::translation_3d(0.0, i as Float * 3.0, 0.0)),
+            )));
+        }
+        let stats = group.build_bvh(SplitMethod::Sah);
+        // what a parent group does when the group is added to it
+        group.apply_transform(&Matrix::translation_3d(10.0, 0.0, 0.0));
+        assert_eq!(group.bvh_stats(), Some(stats));
+        assert_eq!(group.bounds().min.x, 9.0);
+        assert_eq!(group.bounds().max.y, 58.0);
+        let ray = Ray::new(Point::new(10.0, 30.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = group.intersect(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].t, 4.0);
+        let ray = Ray::new(Point::new(0.0, 30.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(group.intersect(&ray).len(), 0);
+    }
+
+    #[test]
+    fn should_build_a_mesh_of_triangles() {
+        let vertices = [
+            Point::new(-1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, 1.0),
+            Point::new(-1.0, 0.0, 1.0),
+        ];
+        let group = Group::mesh(
+            &vertices,
+            &[[0, 1, 2], [0, 2, 3]],
+            Material::default(),
+            Some(Matrix::translation_3d(0.0, 2.0, 0.0)),
+        );
+        assert_eq!(group.children().len(), 2);
+        let bounds = group.bounds();
+        assert_eq!(bounds.min.y, 2.0);
+        assert_eq!(bounds.max.x, 1.0);
+        let ray = Ray::new(Point::new(0.5, 0.0, -0.5), Vector::new(0.0, 1.0, 0.0));
+        let intersections = group.intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].t, 2.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Group::mesh face [0, 1, 4] references a missing vertex")]
+    fn should_panic_on_faces_outside_the_vertices() {
+        let vertices = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        Group::mesh(&vertices, &[[0, 1, 4]], Material::default(), None);
+    }
+}
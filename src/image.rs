@@ -0,0 +1,387 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+};
+
+use crate::{canvas::Canvas, color::Color, utils::Float};
+
+/// Reads a PPM (P3 or P6) or a Radiance HDR file, the format is picked from the content
+#[allow(dead_code)]
+pub fn load_image(path: &str) -> Result<Canvas> {
+    let data = fs::read(path)?;
+    if data.starts_with(b"P3") || data.starts_with(b"P6") {
+        parse_ppm(&data)
+    } else if data.starts_with(b"#?") {
+        parse_hdr(&data)
+    } else {
+        Err(invalid(&format!(
+            "{} is neither a PPM nor a Radiance HDR image",
+            path
+        )))
+    }
+}
+
+/// Colors are scaled to [0, 1] by the maximum value of the header
+pub fn parse_ppm(data: &[u8]) -> Result<Canvas> {
+    let mut reader = PpmReader { data, position: 0 };
+    let magic = reader.token()?;
+    if magic != "P3" && magic != "P6" {
+        return Err(invalid(&format!("unknown PPM magic number {}", magic)));
+    }
+    let width = reader.number()?;
+    let height = reader.number()?;
+    let max_value = reader.number()?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid(&format!("invalid PPM maximum value {}", max_value)));
+    }
+    check_size(width, height)?;
+    let scale = 1.0 / max_value as Float;
+
+    if magic == "P3" {
+        // the rows are read one at a time, a header that is larger than the data fails
+        // before the whole canvas is allocated
+        let mut pixels = Vec::new();
+        for _ in 0..height {
+            let mut row = Vec::with_capacity(width);
+            for _ in 0..width {
+                let r = reader.number()? as Float * scale;
+                let g = reader.number()? as Float * scale;
+                let b = reader.number()? as Float * scale;
+                row.push(Color::new(r, g, b));
+            }
+            pixels.push(row);
+        }
+        return Ok(into_canvas(pixels, width, height));
+    }
+
+    // a single whitespace separates the header from the binary samples
+    reader.position += 1;
+    let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+    let expected = width * height * 3 * bytes_per_sample;
+    let samples = data
+        .get(reader.position..)
+        .and_then(|rest| rest.get(..expected))
+        .ok_or_else(|| invalid("PPM pixel data is truncated"))?;
+    let sample = |index: usize| {
+        let value = if bytes_per_sample == 1 {
+            samples[index] as u32
+        } else {
+            u16::from_be_bytes([samples[index * 2], samples[index * 2 + 1]]) as u32
+        };
+        value as Float * scale
+    };
+    let pixels = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let index = (y * width + x) * 3;
+                    Color::new(sample(index), sample(index + 1), sample(index + 2))
+                })
+                .collect()
+        })
+        .collect();
+    Ok(into_canvas(pixels, width, height))
+}
+
+/// Radiance RGBE, both flat and run length encoded scanlines, only the usual -Y +X
+/// orientation is supported
+pub fn parse_hdr(data: &[u8]) -> Result<Canvas> {
+    let mut position = 0;
+    let mut next_line = || -> Result<String> {
+        let rest = data
+            .get(position..)
+            .filter(|rest| !rest.is_empty())
+            .ok_or_else(|| invalid("HDR header is truncated"))?;
+        let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        position += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).trim().to_string())
+    };
+
+    let magic = next_line()?;
+    if !magic.starts_with("#?") {
+        return Err(invalid("missing HDR magic number"));
+    }
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid(&format!("unsupported HDR format {}", format)));
+            }
+        }
+    }
+    let resolution = next_line()?;
+    let parts: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match parts.as_slice() {
+        ["-Y", height, "+X", width] => (parse_size(height)?, parse_size(width)?),
+        _ => {
+            return Err(invalid(&format!(
+                "unsupported HDR resolution {}",
+                resolution
+            )))
+        }
+    };
+
+    check_size(width, height)?;
+    let mut data = &data[position.min(data.len())..];
+    let mut scanline = vec![[0u8; 4]; width];
+    let mut pixels = Vec::new();
+    for _ in 0..height {
+        data = read_scanline(data, &mut scanline)?;
+        pixels.push(scanline.iter().map(rgbe_to_color).collect());
+    }
+    Ok(into_canvas(pixels, width, height))
+}
+
+fn read_scanline<'a>(data: &'a [u8], scanline: &mut [[u8; 4]]) -> Result<&'a [u8]> {
+    let width = scanline.len();
+    let truncated = || invalid("HDR pixel data is truncated");
+    let is_rle = (8..32768).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && data[2] & 0x80 == 0;
+    if !is_rle {
+        let bytes = data.get(..width * 4).ok_or_else(truncated)?;
+        for (x, pixel) in scanline.iter_mut().enumerate() {
+            pixel.copy_from_slice(&bytes[x * 4..x * 4 + 4]);
+        }
+        return Ok(&data[width * 4..]);
+    }
+
+    if ((data[2] as usize) << 8 | data[3] as usize) != width {
+        return Err(invalid("HDR scanline width doesn't match the image"));
+    }
+    let mut position = 4;
+    // each channel is stored separately, as runs of a repeated byte or literal bytes
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(position).ok_or_else(truncated)? as usize;
+            position += 1;
+            if count > 128 {
+                let count = count - 128;
+                let value = *data.get(position).ok_or_else(truncated)?;
+                position += 1;
+                if x + count > width {
+                    return Err(invalid("HDR run overflows the scanline"));
+                }
+                for pixel in scanline[x..x + count].iter_mut() {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid("HDR run overflows the scanline"));
+                }
+                let values = data.get(position..position + count).ok_or_else(truncated)?;
+                position += count;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = *value;
+                }
+                x += count;
+            }
+        }
+    }
+    Ok(&data[position..])
+}
+
+fn rgbe_to_color(rgbe: &[u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let factor = (2.0 as Float).powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        rgbe[0] as Float * factor,
+        rgbe[1] as Float * factor,
+        rgbe[2] as Float * factor,
+    )
+}
+
+/// Textures and backgrounds can't sample an empty image, and the canvas is limited to u16
+fn check_size(width: usize, height: usize) -> Result<()> {
+    if width == 0 || height == 0 {
+        return Err(invalid("the image can't be empty"));
+    }
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(invalid(&format!(
+            "image of {}x{} is too large",
+            width, height
+        )));
+    }
+    Ok(())
+}
+
+/// The size has been checked and the rows have all been read
+fn into_canvas(pixels: Vec<Vec<Color>>, width: usize, height: usize) -> Canvas {
+    Canvas {
+        width: width as u16,
+        height: height as u16,
+        pixels,
+        alpha: vec![vec![1.0; width]; height],
+    }
+}
+
+fn parse_size(value: &str) -> Result<usize> {
+    value
+        .parse()
+        .map_err(|_| invalid(&format!("invalid image size {}", value)))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// Whitespace separated tokens of a PPM header (or P3 body), comments start with #
+struct PpmReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl PpmReader<'_> {
+    fn token(&mut self) -> Result<String> {
+        loop {
+            match self.data.get(self.position) {
+                Some(b'#') => {
+                    while let Some(byte) = self.data.get(self.position) {
+                        if *byte == b'\n' {
+                            break;
+                        }
+                        self.position += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => self.position += 1,
+                Some(_) => break,
+                None => return Err(invalid("PPM data is truncated")),
+            }
+        }
+        let start = self.position;
+        while let Some(byte) = self.data.get(self.position) {
+            if byte.is_ascii_whitespace() {
+                break;
+            }
+            self.position += 1;
+        }
+        Ok(String::from_utf8_lossy(&self.data[start..self.position]).to_string())
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| invalid(&format!("invalid PPM value {}", token)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_a_plain_ppm() {
+        let data = b"P3\n# made by hand\n4 3\n255\n\
+            255 127 0  0 0 0  0 0 0  0 0 0\n\
+            0 0 0  0 0 0  0 0 0  0 0 0\n\
+            0 0 0  0 0 0  0 0 0  0 0 255\n";
+        let canvas = parse_ppm(data).unwrap();
+        assert_eq!(canvas.width, 4);
+        assert_eq!(canvas.height, 3);
+        assert_eq!(canvas.get_pixel(0, 0), Color::new(1.0, 127.0 / 255.0, 0.0));
+        assert_eq!(canvas.get_pixel(3, 2), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn should_scale_by_the_maximum_value() {
+        let data = b"P3\n2 1\n100\n100 100 100  50 50 50";
+        let canvas = parse_ppm(data).unwrap();
+        assert_eq!(canvas.get_pixel(0, 0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(canvas.get_pixel(1, 0), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn should_read_a_binary_ppm() {
+        let mut data = b"P6 2 1 255\n".to_vec();
+        data.extend_from_slice(&[255, 0, 0, 0, 51, 255]);
+        let canvas = parse_ppm(&data).unwrap();
+        assert_eq!(canvas.get_pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(canvas.get_pixel(1, 0), Color::new(0.0, 0.2, 1.0));
+
+        let mut data = b"P6 1 1 65535\n".to_vec();
+        data.extend_from_slice(&[255, 255, 0, 0, 127, 255]);
+        let canvas = parse_ppm(&data).unwrap();
+        assert_eq!(
+            canvas.get_pixel(0, 0),
+            Color::new(1.0, 0.0, 32767.0 / 65535.0)
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_ppm() {
+        assert!(parse_ppm(b"P32\n1 1\n255\n0 0 0").is_err());
+        assert!(parse_ppm(b"P3\n1 1\n255\n0 0").is_err());
+        assert!(parse_ppm(b"P3\n1 1\n0\n0 0 0").is_err());
+        assert!(parse_ppm(b"P6 2 1 255\n\x00\x00").is_err());
+        let error = parse_ppm(b"P3\n1 x\n255\n").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "invalid PPM value x");
+    }
+
+    #[test]
+    fn should_reject_empty_images() {
+        let error = parse_ppm(b"P3\n0 0\n255\n").unwrap_err();
+        assert_eq!(error.to_string(), "the image can't be empty");
+        assert!(parse_ppm(b"P6 0 1 255\n").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 0 +X 1\n").is_err());
+    }
+
+    #[test]
+    fn should_read_the_data_before_allocating_the_canvas() {
+        let error = parse_ppm(b"P6 65535 65535 255\n\x00\x00\x00").unwrap_err();
+        assert_eq!(error.to_string(), "PPM pixel data is truncated");
+        assert!(parse_ppm(b"P3\n65535 65535\n255\n0 0 0").is_err());
+        let error = parse_hdr(b"#?RADIANCE\n\n-Y 65535 +X 65535\n\x80\x80\x80\x81").unwrap_err();
+        assert_eq!(error.to_string(), "HDR pixel data is truncated");
+    }
+
+    #[test]
+    fn should_read_a_flat_hdr() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        // 128 * 2^(129 - 136) = 1 and 128 * 2^(131 - 136) = 4
+        data.extend_from_slice(&[128, 64, 0, 129, 128, 128, 128, 131]);
+        let canvas = parse_hdr(&data).unwrap();
+        assert_eq!(canvas.width, 2);
+        assert_eq!(canvas.get_pixel(0, 0), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(canvas.get_pixel(1, 0), Color::new(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn should_read_a_run_length_encoded_hdr() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // red: a run of 8, green: 8 literals, blue: two runs, exponent: a run
+        data.extend_from_slice(&[128 + 8, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[128 + 4, 0, 128 + 4, 128]);
+        data.extend_from_slice(&[128 + 8, 129]);
+        let canvas = parse_hdr(&data).unwrap();
+        assert_eq!(canvas.width, 8);
+        assert_eq!(canvas.get_pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(canvas.get_pixel(7, 0), Color::new(1.0, 0.875, 1.0));
+    }
+
+    #[test]
+    fn should_reject_invalid_hdr() {
+        assert!(parse_hdr(b"RADIANCE\n\n-Y 1 +X 1\n\x80\x80\x80\x81").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n+Y 1 +X 1\n\x80\x80\x80\x81").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x81").is_err());
+    }
+
+    #[test]
+    fn should_report_missing_files() {
+        let error = load_image("does/not/exist.ppm").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::{
+    color::Color,
+    computations::Computations,
+    intersection::hit,
+    light::sample_vectors,
+    material::Material,
+    object::Object,
+    ray::Ray,
+    utils::{random_float, Float},
+    world::World,
+};
+
+/// Bounces before russian roulette starts terminating paths
+const MIN_BOUNCES: usize = 3;
+
+/// Shadow rays towards emitters stop this short of the sampled point, so the emitter
+/// doesn't shadow itself
+const EMITTER_OFFSET: Float = 0.001;
+
+/// Way the camera turns a ray into a color
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Integrator {
+    /// Direct lighting with the Phong model, see `World::color_at`
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing with diffuse bounces, paths are cut by russian roulette
+    /// or after `max_depth` bounces
+    PathTracer { max_depth: usize },
+}
+
+impl Integrator {
+    pub fn radiance(&self, world: &World, ray: &Ray) -> Color {
+        match self {
+            Integrator::Whitted => world.color_at(ray),
+            Integrator::PathTracer { .. } => self.radiance_with(world, &world.emitters(), ray),
+        }
+    }
+
+    /// Same as `radiance` with the emitters of the world already collected, renders
+    /// gather them once instead of walking the world for every sample
+    pub fn radiance_with(&self, world: &World, emitters: &[&dyn Object], ray: &Ray) -> Color {
+        match self {
+            Integrator::Whitted => world.color_at(ray),
+            Integrator::PathTracer { max_depth } => trace_path(world, emitters, ray, *max_depth),
+        }
+    }
+}
+
+fn trace_path(
+    world: &World,
+    emitters: &[&dyn Object],
+    camera_ray: &Ray,
+    max_depth: usize,
+) -> Color {
+    let mut radiance = Color::new(0.0, 0.0, 0.0);
+    let mut throughput = Color::new(1.0, 1.0, 1.0);
+    let mut ray = Ray::with_time(camera_ray.origin, camera_ray.direction, camera_ray.time);
+
+    for depth in 0..=max_depth {
+        let intersections = world.intersect(&ray);
+        // the background lights the scene too, it's only reached by escaping paths
+        let intersection = match hit(&intersections) {
+            Some(intersection) => intersection,
+            None => {
+                let background = world.background.color_in(&ray.direction);
+                radiance = &radiance + &(&throughput * &background);
+                break;
+            }
+        };
+        let comps = Computations::prepare(intersection, &ray);
+        let material = comps.object.material().surface_at(&comps);
+        // emitters are sampled directly at every bounce, adding their emission when a
+        // bounce hits them would count it twice
+        if depth == 0 {
+            radiance = &radiance + &(&throughput * &material.emission);
+        }
+
+        radiance = &radiance + &(&throughput * &direct_light(world, &comps, &material));
+        radiance = &radiance + &(&throughput * &emitted_light(world, &comps, &material, emitters));
+
+        let (direction, weight) = match material.sample_bounce(&comps.normalv, &comps.eyev) {
+            Some(bounce) => bounce,
+            None => break,
+        };
+        throughput = &throughput * &weight;
+        if depth >= MIN_BOUNCES {
+            let survival = throughput
+                .r
+                .max(throughput.g)
+                .max(throughput.b)
+                .clamp(0.05, 0.95);
+            if random_float() >= survival {
+                break;
+            }
+            throughput = &throughput * (1.0 / survival);
+        }
+        if throughput.r <= 0.0 && throughput.g <= 0.0 && throughput.b <= 0.0 {
+            break;
+        }
+
+        ray = Ray::with_time(comps.over_point, direction, ray.time);
+    }
+    radiance
+}
+
+/// Light arriving straight from the lights of the world, paths can never hit them since
+/// they have no geometry
+fn direct_light(world: &World, comps: &Computations, material: &Material) -> Color {
+    let mut color = Color::new(0.0, 0.0, 0.0);
+    for light in world.lights.iter() {
+        let samples = light.light_samples(&comps.over_point);
+        let intensity = light.sampled_intensity(&comps.over_point, &samples, world, comps.time);
+        if intensity <= 0.0 {
+            continue;
+        }
+        let light_vectors = sample_vectors(&comps.over_point, &samples);
+        let mut reflected = Color::new(0.0, 0.0, 0.0);
+        for lightv in light_vectors.iter() {
+            let cos = lightv.dot_product(&comps.normalv);
+            if cos <= 0.0 {
+                continue;
+            }
+            let f = material.brdf(&comps.normalv, &comps.eyev, lightv);
+            reflected = &reflected + &(&f * cos);
+        }
+        let reflected = &reflected * (intensity / light_vectors.len().max(1) as Float);
+        color = &color + &(&reflected * &light.intensity());
+    }
+    color
+}
+
+/// Light arriving from emissive objects, a single point on a single random emitter is
+/// sampled. Emitters are lit from both sides.
+fn emitted_light(
+    world: &World,
+    comps: &Computations,
+    material: &Material,
+    emitters: &[&dyn Object],
+) -> Color {
+    let black = Color::new(0.0, 0.0, 0.0);
+    if emitters.is_empty() {
+        return black;
+    }
+    let index = ((random_float() * emitters.len() as Float) as usize).min(emitters.len() - 1);
+    let emitter = emitters[index];
+    let sample = match emitter.sample_surface(comps.time) {
+        Some(sample) => sample,
+        None => return black,
+    };
+
+    let to_light = &sample.point - &comps.over_point;
+    let distance = to_light.magnitude();
+    if distance <= EMITTER_OFFSET {
+        return black;
+    }
+    let lightv = &to_light / distance;
+    let cos_surface = lightv.dot_product(&comps.normalv);
+    let cos_light = lightv.dot_product(&sample.normal).abs();
+    if cos_surface <= 0.0 || cos_light <= 0.0 {
+        return black;
+    }
+    let target = &sample.point - &(&lightv * EMITTER_OFFSET);
+    if world.is_shadowed(&comps.over_point, &target, comps.time) {
+        return black;
+    }
+
+    // converts the area pdf (1 / area, times the chance of picking this emitter) to solid
+    // angle
+    let weight =
+        cos_surface * cos_light * sample.area * emitters.len() as Float / (distance * distance);
+    let f = material.brdf(&comps.normalv, &comps.eyev, &lightv);
+    &(&emitter.material().emission * &f) * weight
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::consts::PI;
+
+    use super::*;
+    use crate::{
+        background::Background, group::Group, light::PointLight, matrix::Matrix, point::Point,
+        sphere::Sphere, vector::Vector, world::default_world,
+    };
+
+    #[test]
+    fn should_use_the_world_shading_for_whitted() {
+        let world = default_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(Integrator::default(), Integrator::Whitted);
+        assert_eq!(
+            Integrator::Whitted.radiance(&world, &ray),
+            world.color_at(&ray)
+        );
+    }
+
+    #[test]
+    fn should_return_black_when_the_path_escapes() {
+        let world = default_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        let integrator = Integrator::PathTracer { max_depth: 5 };
+        assert_eq!(integrator.radiance(&world, &ray), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_be_lit_by_the_background() {
+        let mut world = World::new();
+        world.background = Background::Color(Color::new(1.0, 1.0, 1.0));
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material = Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            diffuse: 0.5,
+            ..Default::default()
+        };
+        world.add_object(Box::new(sphere));
+        let integrator = Integrator::PathTracer { max_depth: 5 };
+        // every bounce leaves the sphere and sees the same sky
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(integrator.radiance(&world, &ray), Color::new(0.5, 0.5, 0.5));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(integrator.radiance(&world, &ray), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn should_see_emissive_surfaces() {
+        let mut world = World::new();
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material = Material {
+            color: Color::new(0.0, 0.0, 0.0),
+            emission: Color::new(2.0, 1.0, 0.5),
+            ..Default::default()
+        };
+        world.add_object(Box::new(sphere));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let integrator = Integrator::PathTracer { max_depth: 5 };
+        assert_eq!(integrator.radiance(&world, &ray), Color::new(2.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn should_gather_direct_light_from_point_lights() {
+        let mut world = World::new();
+        world.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material = Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            diffuse: 0.5,
+            ..Default::default()
+        };
+        world.add_object(Box::new(sphere));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        // the bounce leaves the sphere and never comes back so only direct light is left
+        let integrator = Integrator::PathTracer { max_depth: 5 };
+        let expected = 0.5 / PI;
+        assert_eq!(
+            integrator.radiance(&world, &ray),
+            Color::new(expected, expected, expected)
+        );
+    }
+
+    #[test]
+    fn should_gather_direct_light_on_microfacet_materials() {
+        let mut world = World::new();
+        world.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material = Material::pbr(Color::new(1.0, 1.0, 1.0), 0.0, 1.0);
+        world.add_object(Box::new(sphere));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        // diffuse (1 - F0) / PI plus specular D * G * F0 / 4 = 0.04 / (4 * PI)
+        let integrator = Integrator::PathTracer { max_depth: 5 };
+        let expected = 0.97 / PI;
+        assert_eq!(
+            integrator.radiance(&world, &ray),
+            Color::new(expected, expected, expected)
+        );
+    }
+
+    #[test]
+    fn should_converge_inside_a_glowing_sphere() {
+        // every bounce hits the same sphere, so the radiance is the sum of the geometric
+        // series emission * (1 + albedo + albedo² + ...) = emission / (1 - albedo)
+        let mut world = World::new();
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material = Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            diffuse: 0.5,
+            emission: Color::new(1.0, 1.0, 1.0),
+            ..Default::default()
+        };
+        world.add_object(Box::new(sphere));
+        let integrator = Integrator::PathTracer { max_depth: 100 };
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let samples = 2000;
+        let mut sum = 0.0;
+        for _ in 0..samples {
+            sum += integrator.radiance(&world, &ray).r;
+        }
+        let average = sum / samples as Float;
+        assert!((average - 2.0).abs() < 0.1);
+    }
+
+    fn floor_under_a_light_panel(emission: Color) -> World {
+        let mut world = World::new();
+        let floor = Group::mesh(
+            &[
+                Point::new(-10.0, 0.0, -10.0),
+                Point::new(10.0, 0.0, -10.0),
+                Point::new(10.0, 0.0, 10.0),
+                Point::new(-10.0, 0.0, 10.0),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            Material {
+                color: Color::new(1.0, 1.0, 1.0),
+                diffuse: 1.0,
+                ..Default::default()
+            },
+            None,
+        );
+        world.add_object(Box::new(floor));
+        let panel = Group::mesh(
+            &[
+                Point::new(-0.1, 0.0, -0.1),
+                Point::new(0.1, 0.0, -0.1),
+                Point::new(0.1, 0.0, 0.1),
+                Point::new(-0.1, 0.0, 0.1),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            Material {
+                color: Color::new(0.0, 0.0, 0.0),
+                emission,
+                ..Default::default()
+            },
+            Some(Matrix::translation_3d(0.0, 1.0, 0.0)),
+        );
+        world.add_object(Box::new(panel));
+        world
+    }
+
+    #[test]
+    fn should_light_surfaces_with_emissive_meshes() {
+        // the small panel right above the floor acts almost like a point light,
+        // irradiance = emission * area / distance², radiance = irradiance / PI
+        let world = floor_under_a_light_panel(Color::new(10.0, 10.0, 10.0));
+        let ray = Ray::new(
+            Point::new(0.0, 0.5, -0.5),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let integrator = Integrator::PathTracer { max_depth: 0 };
+        let samples = 500;
+        let mut sum = 0.0;
+        for _ in 0..samples {
+            sum += integrator.radiance(&world, &ray).r;
+        }
+        let average = sum / samples as Float;
+        let expected = 10.0 * 0.04 / PI;
+        assert!((average - expected).abs() < 0.01);
+        assert_eq!(
+            Integrator::Whitted.radiance(&world, &ray),
+            Color::new(0.0, 0.0, 0.0)
+        );
+
+        let world = floor_under_a_light_panel(Color::new(0.0, 0.0, 0.0));
+        assert_eq!(integrator.radiance(&world, &ray), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_only_sample_the_emitters_it_is_given() {
+        let world = floor_under_a_light_panel(Color::new(10.0, 10.0, 10.0));
+        let ray = Ray::new(
+            Point::new(0.0, 0.5, -0.5),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        let integrator = Integrator::PathTracer { max_depth: 0 };
+        assert_eq!(
+            integrator.radiance_with(&world, &[], &ray),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        let emitters = world.emitters();
+        assert!(integrator.radiance_with(&world, &emitters, &ray).r > 0.0);
+    }
+}
@@ -1,15 +1,41 @@
-use crate::object::Object;
+use crate::utils::Float;
 
+#[allow(dead_code)]
 #[derive(Debug)]
-pub struct Intersection<T: Object> {
-    pub t: f32,
+pub struct Intersection<T> {
+    pub t: Float,
     pub object: T,
+    /// Texture coordinates of the hit, only for shapes that carry their own (triangles
+    /// with per-vertex UVs)
+    pub uv: Option<(Float, Float)>,
 }
 
-impl<T: Object> Intersection<T> {
-    pub fn new(t: f32, object: T) -> Intersection<T> {
-        Intersection { t, object }
+impl<T> Intersection<T> {
+    pub fn new(t: Float, object: T) -> Intersection<T> {
+        Intersection {
+            t,
+            object,
+            uv: None,
+        }
     }
+
+    pub fn with_uv(t: Float, object: T, uv: (Float, Float)) -> Intersection<T> {
+        Intersection {
+            t,
+            object,
+            uv: Some(uv),
+        }
+    }
+}
+
+pub fn sort_intersections<T>(intersections: &mut [Intersection<T>]) {
+    intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+}
+
+/// The hit is the lowest non negative intersection, expects them sorted
+#[allow(dead_code)]
+pub fn hit<T>(intersections: &[Intersection<T>]) -> Option<&Intersection<T>> {
+    intersections.iter().find(|intersec| intersec.t >= 0.0)
 }
 
 #[cfg(test)]
@@ -24,4 +50,30 @@ mod tests {
         assert_eq!(intersection.t, 3.0);
         assert_eq!(intersection.object.radius, 1.0);
     }
+
+    #[test]
+    fn should_sort_intersections() {
+        let mut intersections = vec![
+            Intersection::new(5.0, ()),
+            Intersection::new(-3.0, ()),
+            Intersection::new(2.0, ()),
+        ];
+        sort_intersections(&mut intersections);
+        assert_eq!(intersections[0].t, -3.0);
+        assert_eq!(intersections[1].t, 2.0);
+        assert_eq!(intersections[2].t, 5.0);
+    }
+
+    #[test]
+    fn should_get_the_lowest_non_negative_hit() {
+        let intersections = vec![
+            Intersection::new(-3.0, ()),
+            Intersection::new(2.0, ()),
+            Intersection::new(5.0, ()),
+        ];
+        assert_eq!(hit(&intersections).unwrap().t, 2.0);
+
+        let intersections = vec![Intersection::new(-2.0, ()), Intersection::new(-1.0, ())];
+        assert!(hit(&intersections).is_none());
+    }
 }
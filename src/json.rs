@@ -0,0 +1,353 @@
+use std::fmt;
+
+/// Parsed JSON value that remembers the line it starts on, so errors found later while
+/// interpreting it can point at the right place in the file
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json {
+    pub line: usize,
+    pub value: JsonValue,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys are kept in the order of the file
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+#[allow(dead_code)]
+impl Json {
+    /// Value of a key when this is an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match &self.value {
+            JsonValue::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Name of the kind of value, used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self.value {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "a boolean",
+            JsonValue::Number(_) => "a number",
+            JsonValue::String(_) => "a string",
+            JsonValue::Array(_) => "an array",
+            JsonValue::Object(_) => "an object",
+        }
+    }
+}
+
+/// Parses a whole document, anything but whitespace after the value is an error
+#[allow(dead_code)]
+pub fn parse_json(text: &str) -> Result<Json, JsonError> {
+    let mut parser = Parser {
+        data: text.as_bytes(),
+        position: 0,
+        line: 1,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position < parser.data.len() {
+        return Err(parser.error("unexpected content after the end of the document"));
+    }
+    Ok(value)
+}
+
+/// Deeper documents are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'\n' => self.line += 1,
+                b' ' | b'\t' | b'\r' => {}
+                _ => return,
+            }
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("the document is nested too deeply"));
+        }
+        self.skip_whitespace();
+        let line = self.line;
+        let value = match self.peek() {
+            None => return Err(self.error("unexpected end of the document")),
+            Some(b'{') => self.object(depth)?,
+            Some(b'[') => self.array(depth)?,
+            Some(b'"') => JsonValue::String(self.string()?),
+            Some(b't') => self.keyword("true", JsonValue::Bool(true))?,
+            Some(b'f') => self.keyword("false", JsonValue::Bool(false))?,
+            Some(b'n') => self.keyword("null", JsonValue::Null)?,
+            Some(b'-' | b'0'..=b'9') => JsonValue::Number(self.number()?),
+            Some(byte) => {
+                return Err(self.error(&format!("unexpected character '{}'", byte as char)))
+            }
+        };
+        Ok(Json { line, value })
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if self.data[self.position..].starts_with(word.as_bytes()) {
+            self.position += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("unknown keyword, expected true, false or null"))
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.position += 1;
+        let mut entries: Vec<(String, Json)> = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key in double quotes"));
+            }
+            let key = self.string()?;
+            if entries.iter().any(|(name, _)| *name == key) {
+                return Err(self.error(&format!("duplicate key \"{}\"", key)));
+            }
+            self.expect(b':')?;
+            let value = self.value(depth + 1)?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}' in object")),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.position += 1;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.position += 1;
+        let mut bytes = vec![];
+        loop {
+            let byte = match self.peek() {
+                None | Some(b'\n') => return Err(self.error("unterminated string")),
+                Some(byte) => byte,
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let decoded = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(decoded.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    /// The 4 hex digits after \u, surrogate pairs are combined
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let first = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if !self.data[self.position..].starts_with(b"\\u") {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            self.position += 2;
+            let second = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .data
+            .get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("expected 4 hex digits after \\u"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> Result<f64, JsonError> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.data[start..self.position]).unwrap_or("");
+        // Rust accepts a few forms JSON doesn't, like "1." or "01"
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        let leading_zero =
+            digits.starts_with('0') && digits[1..].starts_with(|c: char| c.is_ascii_digit());
+        let bare_point = digits.ends_with('.') || digits.contains(".e") || digits.contains(".E");
+        let valid =
+            digits.starts_with(|c: char| c.is_ascii_digit()) && !leading_zero && !bare_point;
+        match text.parse::<f64>() {
+            Ok(number) if valid => Ok(number),
+            _ => Err(self.error(&format!("invalid number {}", text))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_every_kind_of_value() {
+        let json =
+            parse_json(r#"{"a": [1, -2.5, 3e2], "b": {"c": true, "d": null}, "e": "x\n\"y\" é"}"#)
+                .unwrap();
+        let numbers: Vec<f64> = match &json.get("a").unwrap().value {
+            JsonValue::Array(items) => items
+                .iter()
+                .map(|item| match item.value {
+                    JsonValue::Number(n) => n,
+                    _ => panic!("not a number"),
+                })
+                .collect(),
+            _ => panic!("not an array"),
+        };
+        assert_eq!(numbers, vec![1.0, -2.5, 300.0]);
+        let b = json.get("b").unwrap();
+        assert_eq!(b.get("c").unwrap().value, JsonValue::Bool(true));
+        assert_eq!(b.get("d").unwrap().value, JsonValue::Null);
+        assert_eq!(
+            json.get("e").unwrap().value,
+            JsonValue::String("x\n\"y\" é".to_string())
+        );
+    }
+
+    #[test]
+    fn should_remember_the_lines() {
+        let json = parse_json("{\n  \"a\": 1,\n\n  \"b\": [\n    2\n  ]\n}").unwrap();
+        assert_eq!(json.line, 1);
+        assert_eq!(json.get("a").unwrap().line, 2);
+        let b = json.get("b").unwrap();
+        assert_eq!(b.line, 4);
+        match &b.value {
+            JsonValue::Array(items) => assert_eq!(items[0].line, 5),
+            _ => panic!("not an array"),
+        }
+    }
+
+    #[test]
+    fn should_report_the_line_of_syntax_errors() {
+        let cases = [
+            ("{\n\"a\": 1\n\"b\": 2}", 3),
+            ("[1,\n2,\n]", 3),
+            ("{\"a\": tru}", 1),
+            ("\n\n\"open", 3),
+            ("[01]", 1),
+            ("{\"a\": 1, \"a\": 2}", 1),
+            ("[1] 2", 1),
+            ("", 1),
+        ];
+        for (text, line) in cases {
+            let error = parse_json(text).unwrap_err();
+            assert_eq!(error.line, line, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Ray tracer built while following The Ray Tracer Challenge.
+//!
+//! A scene is a [`World`] holding shapes and lights, rendered through a [`Camera`] into
+//! a [`Canvas`]. Scenes can be built in code or read from a JSON file with
+//! [`load_scene`]:
+//!
+//! ```no_run
+//! use ray_tracer_challenge::load_scene;
+//!
+//! let scene = load_scene("scenes/example.json").unwrap();
+//! let image = scene.camera.render(&scene.world);
+//! image.save("example.png").unwrap();
+//! ```
+
+// geometry
+pub mod bounds;
+pub mod matrix;
+pub mod point;
+pub mod quaternion;
+pub mod ray;
+pub mod transform;
+pub mod vector;
+
+// shapes
+pub mod bvh;
+pub mod group;
+pub mod intersection;
+pub mod object;
+pub mod sphere;
+pub mod triangle;
+
+// shading
+pub mod background;
+pub mod bump;
+pub mod color;
+pub mod computations;
+pub mod light;
+pub mod material;
+pub mod microfacet;
+pub mod noise;
+pub mod pattern;
+pub mod texture;
+
+// errors
+pub mod error;
+
+// canvas and files
+pub mod canvas;
+pub mod compositing;
+pub mod draw;
+pub mod image;
+pub mod png;
+pub mod scene;
+
+// rendering
+pub mod animation;
+pub mod camera;
+pub mod physics;
+pub mod integrator;
+pub mod utils;
+pub mod world;
+
+mod json;
+
+pub use camera::Camera;
+pub use canvas::Canvas;
+pub use color::Color;
+pub use error::RayTracerError;
+pub use group::Group;
+pub use light::{AreaLight, DirectionalLight, Light, PointLight, SpotLight};
+pub use material::Material;
+pub use matrix::Matrix;
+pub use object::Object;
+pub use point::Point;
+pub use ray::Ray;
+pub use scene::{load_scene, parse_scene, Scene, SceneError};
+pub use sphere::Sphere;
+pub use transform::Transform;
+pub use triangle::Triangle;
+pub use vector::Vector;
+pub use world::World;
@@ -0,0 +1,624 @@
+use std::fmt::Debug;
+
+use crate::{
+    color::Color,
+    error::{RayTracerError, Result},
+    point::Point,
+    utils::{random_float, Float},
+    vector::Vector,
+    world::World,
+};
+
+pub trait Light: Debug + Send + Sync {
+    fn intensity(&self) -> Color;
+
+    /// Where the light comes from as seen from the point, one entry per sample. Area lights
+    /// jitter them, so draw them once per shading point and use the same set for the
+    /// shading and the shadows
+    fn light_samples(&self, point: &Point) -> Vec<LightSample>;
+
+    /// Part of the light sent towards the point before shadows, 1.0 except outside the
+    /// inner cone of a spot light
+    fn attenuation(&self, _point: &Point) -> Float {
+        1.0
+    }
+
+    /// Normalized vectors from the point towards the light, one per sample
+    fn light_vectors(&self, point: &Point) -> Vec<Vector> {
+        sample_vectors(point, &self.light_samples(point))
+    }
+
+    /// Fraction of the light that reaches the point at that time, 0.0 when it's fully
+    /// shadowed
+    fn intensity_at(&self, point: &Point, world: &World, time: Float) -> Float {
+        self.sampled_intensity(point, &self.light_samples(point), world, time)
+    }
+
+    /// Like `intensity_at` but for samples that were already drawn
+    fn sampled_intensity(
+        &self,
+        point: &Point,
+        samples: &[LightSample],
+        world: &World,
+        time: Float,
+    ) -> Float {
+        let attenuation = self.attenuation(point);
+        if attenuation == 0.0 || samples.is_empty() {
+            return 0.0;
+        }
+        let lit = samples
+            .iter()
+            .filter(|sample| !sample.is_shadowed(point, world, time))
+            .count();
+        attenuation * lit as Float / samples.len() as Float
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone)]
+pub enum LightSample {
+    /// A point on the light, anything between it and the shading point casts a shadow
+    Position(Point),
+    /// Infinitely far away in that (normalized) direction from the shading point
+    Direction(Vector),
+}
+
+#[allow(dead_code)]
+impl LightSample {
+    pub fn direction_from(&self, point: &Point) -> Vector {
+        match self {
+            LightSample::Position(position) => (position - point).normalize(),
+            LightSample::Direction(direction) => *direction,
+        }
+    }
+
+    pub fn is_shadowed(&self, point: &Point, world: &World, time: Float) -> bool {
+        match self {
+            LightSample::Position(position) => world.is_shadowed(point, position, time),
+            LightSample::Direction(direction) => world.is_shadowed_towards(point, direction, time),
+        }
+    }
+}
+
+/// Normalized vectors from the point towards each sample
+pub fn sample_vectors(point: &Point, samples: &[LightSample]) -> Vec<Vector> {
+    samples
+        .iter()
+        .map(|sample| sample.direction_from(point))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub position: Point,
+    pub intensity: Color,
+}
+
+#[allow(dead_code)]
+impl PointLight {
+    pub fn new(position: Point, intensity: Color) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn light_samples(&self, _point: &Point) -> Vec<LightSample> {
+        vec![LightSample::Position(self.position)]
+    }
+}
+
+/// Rectangular light divided in `usteps` x `vsteps` cells, each cell is sampled once
+/// at a random position inside of it (or at its center when `jitter` is off)
+#[derive(Debug, Clone)]
+pub struct AreaLight {
+    pub corner: Point,
+    pub uvec: Vector,
+    pub usteps: usize,
+    pub vvec: Vector,
+    pub vsteps: usize,
+    pub intensity: Color,
+    pub jitter: bool,
+}
+
+#[allow(dead_code)]
+impl AreaLight {
+    /// `full_uvec` and `full_vvec` are the edges of the rectangle starting at `corner`
+    pub fn new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Self {
+        match AreaLight::try_new(corner, full_uvec, usteps, full_vvec, vsteps, intensity) {
+            Ok(light) => light,
+            Err(error) => panic!("AreaLight::new {}", error),
+        }
+    }
+
+    /// Rejects a light without any cell to sample
+    pub fn try_new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Result<Self> {
+        if usteps == 0 || vsteps == 0 {
+            return Err(RayTracerError::InvalidArgument(
+                "the steps must be greater than 0".to_string(),
+            ));
+        }
+        Ok(AreaLight {
+            corner,
+            uvec: &full_uvec / usteps as Float,
+            usteps,
+            vvec: &full_vvec / vsteps as Float,
+            vsteps,
+            intensity,
+            jitter: true,
+        })
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    /// Center of the rectangle
+    pub fn position(&self) -> Point {
+        let half_u = &self.uvec * (self.usteps as Float / 2.0);
+        let half_v = &self.vvec * (self.vsteps as Float / 2.0);
+        &(&self.corner + &half_u) + &half_v
+    }
+
+    pub fn samples(&self) -> Vec<Point> {
+        let mut samples = Vec::with_capacity(self.sample_count());
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                samples.push(self.point_on_light(u, v));
+            }
+        }
+        samples
+    }
+
+    pub fn point_on_light(&self, u: usize, v: usize) -> Point {
+        let (u_offset, v_offset) = if self.jitter {
+            (random_float(), random_float())
+        } else {
+            (0.5, 0.5)
+        };
+        let u_vec = &self.uvec * (u as Float + u_offset);
+        let v_vec = &self.vvec * (v as Float + v_offset);
+        &(&self.corner + &u_vec) + &v_vec
+    }
+}
+
+impl Light for AreaLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn light_samples(&self, _point: &Point) -> Vec<LightSample> {
+        self.samples()
+            .into_iter()
+            .map(LightSample::Position)
+            .collect()
+    }
+}
+
+/// Point light that only shines inside a cone, fading from the inner angle to the outer
+/// one. Both angles are measured in radians from the direction
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Point,
+    pub direction: Vector,
+    pub inner_angle: Float,
+    pub outer_angle: Float,
+    pub intensity: Color,
+}
+
+#[allow(dead_code)]
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        inner_angle: Float,
+        outer_angle: Float,
+        intensity: Color,
+    ) -> Self {
+        match SpotLight::try_new(position, direction, inner_angle, outer_angle, intensity) {
+            Ok(light) => light,
+            Err(error) => panic!("SpotLight::new {}", error),
+        }
+    }
+
+    /// Rejects an inner cone wider than the outer one and a direction of length 0
+    pub fn try_new(
+        position: Point,
+        direction: Vector,
+        inner_angle: Float,
+        outer_angle: Float,
+        intensity: Color,
+    ) -> Result<Self> {
+        if inner_angle > outer_angle {
+            return Err(RayTracerError::InvalidArgument(
+                "the inner angle is greater than the outer angle".to_string(),
+            ));
+        }
+        if direction.magnitude() == 0.0 {
+            return Err(RayTracerError::InvalidArgument(
+                "the direction can't be a zero vector".to_string(),
+            ));
+        }
+        Ok(SpotLight {
+            position,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle,
+            intensity,
+        })
+    }
+
+    /// 1.0 inside the inner cone, 0.0 outside the outer cone and a smooth step in between
+    pub fn falloff(&self, point: &Point) -> Float {
+        let offset = point - &self.position;
+        // a point on the light has no direction, it gets the full intensity
+        if offset.magnitude() == 0.0 {
+            return 1.0;
+        }
+        let cos_angle = offset.normalize().dot_product(&self.direction);
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_angle >= cos_inner {
+            return 1.0;
+        }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn light_samples(&self, _point: &Point) -> Vec<LightSample> {
+        vec![LightSample::Position(self.position)]
+    }
+
+    fn attenuation(&self, point: &Point) -> Float {
+        self.falloff(point)
+    }
+}
+
+/// Light infinitely far away (like the sun), every ray travels in the same direction
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    pub direction: Vector,
+    pub intensity: Color,
+}
+
+#[allow(dead_code)]
+impl DirectionalLight {
+    pub fn new(direction: Vector, intensity: Color) -> Self {
+        match DirectionalLight::try_new(direction, intensity) {
+            Ok(light) => light,
+            Err(error) => panic!("DirectionalLight::new {}", error),
+        }
+    }
+
+    /// Rejects a direction of length 0
+    pub fn try_new(direction: Vector, intensity: Color) -> Result<Self> {
+        if direction.magnitude() == 0.0 {
+            return Err(RayTracerError::InvalidArgument(
+                "the direction can't be a zero vector".to_string(),
+            ));
+        }
+        Ok(DirectionalLight {
+            direction: direction.normalize(),
+            intensity,
+        })
+    }
+}
+
+impl Light for DirectionalLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn light_samples(&self, _point: &Point) -> Vec<LightSample> {
+        vec![LightSample::Direction(-&self.direction)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::consts::PI;
+
+    use super::*;
+    use crate::world::default_world;
+
+    #[test]
+    fn should_create_a_point_light() {
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(light.position.x, 0.0);
+        assert_eq!(light.intensity(), Color::new(1.0, 1.0, 1.0));
+        let vectors = light.light_vectors(&Point::new(0.0, 0.0, -2.0));
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors[0].z, 1.0);
+    }
+
+    #[test]
+    fn should_evaluate_point_light_intensity_at_a_point() {
+        let world = default_world();
+        let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let cases = [
+            (Point::new(0.0, 1.0001, 0.0), 1.0),
+            (Point::new(-1.0001, 0.0, 0.0), 1.0),
+            (Point::new(0.0, 0.0, -1.0001), 1.0),
+            (Point::new(0.0, 0.0, 1.0001), 0.0),
+            (Point::new(1.0001, 0.0, 0.0), 0.0),
+            (Point::new(0.0, -1.0001, 0.0), 0.0),
+            (Point::new(0.0, 0.0, 0.0), 0.0),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(light.intensity_at(&point, &world, 0.0), expected);
+        }
+    }
+
+    #[test]
+    fn should_create_an_area_light() {
+        let light = AreaLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.uvec.x, 0.5);
+        assert_eq!(light.vvec.z, 0.5);
+        assert_eq!(light.sample_count(), 8);
+        let position = light.position();
+        assert_eq!(position.x, 1.0);
+        assert_eq!(position.y, 0.0);
+        assert_eq!(position.z, 0.5);
+    }
+
+    #[test]
+    fn should_find_a_point_on_an_area_light() {
+        let mut light = AreaLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        let cases = [
+            (0, 0, Point::new(0.25, 0.0, 0.25)),
+            (1, 0, Point::new(0.75, 0.0, 0.25)),
+            (0, 1, Point::new(0.25, 0.0, 0.75)),
+            (2, 0, Point::new(1.25, 0.0, 0.25)),
+            (3, 1, Point::new(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, expected) in cases {
+            let point = light.point_on_light(u, v);
+            assert_eq!(point.x, expected.x);
+            assert_eq!(point.y, expected.y);
+            assert_eq!(point.z, expected.z);
+        }
+    }
+
+    #[test]
+    fn should_jitter_points_inside_their_cell() {
+        let light = AreaLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        for _ in 0..20 {
+            let point = light.point_on_light(3, 1);
+            assert!(point.x >= 1.5 && point.x < 2.0);
+            assert!(point.z >= 0.5 && point.z < 1.0);
+        }
+    }
+
+    #[test]
+    fn should_evaluate_area_light_intensity_at_a_point() {
+        let world = default_world();
+        let mut light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5.0),
+            Vector::new(1.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 1.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        let cases = [
+            (Point::new(0.0, 0.0, 2.0), 0.0),
+            (Point::new(1.0, -1.0, 2.0), 0.25),
+            (Point::new(1.5, 0.0, 2.0), 0.5),
+            (Point::new(1.25, 1.25, 3.0), 0.75),
+            (Point::new(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (point, expected) in cases {
+            assert_eq!(light.intensity_at(&point, &world, 0.0), expected);
+        }
+    }
+
+    #[test]
+    fn should_shadow_the_samples_it_is_given() {
+        let world = default_world();
+        let light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5.0),
+            Vector::new(1.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 1.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let point = Point::new(0.0, 0.0, 5.0);
+        // one sample behind the spheres of the default world and one beside them
+        let samples = [
+            LightSample::Position(Point::new(0.0, 0.0, -5.0)),
+            LightSample::Position(Point::new(0.0, 5.0, 5.0)),
+        ];
+        for _ in 0..10 {
+            assert_eq!(light.sampled_intensity(&point, &samples, &world, 0.0), 0.5);
+        }
+        let vectors = sample_vectors(&point, &samples);
+        assert_eq!(vectors[0].z, -1.0);
+        assert_eq!(vectors[1].y, 1.0);
+        let towards = [LightSample::Direction(Vector::new(0.0, 0.0, -1.0))];
+        assert_eq!(light.sampled_intensity(&point, &towards, &world, 0.0), 0.0);
+    }
+
+    #[test]
+    fn should_create_a_spot_light() {
+        let light = SpotLight::new(
+            Point::new(0.0, 5.0, 0.0),
+            Vector::new(0.0, -2.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.direction.y, -1.0);
+        let vectors = light.light_vectors(&Point::new(0.0, 0.0, 0.0));
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors[0].y, 1.0);
+    }
+
+    #[test]
+    fn should_fade_the_spot_light_between_the_cones() {
+        let light = SpotLight::new(
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        // straight below, inside the inner cone
+        assert_eq!(light.falloff(&Point::new(0.0, 0.0, 0.0)), 1.0);
+        // 60 degrees away, outside the outer cone
+        assert_eq!(
+            light.falloff(&Point::new((3.0 as Float).sqrt(), 0.0, 0.0)),
+            0.0
+        );
+        // 30 degrees away, between both cones
+        let falloff = light.falloff(&Point::new(1.0 / (3.0 as Float).sqrt(), 0.0, 0.0));
+        assert!(falloff > 0.0 && falloff < 1.0);
+        let closer = light.falloff(&Point::new(0.5, 0.0, 0.0));
+        assert!(closer > falloff);
+        assert_eq!(light.falloff(&Point::new(0.0, 1.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn should_evaluate_spot_light_intensity_at_a_point() {
+        let world = default_world();
+        let light = SpotLight::new(
+            Point::new(0.0, 5.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(
+            light.intensity_at(&Point::new(0.0, 1.0001, 0.0), &world, 0.0),
+            1.0
+        );
+        assert_eq!(
+            light.intensity_at(&Point::new(0.0, -1.0001, 0.0), &world, 0.0),
+            0.0
+        );
+        assert_eq!(
+            light.intensity_at(&Point::new(10.0, 1.0001, 0.0), &world, 0.0),
+            0.0
+        );
+    }
+
+    #[test]
+    fn should_create_a_directional_light() {
+        let light = DirectionalLight::new(Vector::new(0.0, -3.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        let vectors = light.light_vectors(&Point::new(10.0, 0.0, 3.0));
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors[0].x, 0.0);
+        assert_eq!(vectors[0].y, 1.0);
+        assert_eq!(vectors[0].z, 0.0);
+    }
+
+    #[test]
+    fn should_cast_directional_shadows_from_infinitely_far() {
+        let world = default_world();
+        let light = DirectionalLight::new(Vector::new(0.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            light.intensity_at(&Point::new(0.0, 1.0001, 0.0), &world, 0.0),
+            1.0
+        );
+        assert_eq!(
+            light.intensity_at(&Point::new(0.0, -1.0001, 0.0), &world, 0.0),
+            0.0
+        );
+        // no matter how far the blocker is, the point stays in shadow
+        assert_eq!(
+            light.intensity_at(&Point::new(0.0, -1000.0, 0.0), &world, 0.0),
+            0.0
+        );
+        assert_eq!(
+            light.intensity_at(&Point::new(2.0, -1000.0, 0.0), &world, 0.0),
+            1.0
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_lights() {
+        let area = AreaLight::try_new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            0,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert!(area.is_err());
+        let spot = SpotLight::try_new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 0.0),
+            0.1,
+            0.2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(
+            spot.unwrap_err().to_string(),
+            "the direction can't be a zero vector"
+        );
+        let directional =
+            DirectionalLight::try_new(Vector::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            directional.unwrap_err().to_string(),
+            "the direction can't be a zero vector"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "DirectionalLight::new the direction can't be a zero vector")]
+    fn should_panic_on_a_directional_light_without_direction() {
+        DirectionalLight::new(Vector::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,442 @@
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{
+    bump::NormalMap,
+    color::Color,
+    computations::Computations,
+    light::Light,
+    microfacet::Microfacet,
+    pattern::Pattern,
+    point::Point,
+    utils::{consts::PI, random_cosine_direction, Float},
+    vector::Vector,
+};
+
+/// Reflection model of a material
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Shading {
+    /// Classic Phong, the path tracer only keeps its diffuse part
+    #[default]
+    Phong,
+    /// Physically based GGX / Cook-Torrance, `color` is the base color and `diffuse`,
+    /// `specular` and `shininess` are ignored
+    Microfacet(Microfacet),
+}
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    /// Base color for microfacet materials
+    pub color: Color,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    /// Light given off by the surface, only the path tracer uses it
+    pub emission: Color,
+    pub shading: Shading,
+    /// Replaces `color` when set, shared between the materials that use it
+    pub pattern: Option<Arc<dyn Pattern>>,
+    /// Bumps or normal map applied to the shading normal
+    pub normal_map: Option<NormalMap>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.0,
+            emission: Color::new(0.0, 0.0, 0.0),
+            shading: Shading::Phong,
+            pattern: None,
+            normal_map: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Material {
+    pub fn new(
+        color: Color,
+        ambient: Float,
+        diffuse: Float,
+        specular: Float,
+        shininess: Float,
+    ) -> Self {
+        Material {
+            color,
+            ambient,
+            diffuse,
+            specular,
+            shininess,
+            emission: Color::new(0.0, 0.0, 0.0),
+            shading: Shading::Phong,
+            pattern: None,
+            normal_map: None,
+        }
+    }
+
+    /// Physically based material, metallic and roughness are clamped to [0, 1]
+    pub fn pbr(base_color: Color, metallic: Float, roughness: Float) -> Self {
+        Material {
+            color: base_color,
+            shading: Shading::Microfacet(Microfacet::new(metallic, roughness)),
+            ..Default::default()
+        }
+    }
+
+    /// The material as seen at the hit, with the pattern (if any) resolved to a flat color
+    pub fn surface_at(&self, comps: &Computations) -> Cow<'_, Material> {
+        match &self.pattern {
+            Some(pattern) => Cow::Owned(Material {
+                color: pattern.pattern_at_object(comps.object, &comps.point, comps.uv, comps.time),
+                pattern: None,
+                ..self.clone()
+            }),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission.r > 0.0 || self.emission.g > 0.0 || self.emission.b > 0.0
+    }
+
+    /// Direct lighting, diffuse and specular are averaged over every light vector
+    /// (one per sample of the light) and scaled by `light_intensity` (the fraction of the light that isn't shadowed)
+    pub fn lighting(
+        &self,
+        light: &dyn Light,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        light_intensity: Float,
+    ) -> Color {
+        let light_vectors = light.light_vectors(point);
+        self.lighting_from(light, &light_vectors, eyev, normalv, light_intensity)
+    }
+
+    /// Like `lighting` with light vectors that were already drawn, so they can come from
+    /// the same samples as the shadow test
+    pub fn lighting_from(
+        &self,
+        light: &dyn Light,
+        light_vectors: &[Vector],
+        eyev: &Vector,
+        normalv: &Vector,
+        light_intensity: Float,
+    ) -> Color {
+        match self.shading {
+            Shading::Phong => self.phong(light, light_vectors, eyev, normalv, light_intensity),
+            Shading::Microfacet(microfacet) => self.microfacet(
+                &microfacet,
+                light,
+                light_vectors,
+                eyev,
+                normalv,
+                light_intensity,
+            ),
+        }
+    }
+
+    /// Value of the brdf used by the path tracer, Phong materials are lambertian
+    pub fn brdf(&self, normalv: &Vector, eyev: &Vector, lightv: &Vector) -> Color {
+        match self.shading {
+            Shading::Phong => &self.color * (self.diffuse / PI),
+            Shading::Microfacet(microfacet) => microfacet.brdf(&self.color, normalv, eyev, lightv),
+        }
+    }
+
+    /// Direction of the next bounce of a path and the factor (brdf * cos / pdf) applied to
+    /// its throughput, None when the sampled direction goes under the surface
+    pub fn sample_bounce(&self, normalv: &Vector, eyev: &Vector) -> Option<(Vector, Color)> {
+        match self.shading {
+            // the cosine and the PI of the brdf cancel with the pdf
+            Shading::Phong => Some((random_cosine_direction(normalv), &self.color * self.diffuse)),
+            Shading::Microfacet(microfacet) => {
+                let lightv = microfacet.sample(&self.color, normalv, eyev);
+                let cos = normalv.dot_product(&lightv);
+                let pdf = microfacet.pdf(&self.color, normalv, eyev, &lightv);
+                if cos <= 0.0 || pdf <= 0.0 {
+                    return None;
+                }
+                let f = microfacet.brdf(&self.color, normalv, eyev, &lightv);
+                Some((lightv, &f * (cos / pdf)))
+            }
+        }
+    }
+
+    fn phong(
+        &self,
+        light: &dyn Light,
+        light_vectors: &[Vector],
+        eyev: &Vector,
+        normalv: &Vector,
+        light_intensity: Float,
+    ) -> Color {
+        let effective_color = &self.color * &light.intensity();
+        let ambient = &effective_color * self.ambient;
+        let black = Color::new(0.0, 0.0, 0.0);
+
+        let mut sum = black;
+        for lightv in light_vectors.iter() {
+            let light_dot_normal = lightv.dot_product(normalv);
+            if light_dot_normal < 0.0 {
+                continue;
+            }
+            let diffuse = &effective_color * (self.diffuse * light_dot_normal);
+            let reflectv = (-lightv).reflect(normalv);
+            let reflect_dot_eye = reflectv.dot_product(eyev);
+            let specular = if reflect_dot_eye <= 0.0 {
+                black
+            } else {
+                let factor = reflect_dot_eye.powf(self.shininess);
+                &light.intensity() * (self.specular * factor)
+            };
+            sum = &sum + &(&diffuse + &specular);
+        }
+        if light_vectors.is_empty() {
+            return ambient;
+        }
+        &ambient + &(&sum * (light_intensity / light_vectors.len() as Float))
+    }
+
+    /// The brdf is scaled by PI so a white lambertian surface is as bright as a Phong
+    /// material with a diffuse of 1 under the same light
+    fn microfacet(
+        &self,
+        microfacet: &Microfacet,
+        light: &dyn Light,
+        light_vectors: &[Vector],
+        eyev: &Vector,
+        normalv: &Vector,
+        light_intensity: Float,
+    ) -> Color {
+        let ambient = &(&self.color * &light.intensity()) * self.ambient;
+        if light_vectors.is_empty() {
+            return ambient;
+        }
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        for lightv in light_vectors.iter() {
+            let light_dot_normal = lightv.dot_product(normalv);
+            if light_dot_normal <= 0.0 {
+                continue;
+            }
+            let f = microfacet.brdf(&self.color, normalv, eyev, lightv);
+            sum = &sum + &(&f * (PI * light_dot_normal));
+        }
+        let direct =
+            &(&sum * &light.intensity()) * (light_intensity / light_vectors.len() as Float);
+        &ambient + &direct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canvas::Canvas,
+        intersection::Intersection,
+        light::{AreaLight, PointLight},
+        object::Object,
+        ray::Ray,
+        sphere::Sphere,
+        texture::{Filter, ImageTexture, UvMapping},
+    };
+
+    #[test]
+    fn should_create_the_default_material() {
+        let m = Material::default();
+        assert_eq!(m.color, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(m.ambient, 0.1);
+        assert_eq!(m.diffuse, 0.9);
+        assert_eq!(m.specular, 0.9);
+        assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.emission, Color::new(0.0, 0.0, 0.0));
+        assert!(!m.is_emissive());
+        assert_eq!(m.shading, Shading::Phong);
+        assert!(m.pattern.is_none());
+    }
+
+    #[test]
+    fn should_resolve_the_pattern_at_the_hit() {
+        let mut image = Canvas::new(2, 1);
+        image.write_pixel(&Color::new(1.0, 0.0, 0.0), 0, 0);
+        image.write_pixel(&Color::new(0.0, 0.0, 1.0), 1, 0);
+        let mut texture = ImageTexture::new(Arc::new(image), UvMapping::Spherical, None);
+        texture.filter = Filter::Nearest;
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.material.pattern = Some(Arc::new(texture));
+
+        // the front of the sphere is the left edge of the image, the back the middle
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(4.0, &sphere as &dyn Object);
+        let comps = Computations::prepare(&intersection, &ray);
+        let surface = sphere.material.surface_at(&comps);
+        assert_eq!(surface.color, Color::new(1.0, 0.0, 0.0));
+        assert!(surface.pattern.is_none());
+        let intersection = Intersection::new(6.0, &sphere as &dyn Object);
+        let comps = Computations::prepare(&intersection, &ray);
+        assert_eq!(
+            sphere.material.surface_at(&comps).color,
+            Color::new(0.0, 0.0, 1.0)
+        );
+
+        let plain = Material::default();
+        assert!(matches!(plain.surface_at(&comps), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn should_light_a_microfacet_material() {
+        let m = Material::pbr(Color::new(1.0, 1.0, 1.0), 1.0, 1.0);
+        assert_eq!(m.shading, Shading::Microfacet(Microfacet::new(1.0, 1.0)));
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        // ambient plus PI * D * G * F / 4 with D = 1 / PI, G = 1 and F = 1
+        assert_eq!(
+            m.lighting(&light, &position, &eyev, &normalv, 1.0),
+            Color::new(0.35, 0.35, 0.35)
+        );
+        assert_eq!(
+            m.lighting(&light, &position, &eyev, &normalv, 0.0),
+            Color::new(0.1, 0.1, 0.1)
+        );
+
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            m.lighting(&light, &position, &eyev, &normalv, 1.0),
+            Color::new(0.1, 0.1, 0.1)
+        );
+    }
+
+    #[test]
+    fn should_sample_bounces_by_shading_model() {
+        let normalv = Vector::new(0.0, 1.0, 0.0);
+        let eyev = Vector::new(0.0, 1.0, 0.0);
+        let phong = Material {
+            color: Color::new(1.0, 0.5, 0.0),
+            diffuse: 0.5,
+            ..Default::default()
+        };
+        let (direction, weight) = phong.sample_bounce(&normalv, &eyev).unwrap();
+        assert!(direction.dot_product(&normalv) >= 0.0);
+        assert_eq!(weight, Color::new(0.5, 0.25, 0.0));
+        assert_eq!(
+            phong.brdf(&normalv, &eyev, &direction),
+            Color::new(0.5 / PI, 0.25 / PI, 0.0)
+        );
+
+        // a mirror-like metal sends most bounces back along the normal
+        let metal = Material::pbr(Color::new(1.0, 1.0, 1.0), 1.0, 0.0);
+        let mirrored = (0..100)
+            .filter_map(|_| metal.sample_bounce(&normalv, &eyev))
+            .filter(|(direction, _)| direction.dot_product(&normalv) > 0.99)
+            .count();
+        assert!(mirrored > 50);
+    }
+
+    #[test]
+    fn should_light_with_the_eye_between_light_and_surface() {
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let result = m.lighting(&light, &position, &eyev, &normalv, 1.0);
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn should_light_with_the_eye_offset_45_degrees() {
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(
+            0.0,
+            (2.0 as Float).sqrt() / 2.0,
+            -((2.0 as Float).sqrt()) / 2.0,
+        );
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let result = m.lighting(&light, &position, &eyev, &normalv, 1.0);
+        assert_eq!(result, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn should_light_with_the_light_offset_45_degrees() {
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let result = m.lighting(&light, &position, &eyev, &normalv, 1.0);
+        let expected = 0.1 + 0.9 * (2.0 as Float).sqrt() / 2.0;
+        assert_eq!(result, Color::new(expected, expected, expected));
+    }
+
+    #[test]
+    fn should_light_with_the_light_behind_the_surface() {
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+        let result = m.lighting(&light, &position, &eyev, &normalv, 1.0);
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn should_scale_diffuse_and_specular_by_light_intensity() {
+        let m = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            ..Default::default()
+        };
+        let position = Point::new(0.0, 0.0, -1.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(
+            m.lighting(&light, &position, &eyev, &normalv, 0.5),
+            Color::new(0.55, 0.55, 0.55)
+        );
+        assert_eq!(
+            m.lighting(&light, &position, &eyev, &normalv, 0.0),
+            Color::new(0.1, 0.1, 0.1)
+        );
+    }
+
+    #[test]
+    fn should_average_the_samples_of_an_area_light() {
+        let mut light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5.0),
+            Vector::new(1.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 1.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        let m = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            color: Color::new(1.0, 1.0, 1.0),
+            ..Default::default()
+        };
+        let position = Point::new(0.0, 0.0, -1.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        // every sample is at the same angle, sqrt(0.25² + 0.25² + 4²)
+        let cos = 4.0 / ((0.125 as Float) + 16.0).sqrt();
+        let expected = 0.1 + 0.9 * cos;
+        assert_eq!(
+            m.lighting(&light, &position, &eyev, &normalv, 1.0),
+            Color::new(expected, expected, expected)
+        );
+    }
+}
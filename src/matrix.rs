@@ -1,5 +1,8 @@
+use crate::error::{RayTracerError, Result};
 use crate::point::Point;
-use crate::utils::EPSILON;
+use crate::quaternion::Quaternion;
+use crate::transform::Decomposition;
+use crate::utils::{Float, EPSILON};
 use crate::vector::Vector;
 use std::cmp::PartialEq;
 use std::ops::Add;
@@ -8,7 +11,7 @@ use std::ops::Sub;
 
 #[derive(Debug)]
 pub struct Matrix {
-    matrix: Vec<Vec<f32>>,
+    matrix: Vec<Vec<Float>>,
     pub row_count: usize,
     pub col_count: usize,
 }
@@ -26,16 +29,8 @@ impl Clone for Matrix {
 #[allow(dead_code)]
 impl Matrix {
     pub fn new(row_num: usize, col_num: usize) -> Self {
-        let mut m = Vec::new();
-        for _ in 0..row_num {
-            let mut v = Vec::new();
-            for _ in 0..col_num {
-                v.push(0.0);
-            }
-            m.push(v);
-        }
         Matrix {
-            matrix: m,
+            matrix: vec![vec![0.0; col_num]; row_num],
             row_count: row_num,
             col_count: col_num,
         }
@@ -55,7 +50,7 @@ impl Matrix {
         matrix
     }
 
-    pub fn translation_3d(x: f32, y: f32, z: f32) -> Matrix {
+    pub fn translation_3d(x: Float, y: Float, z: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_cell(0, 3, x);
         m.write_cell(1, 3, y);
@@ -63,7 +58,7 @@ impl Matrix {
         m
     }
 
-    pub fn scaling_3d(x: f32, y: f32, z: f32) -> Matrix {
+    pub fn scaling_3d(x: Float, y: Float, z: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_cell(0, 0, x);
         m.write_cell(1, 1, y);
@@ -71,7 +66,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotate_x_3d(rad: f32) -> Matrix {
+    pub fn rotate_x_3d(rad: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_cell(1, 1, rad.cos());
         m.write_cell(1, 2, -rad.sin());
@@ -80,7 +75,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotate_y_3d(rad: f32) -> Matrix {
+    pub fn rotate_y_3d(rad: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_cell(0, 0, rad.cos());
         m.write_cell(0, 2, rad.sin());
@@ -89,7 +84,7 @@ impl Matrix {
         m
     }
 
-    pub fn rotate_z_3d(rad: f32) -> Matrix {
+    pub fn rotate_z_3d(rad: Float) -> Matrix {
         let mut m = Matrix::identity(4);
         m.write_cell(0, 0, rad.cos());
         m.write_cell(0, 1, -rad.sin());
@@ -98,7 +93,20 @@ impl Matrix {
         m
     }
 
-    pub fn shear_3d(x_y: f32, x_z: f32, y_x: f32, y_z: f32, z_x: f32, z_y: f32) -> Matrix {
+    /// Rotation of `rad` radians around any axis going through the origin, a zero axis
+    /// gives the identity
+    pub fn rotate_axis_3d(axis: &Vector, rad: Float) -> Matrix {
+        Quaternion::from_axis_angle(axis, rad).to_matrix()
+    }
+
+    pub fn shear_3d(
+        x_y: Float,
+        x_z: Float,
+        y_x: Float,
+        y_z: Float,
+        z_x: Float,
+        z_y: Float,
+    ) -> Matrix {
         let mut m = Matrix::identity(4);
         // x
         m.write_cell(0, 1, x_y);
@@ -112,40 +120,116 @@ impl Matrix {
         m
     }
 
-    pub fn get_cell(&self, row: usize, col: usize) -> f32 {
+    /// Orients the world relative to the eye, looking from `from` to `to`
+    pub fn view_transform(from: &Point, to: &Point, up: &Vector) -> Matrix {
+        match Matrix::try_view_transform(from, to, up) {
+            Ok(transform) => transform,
+            Err(error) => panic!("Matrix::view_transform {}", error),
+        }
+    }
+
+    /// Rejects an eye at the point it looks at and an up vector parallel to the view
+    pub fn try_view_transform(from: &Point, to: &Point, up: &Vector) -> Result<Matrix> {
+        let direction = to - from;
+        if direction.magnitude() < EPSILON {
+            return Err(RayTracerError::InvalidArgument(
+                "the eye is at the point it looks at".to_string(),
+            ));
+        }
+        if up.magnitude() < EPSILON
+            || direction
+                .normalize()
+                .cross_product(&up.normalize())
+                .magnitude()
+                < EPSILON
+        {
+            return Err(RayTracerError::InvalidArgument(
+                "the up vector is parallel to the view direction".to_string(),
+            ));
+        }
+        let forward = (to - from).normalize();
+        let left = forward.cross_product(&up.normalize());
+        let true_up = left.cross_product(&forward);
+        let mut orientation = Matrix::new(4, 4);
+        orientation.patch(vec![
+            vec![left.x, left.y, left.z, 0.0],
+            vec![true_up.x, true_up.y, true_up.z, 0.0],
+            vec![-forward.x, -forward.y, -forward.z, 0.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+        Ok(&orientation * &Matrix::translation_3d(-from.x, -from.y, -from.z))
+    }
+
+    /// Cell by cell linear interpolation, exact for translations and scaling, rotations
+    /// between the two matrices are only approximated
+    pub fn lerp(&self, other: &Matrix, t: Float) -> Matrix {
+        &(self * (1.0 - t)) + &(other * t)
+    }
+
+    /// The first 3 rows of a 4x4 matrix multiplied by a tuple, None for other sizes.
+    /// Much faster than going through a 4x1 matrix
+    fn rows_dot(&self, tuple: [Float; 4]) -> Option<[Float; 3]> {
+        if self.row_count != 4 || self.col_count != 4 {
+            return None;
+        }
+        let row = |r: usize| {
+            let row = &self.matrix[r];
+            row[0] * tuple[0] + row[1] * tuple[1] + row[2] * tuple[2] + row[3] * tuple[3]
+        };
+        Some([row(0), row(1), row(2)])
+    }
+
+    pub fn get_cell(&self, row: usize, col: usize) -> Float {
         self.matrix[row][col]
     }
 
-    pub fn write_cell(&mut self, row: usize, col: usize, value: f32) {
+    pub fn write_cell(&mut self, row: usize, col: usize, value: Float) {
         self.matrix[row][col] = value;
     }
 
-    pub fn patch(&mut self, new_value: Vec<Vec<f32>>) {
-        if new_value.len() != self.row_count || new_value[0].len() != self.col_count {
-            panic!("The new value doesn't have the same dimensions");
+    pub fn patch(&mut self, new_value: Vec<Vec<Float>>) {
+        if let Err(error) = self.try_patch(new_value) {
+            panic!("Matrix::patch {}", error);
+        }
+    }
+
+    /// Replaces every cell, the values must have the dimensions of the matrix
+    pub fn try_patch(&mut self, new_value: Vec<Vec<Float>>) -> Result<()> {
+        let found = (
+            new_value.len(),
+            new_value.first().map_or(0, |row| row.len()),
+        );
+        if found != (self.row_count, self.col_count)
+            || new_value.iter().any(|row| row.len() != self.col_count)
+        {
+            return Err(RayTracerError::DimensionMismatch {
+                expected: (self.row_count, self.col_count),
+                found,
+            });
         }
 
-        for row_index in 0..self.row_count {
-            for col_index in 0..self.col_count {
-                self.write_cell(row_index, col_index, new_value[row_index][col_index]);
+        for (row_index, row) in new_value.iter().enumerate() {
+            for (col_index, value) in row.iter().enumerate() {
+                self.write_cell(row_index, col_index, *value);
             }
         }
+        Ok(())
     }
 
-    pub fn get_col(&self, col_index: usize) -> Vec<f32> {
-        let mut vec: Vec<f32> = Vec::new();
+    pub fn get_col(&self, col_index: usize) -> Vec<Float> {
+        let mut vec: Vec<Float> = Vec::new();
         for row_index in 0..self.row_count {
             vec.push(self.get_cell(row_index, col_index));
         }
-        return vec;
+        vec
     }
 
-    pub fn get_row(&self, row_index: usize) -> Vec<f32> {
-        let mut vec: Vec<f32> = Vec::new();
+    pub fn get_row(&self, row_index: usize) -> Vec<Float> {
+        let mut vec: Vec<Float> = Vec::new();
         for col_index in 0..self.col_count {
             vec.push(self.get_cell(row_index, col_index));
         }
-        return vec;
+        vec
     }
 
     pub fn sub_matrix(&self, row_index: usize, col_index: usize) -> Matrix {
@@ -164,25 +248,37 @@ impl Matrix {
         new_matrix
     }
 
-    pub fn determinant(&self) -> f32 {
+    pub fn determinant(&self) -> Float {
+        match self.try_determinant() {
+            Ok(det) => det,
+            Err(error) => panic!("Matrix::determinant {}", error),
+        }
+    }
+
+    pub fn try_determinant(&self) -> Result<Float> {
         if !self.is_square() {
-            panic!(
-                "Cofactor::matrix not squared {}x{}",
-                self.row_count, self.col_count
-            );
+            return Err(RayTracerError::NotSquare {
+                rows: self.row_count,
+                cols: self.col_count,
+            });
         }
-        if self.row_count == 2 {
-            return self.get_cell(0, 0) * self.get_cell(1, 1)
-                - self.get_cell(0, 1) * self.get_cell(1, 0);
+        match self.row_count {
+            0 => return Ok(1.0),
+            1 => return Ok(self.get_cell(0, 0)),
+            2 => {
+                return Ok(self.get_cell(0, 0) * self.get_cell(1, 1)
+                    - self.get_cell(0, 1) * self.get_cell(1, 0))
+            }
+            _ => {}
         }
 
         let mut det = 0.0;
         for c_i in 0..self.col_count {
-            det += (-1f32).powf(0f32 + c_i as f32 + 2f32)
+            det += (-1.0 as Float).powf(c_i as Float + 2.0)
                 * self.get_cell(0, c_i)
-                * self.sub_matrix(0, c_i).determinant();
+                * self.sub_matrix(0, c_i).try_determinant()?;
         }
-        det
+        Ok(det)
     }
 
     pub fn transpose(&self) -> Matrix {
@@ -199,15 +295,16 @@ impl Matrix {
         self.row_count == self.col_count
     }
 
+    /// False for non square matrices and when the determinant is 0 or not a number
     pub fn has_inverse(&self) -> bool {
-        self.determinant() != 0.0
+        matches!(self.try_determinant(), Ok(det) if det != 0.0 && det.is_finite())
     }
 
     fn co_factors_transpose_matrix(&self) -> Matrix {
         let mut co_matrix = Matrix::new(self.row_count, self.col_count);
         for r_i in 0..self.row_count {
             for c_i in 0..self.col_count {
-                let co_factor = (-1f32).powf(r_i as f32 + c_i as f32 + 2.0)
+                let co_factor = (-1.0 as Float).powf(r_i as Float + c_i as Float + 2.0)
                     * self.sub_matrix(r_i, c_i).determinant();
                 co_matrix.write_cell(c_i, r_i, co_factor);
             }
@@ -216,11 +313,78 @@ impl Matrix {
     }
 
     pub fn inverse(&self) -> Matrix {
-        if !self.has_inverse() {
-            panic!("Inverse::matrix doesn't have inverse");
+        match self.try_inverse() {
+            Ok(inverse) => inverse,
+            Err(error) => panic!("Matrix::inverse {}", error),
+        }
+    }
+
+    pub fn try_inverse(&self) -> Result<Matrix> {
+        let det = self.try_determinant()?;
+        if det == 0.0 || !det.is_finite() {
+            return Err(RayTracerError::SingularMatrix);
         }
         let co_factors_transpose_matrix = self.co_factors_transpose_matrix();
-        &co_factors_transpose_matrix * (1f32 / self.determinant())
+        Ok(&co_factors_transpose_matrix * (1.0 / det))
+    }
+
+    /// Translation, rotation and scale of an affine 4x4 matrix, the inverse of
+    /// `Decomposition::to_matrix`. Matrices with a shear or a projection have no such parts
+    /// and are rejected, like the ones that flatten a dimension
+    pub fn decompose(&self) -> Result<Decomposition> {
+        if self.row_count != 4 || self.col_count != 4 {
+            return Err(RayTracerError::DimensionMismatch {
+                expected: (4, 4),
+                found: (self.row_count, self.col_count),
+            });
+        }
+        if self.get_row(3) != [0.0, 0.0, 0.0, 1.0] {
+            return Err(RayTracerError::InvalidArgument(
+                "the matrix has a projection, it can't be decomposed".to_string(),
+            ));
+        }
+        let translation = Vector::new(
+            self.get_cell(0, 3),
+            self.get_cell(1, 3),
+            self.get_cell(2, 3),
+        );
+        let mut axes = [0, 1, 2].map(|c| {
+            Vector::new(
+                self.get_cell(0, c),
+                self.get_cell(1, c),
+                self.get_cell(2, c),
+            )
+        });
+        let mut scale = axes.map(|axis| axis.magnitude());
+        if scale.iter().any(|s| *s < EPSILON) {
+            return Err(RayTracerError::SingularMatrix);
+        }
+        // a mirror shows as a left handed basis, it goes in the x scale so the rest is a rotation
+        if axes[0].cross_product(&axes[1]).dot_product(&axes[2]) < 0.0 {
+            scale[0] = -scale[0];
+        }
+        for (axis, s) in axes.iter_mut().zip(scale) {
+            *axis = &*axis / s;
+        }
+        let orthogonal = axes[0].dot_product(&axes[1]).abs() < EPSILON * 10.0
+            && axes[0].dot_product(&axes[2]).abs() < EPSILON * 10.0
+            && axes[1].dot_product(&axes[2]).abs() < EPSILON * 10.0;
+        if !orthogonal {
+            return Err(RayTracerError::InvalidArgument(
+                "the matrix has a shear, it can't be decomposed".to_string(),
+            ));
+        }
+        let mut rotation = Matrix::identity(4);
+        for (c, axis) in axes.iter().enumerate() {
+            rotation.write_cell(0, c, axis.x);
+            rotation.write_cell(1, c, axis.y);
+            rotation.write_cell(2, c, axis.z);
+        }
+        Ok(Decomposition {
+            translation,
+            rotation: Quaternion::from_matrix(&rotation),
+            scale: Vector::new(scale[0], scale[1], scale[2]),
+        })
     }
 }
 
@@ -294,10 +458,10 @@ impl Mul<&Matrix> for &Matrix {
     }
 }
 
-impl Mul<f32> for &Matrix {
+impl Mul<Float> for &Matrix {
     type Output = Matrix;
 
-    fn mul(self, other: f32) -> Matrix {
+    fn mul(self, other: Float) -> Matrix {
         let mut new_matrix = Matrix::new(self.row_count, self.col_count);
         for row_index in 0..self.row_count {
             for col_index in 0..self.col_count {
@@ -316,6 +480,9 @@ impl Mul<&Point> for &Matrix {
     type Output = Point;
 
     fn mul(self, p: &Point) -> Point {
+        if let Some([x, y, z]) = self.rows_dot([p.x, p.y, p.z, p.w]) {
+            return Point::new(x, y, z);
+        }
         let point_matrix = p.to_matrix();
         let new_point_matrix = self * &point_matrix;
         Point::new(
@@ -330,6 +497,9 @@ impl Mul<&Vector> for &Matrix {
     type Output = Vector;
 
     fn mul(self, v: &Vector) -> Vector {
+        if let Some([x, y, z]) = self.rows_dot([v.x, v.y, v.z, v.w]) {
+            return Vector::new(x, y, z);
+        }
         let vector_matrix = v.to_matrix();
         let new_vector_matrix = self * &vector_matrix;
         Vector::new(
@@ -356,8 +526,9 @@ impl PartialEq for Matrix {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::op_ref)]
 mod tests {
-    use std::f32::consts::PI;
+    use crate::utils::consts::PI;
 
     use super::*;
 
@@ -899,4 +1070,114 @@ mod tests {
         assert_eq!((p2.z - 7.0).abs() < EPSILON, true);
         assert_eq!(p2.w, 1.0);
     }
+
+    #[test]
+    fn should_get_the_default_view_transform() {
+        let t = Matrix::view_transform(
+            &Point::new(0.0, 0.0, 0.0),
+            &Point::new(0.0, 0.0, -1.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        assert_eq!(t, Matrix::identity(4));
+    }
+
+    #[test]
+    fn should_get_the_view_transform_looking_in_positive_z() {
+        let t = Matrix::view_transform(
+            &Point::new(0.0, 0.0, 0.0),
+            &Point::new(0.0, 0.0, 1.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        assert_eq!(t, Matrix::scaling_3d(-1.0, 1.0, -1.0));
+    }
+
+    #[test]
+    fn should_move_the_world_with_the_view_transform() {
+        let t = Matrix::view_transform(
+            &Point::new(0.0, 0.0, 8.0),
+            &Point::new(0.0, 0.0, 0.0),
+            &Vector::new(0.0, 1.0, 0.0),
+        );
+        assert_eq!(t, Matrix::translation_3d(0.0, 0.0, -8.0));
+    }
+
+    #[test]
+    fn should_get_an_arbitrary_view_transform() {
+        let t = Matrix::view_transform(
+            &Point::new(1.0, 3.0, 2.0),
+            &Point::new(4.0, -2.0, 8.0),
+            &Vector::new(1.0, 1.0, 0.0),
+        );
+        let mut expected = Matrix::new(4, 4);
+        expected.patch(vec![
+            vec![-0.50709255, 0.50709255, 0.6761234, -2.3664317],
+            vec![0.76771593, 0.6060915, 0.12121832, -2.828427],
+            vec![-0.35856858, 0.59761435, -0.71713716, 0.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(t, expected);
+    }
+
+    #[test]
+    fn should_interpolate_matrices() {
+        let a = Matrix::translation_3d(0.0, 0.0, 0.0);
+        let b = Matrix::translation_3d(4.0, -2.0, 8.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.25), Matrix::translation_3d(1.0, -0.5, 2.0));
+    }
+
+    #[test]
+    fn should_reject_singular_and_non_square_matrices() {
+        let mut singular = Matrix::new(4, 4);
+        singular.patch(vec![
+            vec![-4.0, 2.0, -2.0, -3.0],
+            vec![9.0, 6.0, 2.0, 6.0],
+            vec![0.0, -5.0, 1.0, -5.0],
+            vec![0.0, 0.0, 0.0, 0.0],
+        ]);
+        assert_eq!(singular.try_inverse(), Err(RayTracerError::SingularMatrix));
+        let not_square = Matrix::new(2, 3);
+        assert_eq!(
+            not_square.try_determinant(),
+            Err(RayTracerError::NotSquare { rows: 2, cols: 3 })
+        );
+        assert!(!not_square.has_inverse());
+        assert_eq!(
+            Matrix::scaling_3d(2.0, 2.0, 2.0).try_inverse(),
+            Ok(Matrix::scaling_3d(0.5, 0.5, 0.5))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix::inverse the matrix doesn't have an inverse")]
+    fn should_panic_when_inverting_a_singular_matrix() {
+        Matrix::scaling_3d(1.0, 0.0, 1.0).inverse();
+    }
+
+    #[test]
+    fn should_reject_patches_of_another_size() {
+        let mut matrix = Matrix::new(2, 2);
+        assert_eq!(
+            matrix.try_patch(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]),
+            Err(RayTracerError::DimensionMismatch {
+                expected: (2, 2),
+                found: (2, 3)
+            })
+        );
+        assert!(matrix.try_patch(vec![vec![1.0, 2.0], vec![3.0]]).is_err());
+        assert_eq!(matrix, Matrix::new(2, 2));
+    }
+
+    #[test]
+    fn should_reject_degenerate_view_transforms() {
+        let eye = Point::new(1.0, 2.0, 3.0);
+        assert!(Matrix::try_view_transform(&eye, &eye, &Vector::new(0.0, 1.0, 0.0)).is_err());
+        assert!(Matrix::try_view_transform(
+            &eye,
+            &Point::new(1.0, 5.0, 3.0),
+            &Vector::new(0.0, 1.0, 0.0)
+        )
+        .is_err());
+    }
 }
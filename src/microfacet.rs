@@ -0,0 +1,240 @@
+use crate::{
+    color::Color,
+    utils::{consts::PI, random_cosine_direction, random_float, Float},
+    vector::Vector,
+};
+
+/// Reflectance of dielectrics at normal incidence
+const DIELECTRIC_F0: Float = 0.04;
+
+/// Perfectly smooth surfaces make the distribution a dirac, the roughness is kept above this
+const MIN_ALPHA: Float = 0.001;
+
+/// GGX / Cook-Torrance parameters, `roughness` is perceptual and squared into alpha
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Microfacet {
+    pub metallic: Float,
+    pub roughness: Float,
+}
+
+#[allow(dead_code)]
+impl Microfacet {
+    pub fn new(metallic: Float, roughness: Float) -> Self {
+        Microfacet {
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn alpha(&self) -> Float {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    /// Metals tint their reflection with the base color, dielectrics reflect a few percent
+    pub fn f0(&self, base_color: &Color) -> Color {
+        let dielectric = Color::new(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0);
+        &(&dielectric * (1.0 - self.metallic)) + &(base_color * self.metallic)
+    }
+
+    /// Value of the brdf for light coming from `lightv` and leaving towards `eyev`, the
+    /// cosine term is not included
+    pub fn brdf(
+        &self,
+        base_color: &Color,
+        normalv: &Vector,
+        eyev: &Vector,
+        lightv: &Vector,
+    ) -> Color {
+        let n_dot_l = normalv.dot_product(lightv);
+        let n_dot_v = normalv.dot_product(eyev);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let halfway = (eyev + lightv).normalize();
+        let n_dot_h = normalv.dot_product(&halfway).max(0.0);
+        let v_dot_h = eyev.dot_product(&halfway).max(0.0);
+        let alpha = self.alpha();
+
+        let fresnel = fresnel_schlick(v_dot_h, &self.f0(base_color));
+        let specular = &fresnel
+            * (ggx_distribution(n_dot_h, alpha) * smith_g(n_dot_v, n_dot_l, alpha)
+                / (4.0 * n_dot_v * n_dot_l));
+        // light that isn't reflected by the coating is scattered by the body, metals have none
+        let kd = &Color::new(1.0, 1.0, 1.0) - &fresnel;
+        let diffuse = &(&kd * base_color) * ((1.0 - self.metallic) / PI);
+        &diffuse + &specular
+    }
+
+    /// Chance of sampling the specular lobe instead of the diffuse one
+    fn specular_probability(&self, base_color: &Color) -> Float {
+        let f0 = self.f0(base_color);
+        let specular = (f0.r + f0.g + f0.b) / 3.0;
+        let diffuse = (1.0 - self.metallic) * (base_color.r + base_color.g + base_color.b) / 3.0;
+        if specular + diffuse <= 0.0 {
+            return 1.0;
+        }
+        (specular / (specular + diffuse)).clamp(0.1, 0.9)
+    }
+
+    /// Probability density (solid angle) of `sample` picking `lightv`
+    pub fn pdf(
+        &self,
+        base_color: &Color,
+        normalv: &Vector,
+        eyev: &Vector,
+        lightv: &Vector,
+    ) -> Float {
+        let n_dot_l = normalv.dot_product(lightv);
+        if n_dot_l <= 0.0 {
+            return 0.0;
+        }
+        let halfway = (eyev + lightv).normalize();
+        let n_dot_h = normalv.dot_product(&halfway).max(0.0);
+        let v_dot_h = eyev.dot_product(&halfway);
+        let specular_pdf = if v_dot_h <= 0.0 {
+            0.0
+        } else {
+            ggx_distribution(n_dot_h, self.alpha()) * n_dot_h / (4.0 * v_dot_h)
+        };
+        let diffuse_pdf = n_dot_l / PI;
+        let p = self.specular_probability(base_color);
+        p * specular_pdf + (1.0 - p) * diffuse_pdf
+    }
+
+    /// Picks a direction for the next bounce, either around the mirror direction (GGX
+    /// distribution of the normals) or around the normal (cosine distribution)
+    pub fn sample(&self, base_color: &Color, normalv: &Vector, eyev: &Vector) -> Vector {
+        if random_float() < self.specular_probability(base_color) {
+            let halfway = sample_ggx_normal(normalv, self.alpha());
+            (-eyev).reflect(&halfway)
+        } else {
+            random_cosine_direction(normalv)
+        }
+    }
+}
+
+/// GGX (Trowbridge-Reitz) normal distribution
+pub fn ggx_distribution(n_dot_h: Float, alpha: Float) -> Float {
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denominator * denominator)
+}
+
+/// Smith masking-shadowing, product of the separable GGX terms of both directions
+pub fn smith_g(n_dot_v: Float, n_dot_l: Float, alpha: Float) -> Float {
+    let g1 = |cos: Float| {
+        let alpha2 = alpha * alpha;
+        2.0 * cos / (cos + (alpha2 + (1.0 - alpha2) * cos * cos).sqrt())
+    };
+    g1(n_dot_v) * g1(n_dot_l)
+}
+
+/// Schlick approximation of the Fresnel reflectance
+pub fn fresnel_schlick(cos: Float, f0: &Color) -> Color {
+    let factor = (1.0 - cos).clamp(0.0, 1.0).powi(5);
+    &(f0 * (1.0 - factor)) + &(&Color::new(1.0, 1.0, 1.0) * factor)
+}
+
+/// Microfacet normal distributed as D(h) * cos(theta_h)
+fn sample_ggx_normal(normalv: &Vector, alpha: Float) -> Vector {
+    let (u1, u2) = (random_float(), random_float());
+    let cos_theta = ((1.0 - u1) / (1.0 + (alpha * alpha - 1.0) * u1)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    let (tangent, bitangent) = normalv.orthonormal_basis();
+    &(&(&tangent * (sin_theta * phi.cos())) + &(&bitangent * (sin_theta * phi.sin())))
+        + &(normalv * cos_theta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::EPSILON;
+
+    #[test]
+    fn should_evaluate_the_ggx_distribution() {
+        assert!((ggx_distribution(1.0, 1.0) - 1.0 / PI).abs() < EPSILON);
+        assert!((ggx_distribution(0.3, 1.0) - 1.0 / PI).abs() < EPSILON);
+        // smoother surfaces concentrate the normals around the macro normal
+        assert!(ggx_distribution(1.0, 0.1) > ggx_distribution(1.0, 0.5));
+        assert!(ggx_distribution(0.5, 0.1) < ggx_distribution(0.5, 0.5));
+    }
+
+    #[test]
+    fn should_not_mask_at_normal_incidence() {
+        assert!((smith_g(1.0, 1.0, 0.5) - 1.0).abs() < EPSILON);
+        assert!(smith_g(0.1, 1.0, 0.5) < smith_g(0.5, 1.0, 0.5));
+    }
+
+    #[test]
+    fn should_reflect_everything_at_grazing_angles() {
+        let f0 = Color::new(0.04, 0.04, 0.04);
+        assert_eq!(fresnel_schlick(1.0, &f0), f0);
+        assert_eq!(fresnel_schlick(0.0, &f0), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn should_tint_the_reflection_of_metals() {
+        let gold = Color::new(1.0, 0.8, 0.3);
+        assert_eq!(Microfacet::new(1.0, 0.5).f0(&gold), gold);
+        assert_eq!(
+            Microfacet::new(0.0, 0.5).f0(&gold),
+            Color::new(0.04, 0.04, 0.04)
+        );
+        assert_eq!(Microfacet::new(2.0, -1.0), Microfacet::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn should_evaluate_the_brdf_head_on() {
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        // D = 1 / PI, G = 1 and F = F0 when every vector is the normal
+        let metal = Microfacet::new(1.0, 1.0);
+        let expected = 1.0 / (4.0 * PI);
+        assert_eq!(
+            metal.brdf(&white, &normalv, &normalv, &normalv),
+            Color::new(expected, expected, expected)
+        );
+        let plastic = Microfacet::new(0.0, 1.0);
+        let expected = 0.96 / PI + 0.01 / PI;
+        assert_eq!(
+            plastic.brdf(&white, &normalv, &normalv, &normalv),
+            Color::new(expected, expected, expected)
+        );
+        let below = Vector::new(0.0, 0.0, 1.0);
+        assert_eq!(
+            plastic.brdf(&white, &normalv, &normalv, &below),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn should_not_create_energy() {
+        // estimates the albedo with the sampling routine, rough surfaces lose some energy
+        // since light bouncing between microfacets is ignored, but none may gain any
+        let normalv = Vector::new(0.0, 1.0, 0.0);
+        let eyev = Vector::new(0.0, 1.0, 1.0).normalize();
+        let white = Color::new(1.0, 1.0, 1.0);
+        for microfacet in [
+            Microfacet::new(1.0, 0.3),
+            Microfacet::new(0.0, 0.5),
+            Microfacet::new(0.5, 0.9),
+        ] {
+            let samples = 4000;
+            let mut sum = 0.0;
+            for _ in 0..samples {
+                let lightv = microfacet.sample(&white, &normalv, &eyev);
+                let pdf = microfacet.pdf(&white, &normalv, &eyev, &lightv);
+                if pdf <= 0.0 {
+                    continue;
+                }
+                let f = microfacet.brdf(&white, &normalv, &eyev, &lightv);
+                sum += f.g * normalv.dot_product(&lightv) / pdf;
+            }
+            let albedo = sum / samples as Float;
+            assert!(albedo > 0.3);
+            assert!(albedo < 1.02);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::point::Point;
+use crate::utils::Float;
+
+/// Gradient noise generator used by procedural patterns
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum NoiseKind {
+    /// Ken Perlin's improved noise, on a cubic lattice
+    #[default]
+    Perlin,
+    /// Simplex noise, fewer directional artifacts and cheaper in 3D
+    Simplex,
+}
+
+/// Deterministic 3D gradient noise, the same seed always gives the same values
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    /// Lattice hash, the 256 shuffled values are repeated to avoid wrapping the indices
+    permutation: Vec<usize>,
+}
+
+/// Corners of the simplex gradients, the middles of the edges of a cube
+const GRADIENTS: [[Float; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+#[allow(dead_code)]
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut values: Vec<usize> = (0..256).collect();
+        // Fisher-Yates with splitmix64, so the sequence doesn't depend on the rand crate
+        let mut state = seed;
+        for i in (1..values.len()).rev() {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            values.swap(i, (z % (i as u64 + 1)) as usize);
+        }
+        let permutation = values.iter().chain(values.iter()).copied().collect();
+        Noise {
+            kind: NoiseKind::Perlin,
+            permutation,
+        }
+    }
+
+    pub fn with_kind(seed: u64, kind: NoiseKind) -> Self {
+        Noise {
+            kind,
+            ..Noise::new(seed)
+        }
+    }
+
+    /// Value roughly in [-1, 1] of the noise selected by `kind`
+    pub fn noise(&self, point: &Point) -> Float {
+        match self.kind {
+            NoiseKind::Perlin => self.perlin(point),
+            NoiseKind::Simplex => self.simplex(point),
+        }
+    }
+
+    /// Fractal brownian motion, octaves of noise with doubling frequency and halving
+    /// amplitude, normalized back to [-1, 1]
+    pub fn fbm(&self, point: &Point, octaves: usize) -> Float {
+        self.octaves(point, octaves, |value| value)
+    }
+
+    /// Like `fbm` but adds the absolute value of each octave, the result is in [0, 1]
+    pub fn turbulence(&self, point: &Point, octaves: usize) -> Float {
+        self.octaves(point, octaves, Float::abs)
+    }
+
+    fn octaves(&self, point: &Point, octaves: usize, shape: fn(Float) -> Float) -> Float {
+        let (mut sum, mut total_amplitude) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for _ in 0..octaves.max(1) {
+            let scaled = Point::new(
+                point.x * frequency,
+                point.y * frequency,
+                point.z * frequency,
+            );
+            sum += amplitude * shape(self.noise(&scaled));
+            total_amplitude += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum / total_amplitude
+    }
+
+    pub fn perlin(&self, point: &Point) -> Float {
+        let (x0, y0, z0) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let (x, y, z) = (point.x - x0, point.y - y0, point.z - z0);
+        let (xi, yi, zi) = (lattice(x0), lattice(y0), lattice(z0));
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let p = &self.permutation;
+        let a = p[xi] + yi;
+        let (aa, ab) = (p[a] + zi, p[a + 1] + zi);
+        let b = p[xi + 1] + yi;
+        let (ba, bb) = (p[b] + zi, p[b + 1] + zi);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    pub fn simplex(&self, point: &Point) -> Float {
+        const F3: Float = 1.0 / 3.0;
+        const G3: Float = 1.0 / 6.0;
+        // skews the space so the simplex containing the point is easy to find
+        let s = (point.x + point.y + point.z) * F3;
+        let (i, j, k) = (
+            (point.x + s).floor(),
+            (point.y + s).floor(),
+            (point.z + s).floor(),
+        );
+        let t = (i + j + k) * G3;
+        let (x0, y0, z0) = (point.x - (i - t), point.y - (j - t), point.z - (k - t));
+
+        let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y0 < z0 {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x0 < z0 {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        let corners = [
+            (x0, y0, z0, 0, 0, 0),
+            (
+                x0 - i1 as Float + G3,
+                y0 - j1 as Float + G3,
+                z0 - k1 as Float + G3,
+                i1,
+                j1,
+                k1,
+            ),
+            (
+                x0 - i2 as Float + 2.0 * G3,
+                y0 - j2 as Float + 2.0 * G3,
+                z0 - k2 as Float + 2.0 * G3,
+                i2,
+                j2,
+                k2,
+            ),
+            (
+                x0 - 1.0 + 3.0 * G3,
+                y0 - 1.0 + 3.0 * G3,
+                z0 - 1.0 + 3.0 * G3,
+                1,
+                1,
+                1,
+            ),
+        ];
+
+        let (ii, jj, kk) = (lattice(i), lattice(j), lattice(k));
+        let p = &self.permutation;
+        let mut sum = 0.0;
+        for (x, y, z, di, dj, dk) in corners {
+            let falloff = 0.6 - x * x - y * y - z * z;
+            if falloff <= 0.0 {
+                continue;
+            }
+            let gradient = GRADIENTS[p[ii + di + p[jj + dj + p[kk + dk]]] % 12];
+            let dot = gradient[0] * x + gradient[1] * y + gradient[2] * z;
+            sum += falloff.powi(4) * dot;
+        }
+        // scales the result to about [-1, 1]
+        32.0 * sum
+    }
+}
+
+fn lattice(value: Float) -> usize {
+    (value as i64).rem_euclid(256) as usize
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: Float, a: Float, b: Float) -> Float {
+    a + t * (b - a)
+}
+
+/// Dot product of the offset with one of 12 gradient directions picked by the hash
+fn grad(hash: usize, x: Float, y: Float, z: Float) -> Float {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_points() -> impl Iterator<Item = Point> {
+        (0..500).map(|i| {
+            let i = i as Float;
+            Point::new(i * 0.173 - 20.0, i * 0.091 + 3.3, i * -0.057 + 0.5)
+        })
+    }
+
+    #[test]
+    fn should_be_reproducible_with_the_same_seed() {
+        let (a, b, c) = (Noise::new(7), Noise::new(7), Noise::new(8));
+        let point = Point::new(1.3, -4.2, 0.7);
+        assert_eq!(a.perlin(&point), b.perlin(&point));
+        assert_eq!(a.simplex(&point), b.simplex(&point));
+        assert!(sample_points().any(|p| a.perlin(&p) != c.perlin(&p)));
+    }
+
+    #[test]
+    fn should_vanish_on_the_perlin_lattice() {
+        let noise = Noise::new(1);
+        for point in [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(3.0, -2.0, 7.0),
+            Point::new(-300.0, 12.0, 1.0),
+        ] {
+            assert_eq!(noise.perlin(&point), 0.0);
+        }
+    }
+
+    #[test]
+    fn should_stay_in_range() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex] {
+            let noise = Noise::with_kind(42, kind);
+            let mut varies = false;
+            for point in sample_points() {
+                let value = noise.noise(&point);
+                assert!((-1.0..=1.0).contains(&value));
+                varies |= value.abs() > 0.1;
+                assert!((-1.0..=1.0).contains(&noise.fbm(&point, 5)));
+                assert!((0.0..=1.0).contains(&noise.turbulence(&point, 5)));
+            }
+            assert!(varies);
+        }
+    }
+
+    #[test]
+    fn should_be_continuous() {
+        let noise = Noise::with_kind(3, NoiseKind::Simplex);
+        let perlin = Noise::new(3);
+        for point in sample_points() {
+            let near = Point::new(point.x + 0.001, point.y, point.z - 0.001);
+            assert!((noise.noise(&point) - noise.noise(&near)).abs() < 0.05);
+            assert!((perlin.noise(&point) - perlin.noise(&near)).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn should_use_a_single_octave_as_plain_noise() {
+        let noise = Noise::new(5);
+        let point = Point::new(0.4, 1.7, -2.2);
+        assert_eq!(noise.fbm(&point, 1), noise.perlin(&point));
+        assert_eq!(noise.turbulence(&point, 1), noise.perlin(&point).abs());
+    }
+}
@@ -1,5 +1,69 @@
-use crate::{point::Point, vector::Vector};
+use std::fmt::Debug;
 
-pub trait Object { 
+use crate::{
+    bounds::Bounds, intersection::Intersection, material::Material, matrix::Matrix, point::Point,
+    ray::Ray, utils::Float, vector::Vector,
+};
+
+/// Point picked uniformly on the surface of an object, in world space
+#[derive(Debug, Copy, Clone)]
+pub struct SurfaceSample {
+    pub point: Point,
+    pub normal: Vector,
+    /// Area of the whole surface, the pdf of the sample is `1 / area`
+    pub area: Float,
+}
+
+#[allow(dead_code)]
+pub trait Object: Debug + Send + Sync {
     fn normal_at(&self, point: &Point) -> Vector;
+
+    /// Normal of objects that move during the exposure, by default they are static
+    fn normal_at_time(&self, point: &Point, _time: Float) -> Vector {
+        self.normal_at(point)
+    }
+
+    /// Always return the intersections sorted
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object>>;
+
+    fn transform(&self) -> &Matrix;
+
+    /// Transform at a time of the exposure, by default objects are static
+    fn transform_at(&self, _time: Float) -> Matrix {
+        self.transform().clone()
+    }
+
+    fn material(&self) -> &Material;
+
+    /// Pre-multiplies the current transform, used when a parent moves its children
+    fn apply_transform(&mut self, matrix: &Matrix);
+
+    /// Point in object space that texture mappings wrap around
+    fn mapping_center(&self) -> Point {
+        Point::new(0.0, 0.0, 0.0)
+    }
+
+    /// Directions in world space along which the texture coordinates stored on the shape
+    /// grow, shapes without coordinates return None
+    fn uv_directions(&self, _time: Float) -> Option<(Vector, Vector)> {
+        None
+    }
+
+    /// Bounding box in object space, before the transform is applied
+    fn bounds(&self) -> Bounds;
+
+    /// Bounding box in the space of the parent (world or group)
+    fn parent_space_bounds(&self) -> Bounds {
+        self.bounds().transform(self.transform())
+    }
+
+    /// Objects held by a composite object, primitives have none
+    fn children(&self) -> &[Box<dyn Object>] {
+        &[]
+    }
+
+    /// Used to sample emissive objects as lights, shapes that can't be sampled return None
+    fn sample_surface(&self, _time: Float) -> Option<SurfaceSample> {
+        None
+    }
 }
@@ -0,0 +1,359 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{
+    color::Color, matrix::Matrix, noise::Noise, object::Object, point::Point, utils::Float,
+};
+
+/// Color that changes over the surface of an object
+#[allow(dead_code)]
+pub trait Pattern: Debug + Send + Sync {
+    /// Color at a point in pattern space, `uv` are the texture coordinates of the hit when
+    /// the shape carries its own
+    fn pattern_at(&self, point: &Point, uv: Option<(Float, Float)>) -> Color;
+
+    fn transform(&self) -> &Matrix;
+
+    /// Color at a point in world space, the point goes through the object transform at
+    /// `time` and then the pattern transform, so patterns move with the object
+    fn pattern_at_object(
+        &self,
+        object: &dyn Object,
+        world_point: &Point,
+        uv: Option<(Float, Float)>,
+        time: Float,
+    ) -> Color {
+        let object_point = &object.transform_at(time).inverse() * world_point;
+        let pattern_point = &self.transform().inverse() * &object_point;
+        self.pattern_at(&pattern_point, uv)
+    }
+}
+
+/// Alternates between two colors along x, marble when perturbed
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct StripePattern {
+    pub a: Color,
+    pub b: Color,
+    pub transform: Matrix,
+}
+
+#[allow(dead_code)]
+impl StripePattern {
+    pub fn new(a: Color, b: Color, transform: Option<Matrix>) -> Self {
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        StripePattern { a, b, transform: t }
+    }
+}
+
+impl Pattern for StripePattern {
+    fn pattern_at(&self, point: &Point, _uv: Option<(Float, Float)>) -> Color {
+        if point.x.floor().rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+}
+
+/// Blends from `a` at x = 0 to `b` at x = 1, repeating every unit, clouds when perturbed
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GradientPattern {
+    pub a: Color,
+    pub b: Color,
+    pub transform: Matrix,
+}
+
+#[allow(dead_code)]
+impl GradientPattern {
+    pub fn new(a: Color, b: Color, transform: Option<Matrix>) -> Self {
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        GradientPattern { a, b, transform: t }
+    }
+}
+
+impl Pattern for GradientPattern {
+    fn pattern_at(&self, point: &Point, _uv: Option<(Float, Float)>) -> Color {
+        let fraction = point.x - point.x.floor();
+        &self.a + &(&(&self.b - &self.a) * fraction)
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+}
+
+/// Concentric rings around the y axis, wood when perturbed
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct RingPattern {
+    pub a: Color,
+    pub b: Color,
+    pub transform: Matrix,
+}
+
+#[allow(dead_code)]
+impl RingPattern {
+    pub fn new(a: Color, b: Color, transform: Option<Matrix>) -> Self {
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        RingPattern { a, b, transform: t }
+    }
+}
+
+impl Pattern for RingPattern {
+    fn pattern_at(&self, point: &Point, _uv: Option<(Float, Float)>) -> Color {
+        let distance = (point.x * point.x + point.z * point.z).sqrt();
+        if distance.floor().rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+}
+
+/// Jitters the lookup point of another pattern with fractal noise
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Perturbed {
+    pub pattern: Arc<dyn Pattern>,
+    pub noise: Noise,
+    /// Largest distance the point is moved on each axis
+    pub scale: Float,
+    pub octaves: usize,
+    pub transform: Matrix,
+}
+
+#[allow(dead_code)]
+impl Perturbed {
+    /// Three octaves by default
+    pub fn new(pattern: Arc<dyn Pattern>, noise: Noise, scale: Float) -> Self {
+        Perturbed {
+            pattern,
+            noise,
+            scale,
+            octaves: 3,
+            transform: Matrix::identity(4),
+        }
+    }
+}
+
+impl Pattern for Perturbed {
+    fn pattern_at(&self, point: &Point, uv: Option<(Float, Float)>) -> Color {
+        // the three axes read the noise far apart so they move independently
+        let offset = |dx: Float, dy: Float, dz: Float| {
+            let shifted = Point::new(point.x + dx, point.y + dy, point.z + dz);
+            self.noise.fbm(&shifted, self.octaves) * self.scale
+        };
+        let jittered = Point::new(
+            point.x + offset(0.0, 0.0, 0.0),
+            point.y + offset(31.4, 0.0, 0.0),
+            point.z + offset(0.0, 0.0, 47.9),
+        );
+        let inner_point = &self.pattern.transform().inverse() * &jittered;
+        self.pattern.pattern_at(&inner_point, uv)
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+}
+
+/// Gray levels from fractal noise, mostly meant as a height field for bumps
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct NoisePattern {
+    pub noise: Noise,
+    pub octaves: usize,
+    pub transform: Matrix,
+}
+
+#[allow(dead_code)]
+impl NoisePattern {
+    /// Four octaves by default
+    pub fn new(noise: Noise, transform: Option<Matrix>) -> Self {
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        NoisePattern {
+            noise,
+            octaves: 4,
+            transform: t,
+        }
+    }
+}
+
+impl Pattern for NoisePattern {
+    fn pattern_at(&self, point: &Point, _uv: Option<(Float, Float)>) -> Color {
+        let level = 0.5 + 0.5 * self.noise.fbm(point, self.octaves);
+        Color::new(level, level, level)
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+
+    fn white() -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    fn black() -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn should_alternate_stripes_along_x() {
+        let pattern = StripePattern::new(white(), black(), None);
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 5.0, -3.0), None),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.9, 0.0, 0.0), None),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(1.0, 0.0, 0.0), None),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(-0.1, 0.0, 0.0), None),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(-1.1, 0.0, 0.0), None),
+            white()
+        );
+    }
+
+    #[test]
+    fn should_blend_a_gradient() {
+        let pattern = GradientPattern::new(white(), black(), None);
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 0.0, 0.0), None),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.25, 0.0, 0.0), None),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(1.75, 0.0, 0.0), None),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn should_draw_rings() {
+        let pattern = RingPattern::new(white(), black(), None);
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 0.0, 0.0), None),
+            white()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(1.0, 0.0, 0.0), None),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 0.0, 1.0), None),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.708, 0.0, 0.708), None),
+            black()
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(2.0, 3.0, 0.0), None),
+            white()
+        );
+    }
+
+    #[test]
+    fn should_go_through_the_object_and_pattern_transforms() {
+        let object = Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::scaling_3d(2.0, 2.0, 2.0)),
+        );
+        let pattern = StripePattern::new(
+            white(),
+            black(),
+            Some(Matrix::translation_3d(0.5, 0.0, 0.0)),
+        );
+        // 2.5 in world space, 1.25 in object space and 0.75 in pattern space
+        assert_eq!(
+            pattern.pattern_at_object(&object, &Point::new(2.5, 0.0, 0.0), None, 0.0),
+            white()
+        );
+    }
+
+    #[test]
+    fn should_move_with_the_object_during_the_exposure() {
+        let mut object = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        object.set_end_transform(Matrix::translation_3d(1.0, 0.0, 0.0));
+        let pattern = StripePattern::new(white(), black(), None);
+        let start = pattern.pattern_at_object(&object, &Point::new(0.5, 0.0, 0.0), None, 0.0);
+        let end = pattern.pattern_at_object(&object, &Point::new(1.5, 0.0, 0.0), None, 1.0);
+        assert_eq!(start, white());
+        assert_eq!(end, white());
+    }
+
+    #[test]
+    fn should_jitter_the_inner_pattern() {
+        let stripes: Arc<dyn Pattern> = Arc::new(StripePattern::new(white(), black(), None));
+        let calm = Perturbed::new(stripes.clone(), Noise::new(9), 0.0);
+        let perturbed = Perturbed::new(stripes.clone(), Noise::new(9), 0.5);
+        let same = Perturbed::new(stripes.clone(), Noise::new(9), 0.5);
+        let mut changed = 0;
+        for i in 0..200 {
+            let point = Point::new(i as Float * 0.05 + 0.01, i as Float * 0.13, 0.3);
+            let plain = stripes.pattern_at(&point, None);
+            assert_eq!(calm.pattern_at(&point, None), plain);
+            assert_eq!(
+                perturbed.pattern_at(&point, None),
+                same.pattern_at(&point, None)
+            );
+            if perturbed.pattern_at(&point, None) != plain {
+                changed += 1;
+            }
+        }
+        // the edges of the stripes moved but most of the surface didn't change color
+        assert!(changed > 5);
+        assert!(changed < 150);
+    }
+
+    #[test]
+    fn should_shade_noise_in_gray() {
+        let pattern = NoisePattern::new(Noise::new(4), None);
+        for i in 0..100 {
+            let point = Point::new(i as Float * 0.37, i as Float * -0.21, 1.5);
+            let color = pattern.pattern_at(&point, None);
+            assert!((0.0..=1.0).contains(&color.r));
+            assert_eq!(color.r, color.g);
+            assert_eq!(color.g, color.b);
+        }
+    }
+}
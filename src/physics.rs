@@ -0,0 +1,383 @@
+use crate::{
+    intersection::hit, material::Material, point::Point, ray::Ray, sphere::Sphere, utils::Float,
+    utils::EPSILON, vector::Vector, world::World,
+};
+
+/// Point mass with a size, used for collisions. Particles don't spin, so this is enough
+/// for props that are dropped or thrown but not for tumbling rigid bodies
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone)]
+pub struct Particle {
+    pub position: Point,
+    pub velocity: Vector,
+    /// 0 or less makes the particle infinitely heavy
+    pub mass: Float,
+    pub radius: Float,
+    /// Set when a collision leaves it too slow to bounce again, it doesn't move anymore
+    pub resting: bool,
+}
+
+#[allow(dead_code)]
+impl Particle {
+    pub fn new(position: Point, velocity: Vector) -> Self {
+        Particle {
+            position,
+            velocity,
+            mass: 1.0,
+            radius: 0.0,
+            resting: false,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone)]
+pub enum Force {
+    /// Same acceleration whatever the mass, like gravity
+    Acceleration(Vector),
+    /// Constant force, heavier particles are pushed less
+    Constant(Vector),
+    /// Air resistance, a force of -coefficient * velocity
+    Drag(Float),
+}
+
+/// How positions and velocities move forward by one timestep
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Integration {
+    /// Position from the old velocity, cheap but gains energy over time
+    Euler,
+    /// Velocity first then position from the new velocity, stable for orbits and springs
+    SemiImplicitEuler,
+    /// Fourth order Runge-Kutta, accurate with velocity dependent forces like drag
+    RungeKutta4,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub forces: Vec<Force>,
+    pub integration: Integration,
+    pub timestep: Float,
+    /// Part of the speed along the normal kept after a bounce, 0 stops and 1 is elastic
+    pub restitution: Float,
+    /// Part of the speed along the surface lost at each contact, so rolling props stop
+    pub friction: Float,
+    /// Below this speed after a collision the particle comes to rest
+    pub rest_speed: Float,
+}
+
+#[allow(dead_code)]
+impl Simulation {
+    pub fn new(forces: Vec<Force>, integration: Integration, timestep: Float) -> Self {
+        Simulation {
+            forces,
+            integration,
+            timestep,
+            restitution: 0.5,
+            friction: 0.1,
+            rest_speed: 0.05,
+        }
+    }
+
+    /// A particle without a positive mass is treated as infinitely heavy, forces don't
+    /// move it but accelerations like gravity still do
+    fn acceleration(&self, particle: &Particle, velocity: &Vector) -> Vector {
+        let inverse_mass = if particle.mass > 0.0 {
+            1.0 / particle.mass
+        } else {
+            0.0
+        };
+        self.forces
+            .iter()
+            .fold(Vector::new(0.0, 0.0, 0.0), |total, force| {
+                let acceleration = match force {
+                    Force::Acceleration(acceleration) => *acceleration,
+                    Force::Constant(force) => force * inverse_mass,
+                    Force::Drag(coefficient) => velocity * (-coefficient * inverse_mass),
+                };
+                &total + &acceleration
+            })
+    }
+
+    /// Where the particle goes in one timestep, ignoring obstacles
+    fn integrate(&self, particle: &Particle) -> (Point, Vector) {
+        let dt = self.timestep;
+        let (position, velocity) = (particle.position, particle.velocity);
+        match self.integration {
+            Integration::Euler => {
+                let acceleration = self.acceleration(particle, &velocity);
+                (
+                    &position + &(&velocity * dt),
+                    &velocity + &(&acceleration * dt),
+                )
+            }
+            Integration::SemiImplicitEuler => {
+                let acceleration = self.acceleration(particle, &velocity);
+                let velocity = &velocity + &(&acceleration * dt);
+                (&position + &(&velocity * dt), velocity)
+            }
+            Integration::RungeKutta4 => {
+                // the forces don't depend on the position, only the velocity needs the 4
+                // slopes, the position follows them
+                let slope = |v: &Vector| self.acceleration(particle, v);
+                let k1 = slope(&velocity);
+                let v2 = &velocity + &(&k1 * (dt / 2.0));
+                let k2 = slope(&v2);
+                let v3 = &velocity + &(&k2 * (dt / 2.0));
+                let k3 = slope(&v3);
+                let v4 = &velocity + &(&k3 * dt);
+                let k4 = slope(&v4);
+                let average = |a: &Vector, b: &Vector, c: &Vector, d: &Vector| {
+                    &(&(a + &(b * 2.0)) + &(&(c * 2.0) + d)) / 6.0
+                };
+                (
+                    &position + &(&average(&velocity, &v2, &v3, &v4) * dt),
+                    &velocity + &(&average(&k1, &k2, &k3, &k4) * dt),
+                )
+            }
+        }
+    }
+
+    /// The particle one timestep later. With a world, the path is checked with a ray and
+    /// the particle bounces off the first surface it would go through
+    pub fn step(&self, particle: &Particle, world: Option<&World>) -> Particle {
+        if particle.resting {
+            return *particle;
+        }
+        let (position, velocity) = self.integrate(particle);
+        let moved = Particle {
+            position,
+            velocity,
+            ..*particle
+        };
+        let world = match world {
+            Some(world) => world,
+            None => return moved,
+        };
+        let path = &position - &particle.position;
+        let distance = path.magnitude();
+        if distance < EPSILON {
+            return moved;
+        }
+        let direction = &path / distance;
+        let ray = Ray::new(particle.position, direction);
+        let intersections = world.intersect(&ray);
+        let contact = match hit(&intersections) {
+            Some(contact) => contact,
+            None => return moved,
+        };
+        let point = ray.position(contact.t);
+        let mut normal = contact.object.normal_at(&point);
+        if normal.dot_product(&direction) > 0.0 {
+            normal = -&normal;
+        }
+        // the surface of the particle touches first, a radius away from the surface along
+        // the normal, so further back along a slanted path
+        let approach = -normal.dot_product(&direction);
+        let touch = contact.t - particle.radius / approach.max(EPSILON);
+        if touch > distance {
+            return moved;
+        }
+        let along_normal = &normal * velocity.dot_product(&normal);
+        let along_surface = &velocity - &along_normal;
+        let bounced =
+            &(&along_surface * (1.0 - self.friction)) - &(&along_normal * self.restitution);
+        let resting = bounced.magnitude() < self.rest_speed;
+        Particle {
+            position: &ray.position(touch.max(0.0)) + &(&normal * EPSILON),
+            velocity: if resting {
+                Vector::new(0.0, 0.0, 0.0)
+            } else {
+                bounced
+            },
+            resting,
+            ..*particle
+        }
+    }
+
+    /// Every state from the start, for at most `steps` timesteps or until it comes to rest
+    pub fn simulate(&self, start: Particle, world: Option<&World>, steps: usize) -> Trajectory {
+        let mut particles = vec![start];
+        let mut particle = start;
+        for _ in 0..steps {
+            if particle.resting {
+                break;
+            }
+            particle = self.step(&particle, world);
+            particles.push(particle);
+        }
+        Trajectory { particles }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    pub particles: Vec<Particle>,
+}
+
+#[allow(dead_code)]
+impl Trajectory {
+    pub fn points(&self) -> Vec<Point> {
+        self.particles
+            .iter()
+            .map(|particle| particle.position)
+            .collect()
+    }
+
+    /// Where the particle stopped (or was when the simulation ended)
+    pub fn last(&self) -> &Particle {
+        &self.particles[self.particles.len() - 1]
+    }
+
+    /// One sphere per state, the radius of the particle or `radius` when it has none
+    pub fn spheres(&self, radius: Float, material: &Material) -> Vec<Sphere> {
+        self.particles
+            .iter()
+            .map(|particle| {
+                let size = if particle.radius > 0.0 {
+                    particle.radius
+                } else {
+                    radius
+                };
+                let mut sphere = Sphere::new(particle.position, size, None);
+                sphere.material = material.clone();
+                sphere
+            })
+            .collect()
+    }
+
+    /// Adds the spheres of `spheres` to the world, to see the path in a render
+    pub fn add_to_world(&self, world: &mut World, radius: Float, material: &Material) {
+        for sphere in self.spheres(radius, material) {
+            world.add_object(Box::new(sphere));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::triangle::Triangle;
+
+    fn falling(integration: Integration) -> Simulation {
+        Simulation::new(
+            vec![Force::Acceleration(Vector::new(0.0, -10.0, 0.0))],
+            integration,
+            0.01,
+        )
+    }
+
+    fn floor() -> World {
+        let mut world = World::new();
+        for corners in [
+            [(-10.0, -10.0), (10.0, -10.0), (10.0, 10.0)],
+            [(-10.0, -10.0), (10.0, 10.0), (-10.0, 10.0)],
+        ] {
+            let [a, b, c] = corners.map(|(x, z)| Point::new(x, 0.0, z));
+            world.add_object(Box::new(Triangle::new(a, b, c, None)));
+        }
+        world
+    }
+
+    #[test]
+    fn should_fall_like_the_equations_of_motion() {
+        let start = Particle::new(Point::new(0.0, 100.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        for (integration, tolerance) in [
+            (Integration::Euler, 0.1),
+            (Integration::SemiImplicitEuler, 0.1),
+            (Integration::RungeKutta4, 0.0001),
+        ] {
+            let end = *falling(integration).simulate(start, None, 100).last();
+            // one second: y = 100 - 10 / 2, x = 1
+            assert!(
+                (end.position.y - 95.0).abs() < tolerance,
+                "{:?}",
+                integration
+            );
+            assert!((end.position.x - 1.0).abs() < 0.001);
+            assert!((end.velocity.y + 10.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn should_slow_down_with_drag() {
+        let mut simulation = Simulation::new(vec![Force::Drag(1.0)], Integration::RungeKutta4, 0.1);
+        let start = Particle::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let end = *simulation.simulate(start, None, 10).last();
+        // v = e^-t and x = 1 - e^-t after one second
+        let expected = (-1.0 as Float).exp();
+        assert!((end.velocity.x - expected).abs() < 0.0001);
+        assert!((end.position.x - (1.0 - expected)).abs() < 0.0001);
+        simulation.forces = vec![Force::Constant(Vector::new(2.0, 0.0, 0.0))];
+        let heavy = Particle {
+            mass: 4.0,
+            ..Particle::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0))
+        };
+        let end = *simulation.simulate(heavy, None, 10).last();
+        assert!((end.velocity.x - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn should_not_push_particles_without_mass() {
+        let mut simulation = Simulation::new(
+            vec![
+                Force::Constant(Vector::new(2.0, 0.0, 0.0)),
+                Force::Drag(1.0),
+            ],
+            Integration::RungeKutta4,
+            0.1,
+        );
+        let start = Particle {
+            mass: 0.0,
+            ..Particle::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0))
+        };
+        let end = *simulation.simulate(start, None, 10).last();
+        assert_eq!(end.velocity.x, 1.0);
+        simulation
+            .forces
+            .push(Force::Acceleration(Vector::new(0.0, -10.0, 0.0)));
+        let end = *simulation.simulate(start, None, 10).last();
+        assert!((end.velocity.y + 10.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn should_bounce_and_come_to_rest_on_the_floor() {
+        let world = floor();
+        let start = Particle {
+            radius: 0.5,
+            ..Particle::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.5, 0.0, 0.0))
+        };
+        let trajectory =
+            falling(Integration::SemiImplicitEuler).simulate(start, Some(&world), 10_000);
+        assert!(trajectory
+            .particles
+            .iter()
+            .all(|particle| particle.position.y >= 0.5 - 0.001));
+        let bounced = trajectory
+            .particles
+            .windows(2)
+            .any(|pair| pair[0].velocity.y < 0.0 && pair[1].velocity.y > 0.0);
+        assert!(bounced);
+        let end = trajectory.last();
+        assert!(end.resting);
+        assert!((end.position.y - 0.5).abs() < 0.01);
+        assert!(end.position.x > 0.0);
+    }
+
+    #[test]
+    fn should_export_the_trajectory() {
+        let start = Particle::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 1.0, 0.0));
+        let trajectory = falling(Integration::Euler).simulate(start, None, 3);
+        assert_eq!(trajectory.points().len(), 4);
+        let spheres = trajectory.spheres(0.1, &Material::default());
+        let last = trajectory.last().position;
+        assert_eq!(spheres[3].radius, 0.1);
+        assert!((spheres[3].origin.x - last.x).abs() < EPSILON);
+        assert!((spheres[3].origin.y - last.y).abs() < EPSILON);
+        let mut world = World::new();
+        trajectory.add_to_world(&mut world, 0.1, &Material::default());
+        assert_eq!(world.objects().len(), 4);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::canvas::Canvas;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest payload of an uncompressed deflate block
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// 8 bit RGB PNG of the canvas, or RGBA when some pixels aren't opaque. Colors are
+/// clamped to [0, 1] like in the PPM output.
+/// The pixels are stored without compression, which keeps the encoder small and is
+/// still read by every viewer
+#[allow(dead_code)]
+pub fn encode_png(canvas: &Canvas) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&u32::from(canvas.width).to_be_bytes());
+    header.extend_from_slice(&u32::from(canvas.height).to_be_bytes());
+    let opaque = canvas.is_opaque();
+    let (color_type, channels) = if opaque { (2, 3) } else { (6, 4) };
+    // bit depth, color type (RGB or RGBA), compression, filter and interlace methods
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut scanlines =
+        Vec::with_capacity(canvas.pixels.len() * (1 + channels * canvas.width as usize));
+    for (row, alphas) in canvas.pixels.iter().zip(canvas.alpha.iter()) {
+        // every scanline starts with its filter type, 0 is none
+        scanlines.push(0);
+        for (pixel, alpha) in row.iter().zip(alphas.iter()) {
+            let scaled = pixel.scale(255.0);
+            scanlines.extend_from_slice(&[scaled.r as u8, scaled.g as u8, scaled.b as u8]);
+            if !opaque {
+                scanlines.push((alpha.clamp(0.0, 1.0) * 255.0) as u8);
+            }
+        }
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    // the checksum covers the type and the data but not the length
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32K window and no preset dictionary, the check bits make it a
+    // multiple of 31
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        stream.push(u8::from(last));
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data.iter() {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data.iter() {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    /// Chunks of the file as (type, data), checking the CRC of each one
+    fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(&png[..8], &SIGNATURE);
+        let mut chunks = vec![];
+        let mut position = 8;
+        while position < png.len() {
+            let length = u32::from_be_bytes(png[position..position + 4].try_into().unwrap());
+            let end = position + 8 + length as usize;
+            let crc = u32::from_be_bytes(png[end..end + 4].try_into().unwrap());
+            assert_eq!(crc32(&png[position + 4..end]), crc);
+            let kind = String::from_utf8(png[position + 4..position + 8].to_vec()).unwrap();
+            chunks.push((kind, png[position + 8..end].to_vec()));
+            position = end + 4;
+        }
+        chunks
+    }
+
+    /// Concatenated payloads of the stored blocks, checking the adler32
+    fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!((u16::from(stream[0]) << 8 | u16::from(stream[1])) % 31, 0);
+        let mut data = vec![];
+        let mut position = 2;
+        loop {
+            let header = stream[position];
+            let length = u16::from_le_bytes([stream[position + 1], stream[position + 2]]);
+            let complement = u16::from_le_bytes([stream[position + 3], stream[position + 4]]);
+            assert_eq!(length, !complement);
+            let start = position + 5;
+            data.extend_from_slice(&stream[start..start + length as usize]);
+            position = start + length as usize;
+            if header & 1 == 1 {
+                break;
+            }
+        }
+        let adler = u32::from_be_bytes(stream[position..position + 4].try_into().unwrap());
+        assert_eq!(adler32(&data), adler);
+        data
+    }
+
+    #[test]
+    fn should_compute_the_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn should_encode_the_pixels() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(&Color::new(1.0, 0.0, 0.0), 0, 0);
+        canvas.write_pixel(&Color::new(0.0, 2.0, -1.0), 1, 0);
+        canvas.write_pixel(&Color::new(0.5, 0.5, 0.5), 1, 1);
+        let chunks = chunks(&encode_png(&canvas));
+        let kinds: Vec<&str> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, vec!["IHDR", "IDAT", "IEND"]);
+        assert_eq!(chunks[0].1, vec![0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        assert_eq!(
+            inflate_stored(&chunks[1].1),
+            vec![0, 255, 0, 0, 0, 255, 0, 0, 0, 0, 0, 127, 127, 127]
+        );
+    }
+
+    #[test]
+    fn should_add_an_alpha_channel_to_transparent_canvases() {
+        let mut canvas = Canvas::transparent(2, 1);
+        canvas.write_pixel(&Color::new(1.0, 0.0, 0.0), 0, 0);
+        let chunks = chunks(&encode_png(&canvas));
+        assert_eq!(chunks[0].1[9], 6);
+        assert_eq!(
+            inflate_stored(&chunks[1].1),
+            vec![0, 255, 0, 0, 255, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn should_split_large_images_in_several_blocks() {
+        let canvas = Canvas::new(200, 200);
+        let chunks = chunks(&encode_png(&canvas));
+        let data = inflate_stored(&chunks[1].1);
+        assert_eq!(data.len(), 200 * (1 + 3 * 200));
+        assert!(data.iter().all(|byte| *byte == 0));
+    }
+}
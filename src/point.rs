@@ -1,22 +1,23 @@
 use crate::matrix::Matrix;
+use crate::utils::Float;
 use crate::vector::Vector;
 use std::ops::Add;
 use std::ops::Sub;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Point {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub w: f32,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+    pub w: Float,
 }
 
 impl Point {
-    pub fn new(x: f32, y: f32, z: f32) -> Point {
+    pub fn new(x: Float, y: Float, z: Float) -> Point {
         Point { x, y, z, w: 1.0 }
     }
 
-    pub fn to_matrix(&self) -> Matrix {
+    pub fn to_matrix(self) -> Matrix {
         let mut m = Matrix::new(4, 1);
         m.patch(vec![vec![self.x], vec![self.y], vec![self.z], vec![self.w]]);
         m
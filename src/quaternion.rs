@@ -0,0 +1,414 @@
+use std::ops::{Mul, Neg};
+
+use crate::{matrix::Matrix, utils::Float, utils::EPSILON, vector::Vector};
+
+/// Order of the rotations of Euler angles, around the fixed axes of the world: `Xyz` turns
+/// around x first, then y, then z, like `Transform::identity().rotate_x(x).rotate_y(y)
+/// .rotate_z(z)`
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EulerOrder {
+    Xyz,
+    Xzy,
+    Yxz,
+    Yzx,
+    Zxy,
+    Zyx,
+}
+
+impl EulerOrder {
+    /// Indices of the axes, first rotation first
+    fn axes(self) -> [usize; 3] {
+        match self {
+            EulerOrder::Xyz => [0, 1, 2],
+            EulerOrder::Xzy => [0, 2, 1],
+            EulerOrder::Yxz => [1, 0, 2],
+            EulerOrder::Yzx => [1, 2, 0],
+            EulerOrder::Zxy => [2, 0, 1],
+            EulerOrder::Zyx => [2, 1, 0],
+        }
+    }
+
+    /// The orders that are a rotation of x, y, z, the others swap the handedness
+    fn is_even(self) -> bool {
+        matches!(self, EulerOrder::Xyz | EulerOrder::Yzx | EulerOrder::Zxy)
+    }
+}
+
+/// Rotation stored as a unit quaternion w + xi + yj + zk
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+#[allow(dead_code)]
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    /// No rotation
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotation of the upper 3x3 part of a matrix, which must be orthonormal (no scale or
+    /// shear, see `Matrix::decompose` to remove them first)
+    pub fn from_matrix(matrix: &Matrix) -> Self {
+        let m = |r: usize, c: usize| matrix.get_cell(r, c);
+        let trace = m(0, 0) + m(1, 1) + m(2, 2);
+        // divide by the largest of w, x, y and z to stay accurate for every rotation
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(
+                0.25 * s,
+                (m(2, 1) - m(1, 2)) / s,
+                (m(0, 2) - m(2, 0)) / s,
+                (m(1, 0) - m(0, 1)) / s,
+            )
+        } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+            let s = (1.0 + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (m(2, 1) - m(1, 2)) / s,
+                0.25 * s,
+                (m(0, 1) + m(1, 0)) / s,
+                (m(0, 2) + m(2, 0)) / s,
+            )
+        } else if m(1, 1) > m(2, 2) {
+            let s = (1.0 + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (m(0, 2) - m(2, 0)) / s,
+                (m(0, 1) + m(1, 0)) / s,
+                0.25 * s,
+                (m(1, 2) + m(2, 1)) / s,
+            )
+        } else {
+            let s = (1.0 + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.0;
+            Quaternion::new(
+                (m(1, 0) - m(0, 1)) / s,
+                (m(0, 2) + m(2, 0)) / s,
+                (m(1, 2) + m(2, 1)) / s,
+                0.25 * s,
+            )
+        };
+        q.normalize()
+    }
+
+    /// 4x4 rotation matrix, like the ones of `Matrix::rotate_x_3d`
+    pub fn to_matrix(&self) -> Matrix {
+        let Quaternion { w, x, y, z } = self.normalize();
+        let mut matrix = Matrix::identity(4);
+        matrix.patch(vec![
+            vec![
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            vec![
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            vec![
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+        matrix
+    }
+
+    pub fn magnitude(&self) -> Float {
+        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        let mut magnitude = self.magnitude();
+        if magnitude == 0.0 {
+            magnitude = 1.0;
+        }
+        Quaternion::new(
+            self.w / magnitude,
+            self.x / magnitude,
+            self.y / magnitude,
+            self.z / magnitude,
+        )
+    }
+
+    /// Rotation of `rad` radians around `axis`, counterclockwise when the axis points at
+    /// the viewer. A zero axis gives no rotation
+    pub fn from_axis_angle(axis: &Vector, rad: Float) -> Self {
+        let axis = axis.normalize();
+        let sin = (rad / 2.0).sin();
+        Quaternion::new((rad / 2.0).cos(), axis.x * sin, axis.y * sin, axis.z * sin).normalize()
+    }
+
+    /// Axis and angle in [0, PI], the axis is x when there is no rotation
+    pub fn to_axis_angle(&self) -> (Vector, Float) {
+        let q = self.normalize();
+        // -q is the same rotation, picking w >= 0 keeps the angle under PI
+        let q = if q.w < 0.0 { -q } else { q };
+        let sin = (1.0 - q.w * q.w).max(0.0).sqrt();
+        if sin < EPSILON {
+            return (Vector::new(1.0, 0.0, 0.0), 0.0);
+        }
+        (
+            Vector::new(q.x / sin, q.y / sin, q.z / sin),
+            2.0 * q.w.min(1.0).acos(),
+        )
+    }
+
+    /// Rotations around the world axes applied in `order`, each angle in radians is the one
+    /// around the axis of the same name
+    pub fn from_euler(x: Float, y: Float, z: Float, order: EulerOrder) -> Self {
+        let angles = [x, y, z];
+        let axes = [
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        ];
+        order.axes().iter().fold(Quaternion::identity(), |q, axis| {
+            &Quaternion::from_axis_angle(&axes[*axis], angles[*axis]) * &q
+        })
+    }
+
+    /// Angles (x, y, z) that `from_euler` turns back into this rotation. The middle
+    /// rotation is kept in [-PI/2, PI/2]; when it's at a limit (gimbal lock) the first and
+    /// last axes line up and the whole turn goes in the first angle
+    pub fn to_euler(&self, order: EulerOrder) -> (Float, Float, Float) {
+        let matrix = self.to_matrix();
+        let [i, j, k] = order.axes();
+        // the axes renamed to x, y, z, an odd renaming mirrors the angles
+        let m = |r: usize, c: usize| matrix.get_cell([i, j, k][r], [i, j, k][c]);
+        let sign = if order.is_even() { 1.0 } else { -1.0 };
+        let sin_second = (-m(2, 0) * sign).clamp(-1.0, 1.0);
+        let second = sin_second.asin();
+        let (first, third) = if sin_second.abs() < 1.0 - EPSILON {
+            (
+                (m(2, 1) * sign).atan2(m(2, 2)),
+                (m(1, 0) * sign).atan2(m(0, 0)),
+            )
+        } else {
+            ((-m(1, 2) * sign).atan2(m(1, 1)), 0.0)
+        };
+        let mut angles = [0.0; 3];
+        angles[i] = first;
+        angles[j] = second;
+        angles[k] = third;
+        (angles[0], angles[1], angles[2])
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// The opposite rotation
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// The vector turned by this rotation
+    pub fn rotate(&self, vector: &Vector) -> Vector {
+        let q = self.normalize();
+        let turned = &(&q * &Quaternion::new(0.0, vector.x, vector.y, vector.z)) * &q.conjugate();
+        Vector::new(turned.x, turned.y, turned.z)
+    }
+
+    /// Spherical interpolation, turns at a constant speed along the shortest way from
+    /// this rotation (t = 0) to `other` (t = 1)
+    pub fn slerp(&self, other: &Quaternion, t: Float) -> Quaternion {
+        let from = self.normalize();
+        let mut to = other.normalize();
+        let mut cos = from.dot(&to);
+        if cos < 0.0 {
+            to = -to;
+            cos = -cos;
+        }
+        let (a, b) = if cos > 1.0 - EPSILON {
+            // too close for the sine below, a straight line is as good
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Quaternion::new(
+            from.w * a + to.w * b,
+            from.x * a + to.x * b,
+            from.y * a + to.y * b,
+            from.z * a + to.z * b,
+        )
+        .normalize()
+    }
+
+    /// Both q and -q are the same rotation, this is true for either
+    pub fn same_rotation(&self, other: &Quaternion) -> bool {
+        (self.normalize().dot(&other.normalize()).abs() - 1.0).abs() < EPSILON
+    }
+}
+
+// q1 * q2 rotates by q2 then by q1, like the product of their matrices
+impl Mul<&Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: &Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        )
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
+
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Quaternion) -> bool {
+        (self.w - other.w).abs() < EPSILON
+            && (self.x - other.x).abs() < EPSILON
+            && (self.y - other.y).abs() < EPSILON
+            && (self.z - other.z).abs() < EPSILON
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::consts::PI;
+
+    #[test]
+    fn should_convert_axis_rotations() {
+        let half = (PI / 4.0).sin();
+        assert_eq!(
+            Quaternion::new(half, half, 0.0, 0.0).to_matrix(),
+            Matrix::rotate_x_3d(PI / 2.0)
+        );
+        assert_eq!(
+            Quaternion::new(half, 0.0, half, 0.0).to_matrix(),
+            Matrix::rotate_y_3d(PI / 2.0)
+        );
+        assert_eq!(
+            Quaternion::new(half, 0.0, 0.0, half).to_matrix(),
+            Matrix::rotate_z_3d(PI / 2.0)
+        );
+    }
+
+    #[test]
+    fn should_read_the_rotation_of_a_matrix() {
+        let rotations = [
+            Matrix::identity(4),
+            Matrix::rotate_x_3d(PI),
+            Matrix::rotate_y_3d(PI),
+            Matrix::rotate_z_3d(PI),
+            &Matrix::rotate_x_3d(0.3) * &Matrix::rotate_y_3d(-2.0),
+            &(&Matrix::rotate_z_3d(2.5) * &Matrix::rotate_x_3d(1.0)) * &Matrix::rotate_y_3d(3.0),
+        ];
+        for rotation in rotations {
+            assert_eq!(Quaternion::from_matrix(&rotation).to_matrix(), rotation);
+        }
+    }
+
+    #[test]
+    fn should_know_opposite_quaternions_are_the_same_rotation() {
+        let q = Quaternion::new(0.5, 0.5, -0.5, 0.5);
+        let opposite = Quaternion::new(-0.5, -0.5, 0.5, -0.5);
+        assert_ne!(q, opposite);
+        assert!(q.same_rotation(&opposite));
+        assert!(!q.same_rotation(&Quaternion::identity()));
+    }
+
+    #[test]
+    fn should_rotate_around_an_axis() {
+        let axis = Vector::new(1.0, 1.0, 1.0);
+        let q = Quaternion::from_axis_angle(&axis, 2.0 * PI / 3.0);
+        // a third of a turn around the diagonal cycles the axes
+        let turned = q.rotate(&Vector::new(1.0, 0.0, 0.0));
+        assert!((turned.y - 1.0).abs() < EPSILON && turned.x.abs() < EPSILON);
+        let (back_axis, angle) = q.to_axis_angle();
+        assert!((angle - 2.0 * PI / 3.0).abs() < EPSILON);
+        assert!((back_axis.dot_product(&axis.normalize()) - 1.0).abs() < EPSILON);
+        assert_eq!(
+            Quaternion::from_axis_angle(&Vector::new(0.0, 1.0, 0.0), 0.4).to_matrix(),
+            Matrix::rotate_y_3d(0.4)
+        );
+        assert_eq!(Quaternion::identity().to_axis_angle().1, 0.0);
+    }
+
+    #[test]
+    fn should_compose_like_matrices() {
+        let a = Quaternion::from_axis_angle(&Vector::new(1.0, 0.0, 0.0), 0.5);
+        let b = Quaternion::from_axis_angle(&Vector::new(0.0, 0.0, 1.0), -1.3);
+        assert_eq!((&a * &b).to_matrix(), &a.to_matrix() * &b.to_matrix());
+        assert!((&a * &a.conjugate()).same_rotation(&Quaternion::identity()));
+    }
+
+    #[test]
+    fn should_slerp_at_constant_speed() {
+        let from = Quaternion::identity();
+        let to = Quaternion::from_axis_angle(&Vector::new(0.0, 1.0, 0.0), PI / 2.0);
+        for t in [0.0, 0.25, 0.5, 1.0] {
+            let expected = Quaternion::from_axis_angle(&Vector::new(0.0, 1.0, 0.0), t * PI / 2.0);
+            assert!(from.slerp(&to, t).same_rotation(&expected));
+        }
+        // -to is the same rotation and mustn't make it go the long way around
+        let halfway = from.slerp(&-to, 0.5);
+        assert!((halfway.to_axis_angle().1 - PI / 4.0).abs() < EPSILON);
+        assert!(to.slerp(&to, 0.3).same_rotation(&to));
+    }
+
+    #[test]
+    fn should_convert_euler_angles_in_every_order() {
+        let orders = [
+            EulerOrder::Xyz,
+            EulerOrder::Xzy,
+            EulerOrder::Yxz,
+            EulerOrder::Yzx,
+            EulerOrder::Zxy,
+            EulerOrder::Zyx,
+        ];
+        let (x, y, z) = (0.3, -0.7, 1.1);
+        for order in orders {
+            let q = Quaternion::from_euler(x, y, z, order);
+            let mut expected = Matrix::identity(4);
+            for axis in order.axes() {
+                let rotation = match axis {
+                    0 => Matrix::rotate_x_3d(x),
+                    1 => Matrix::rotate_y_3d(y),
+                    _ => Matrix::rotate_z_3d(z),
+                };
+                expected = &rotation * &expected;
+            }
+            assert_eq!(q.to_matrix(), expected, "{:?}", order);
+            let (bx, by, bz) = q.to_euler(order);
+            assert!(
+                (bx - x).abs() < 0.0001 && (by - y).abs() < 0.0001 && (bz - z).abs() < 0.0001,
+                "{:?} gave {:?}",
+                order,
+                (bx, by, bz)
+            );
+        }
+    }
+
+    #[test]
+    fn should_handle_gimbal_lock() {
+        let q = Quaternion::from_euler(0.4, PI / 2.0, 0.2, EulerOrder::Xyz);
+        let (x, y, z) = q.to_euler(EulerOrder::Xyz);
+        assert!(Quaternion::from_euler(x, y, z, EulerOrder::Xyz).same_rotation(&q));
+        assert_eq!(z, 0.0);
+    }
+}
@@ -1,26 +1,40 @@
-use crate::{point::Point, vector::Vector, matrix::Matrix};
+use crate::{point::Point, vector::Vector, matrix::Matrix, utils::Float};
 
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Ray {
     pub origin: Point,
     pub direction: Vector,
+    /// Moment in [0, 1) inside the exposure, used to place moving objects
+    pub time: Float,
 }
 
 #[allow(dead_code)]
 impl Ray {
     pub fn new(origin: Point, direction: Vector) -> Self {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
     }
 
-    pub fn position(&self, t: f32) -> Point {
+    pub fn with_time(origin: Point, direction: Vector, time: Float) -> Self {
+        Ray {
+            origin,
+            direction,
+            time,
+        }
+    }
+
+    pub fn position(&self, t: Float) -> Point {
         &self.origin + &(&self.direction * t)
     }
 
     pub fn transform(&self, matrix:&Matrix) -> Self {
         let new_origin = matrix * &self.origin;
         let new_dir = matrix * &self.direction;
-        Ray::new(new_origin, new_dir)
+        Ray::with_time(new_origin, new_dir, self.time)
     }
 }
 
@@ -40,6 +54,18 @@ mod test {
         assert_eq!(ray.direction.y, 0.0);
         assert_eq!(ray.direction.z, 0.0);
         assert_eq!(ray.direction.w, 0.0);
+        assert_eq!(ray.time, 0.0);
+    }
+
+    #[test]
+    fn should_create_a_ray_at_a_time() {
+        let ray = Ray::with_time(Point::new(2.0, 3.0, 4.0), Vector::new(1.0, 0.0, 0.0), 0.25);
+        assert_eq!(ray.origin.x, 2.0);
+        assert_eq!(ray.direction.x, 1.0);
+        assert_eq!(ray.time, 0.25);
+
+        let new_ray = ray.transform(&Matrix::translation_3d(3.0, 4.0, 5.0));
+        assert_eq!(new_ray.time, 0.25);
     }
 
     #[test]
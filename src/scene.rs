@@ -0,0 +1,976 @@
+use std::{collections::HashMap, fmt, fs, sync::Arc};
+
+use crate::{
+    background::Background,
+    bvh::SplitMethod,
+    camera::Camera,
+    color::Color,
+    error::RayTracerError,
+    group::Group,
+    image::load_image,
+    integrator::Integrator,
+    json::{parse_json, Json, JsonError, JsonValue},
+    light::{AreaLight, DirectionalLight, Light, PointLight, SpotLight},
+    material::{Material, Shading},
+    matrix::Matrix,
+    microfacet::Microfacet,
+    object::Object,
+    pattern::{GradientPattern, Pattern, RingPattern, StripePattern},
+    point::Point,
+    sphere::Sphere,
+    transform::Transform,
+    triangle::Triangle,
+    utils::Float,
+    vector::Vector,
+    world::World,
+};
+
+/// Everything needed to render an image
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Scene {
+    pub world: World,
+    pub camera: Camera,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    /// The file is not valid JSON or doesn't describe a scene
+    Invalid {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<JsonError> for SceneError {
+    fn from(error: JsonError) -> Self {
+        SceneError::Invalid {
+            line: error.line,
+            message: error.message,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, SceneError>;
+
+/// Reads a scene file, see `parse_scene` for the format
+#[allow(dead_code)]
+pub fn load_scene(path: &str) -> Result<Scene> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| std::io::Error::new(error.kind(), format!("{}: {}", path, error)))?;
+    parse_scene(&text)
+}
+
+/// Builds a world and a camera from a JSON document with these top level keys:
+///
+/// - `camera` (required): `width`, `height`, `field_of_view` in degrees, `from`, `to`,
+///   `up`, `samples`, `aperture`, `focal_distance`, `integrator` (`"whitted"` or
+///   `"path_tracer"`) and `max_depth`
+/// - `background`: a color, `{"top": color, "bottom": color}` or
+///   `{"equirectangular": "image path"}`
+/// - `lights`: objects with a `type` of `point`, `area`, `spot` or `directional`
+/// - `define`: named materials, transform lists and shapes reused by name elsewhere
+/// - `objects`: shapes with a `type` of `sphere`, `triangle`, `mesh` or `group`
+///
+/// Colors, points and vectors are arrays of 3 numbers. Transforms are lists applied in
+/// order, each one being `["translate", x, y, z]`, `["scale", x, y, z]`,
+/// `["rotate_x", degrees]` (also y and z), `["rotate", x, y, z, degrees]` around any axis,
+/// `["shear", xy, xz, yx, yz, zx, zy]` or the name of a defined list. Materials and shapes can be a defined name or an object, and
+/// `"extends": "name"` starts from a definition and overrides some of its keys.
+#[allow(dead_code)]
+pub fn parse_scene(text: &str) -> Result<Scene> {
+    let document = parse_json(text)?;
+    let root = fields(&document, "the scene")?;
+    check_keys(
+        &document,
+        &["camera", "background", "lights", "define", "objects"],
+        "the scene",
+    )?;
+
+    let mut definitions = HashMap::new();
+    if let Some(define) = root.get("define") {
+        for (name, value) in fields(define, "define")?.entries {
+            definitions.insert(name, value);
+        }
+    }
+    let loader = Loader { definitions };
+
+    let camera = match root.get("camera") {
+        Some(camera) => loader.camera(camera)?,
+        None => return Err(invalid(&document, "the scene has no camera")),
+    };
+
+    let mut world = World::new();
+    if let Some(background) = root.get("background") {
+        world.background = loader.background(background)?;
+    }
+    if let Some(lights) = root.get("lights") {
+        for light in items(lights, "lights")? {
+            world.add_light(loader.light(light)?);
+        }
+    }
+    if let Some(objects) = root.get("objects") {
+        let material = Material::default();
+        for object in items(objects, "objects")? {
+            world.add_object(loader.object(object, &material, 0)?);
+        }
+    }
+    world.build_bvh(SplitMethod::Sah);
+    Ok(Scene { world, camera })
+}
+
+/// Definitions deeper than this are assumed to extend each other in a loop
+const MAX_NESTING: usize = 32;
+
+struct Loader {
+    definitions: HashMap<String, Json>,
+}
+
+impl Loader {
+    fn definition(&self, json: &Json, name: &str) -> Result<&Json> {
+        self.definitions
+            .get(name)
+            .ok_or_else(|| invalid(json, &format!("nothing is defined as \"{}\"", name)))
+    }
+
+    /// The object with the keys of the definitions it extends merged in, or the definition
+    /// itself when it's just a name
+    fn resolve(&self, json: &Json, what: &str, depth: usize) -> Result<Vec<(String, Json)>> {
+        if depth > MAX_NESTING {
+            return Err(invalid(json, &format!("{} extends itself", what)));
+        }
+        if let JsonValue::String(name) = &json.value {
+            return self.resolve(self.definition(json, name)?, what, depth + 1);
+        }
+        let own = fields(json, what)?;
+        let mut merged = match own.get("extends") {
+            Some(base) => self.resolve(base, what, depth + 1)?,
+            None => vec![],
+        };
+        for (key, value) in own.entries.iter() {
+            if key == "extends" {
+                continue;
+            }
+            match merged.iter_mut().find(|(name, _)| name == key) {
+                Some(entry) => entry.1 = value.clone(),
+                None => merged.push((key.clone(), value.clone())),
+            }
+        }
+        Ok(merged)
+    }
+
+    fn camera(&self, json: &Json) -> Result<Camera> {
+        check_keys(
+            json,
+            &[
+                "width",
+                "height",
+                "field_of_view",
+                "from",
+                "to",
+                "up",
+                "samples",
+                "aperture",
+                "focal_distance",
+                "integrator",
+                "max_depth",
+            ],
+            "the camera",
+        )?;
+        let camera = fields(json, "the camera")?;
+        let width = match camera.get("width") {
+            Some(width) => count(width, "width")?,
+            None => return Err(invalid(json, "the camera has no width")),
+        };
+        let height = match camera.get("height") {
+            Some(height) => count(height, "height")?,
+            None => return Err(invalid(json, "the camera has no height")),
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid(
+                json,
+                "the camera width and height must be at least 1",
+            ));
+        }
+        if width > usize::from(u16::MAX) || height > usize::from(u16::MAX) {
+            return Err(invalid(
+                json,
+                &format!(
+                    "the camera width and height can't be larger than {}",
+                    u16::MAX
+                ),
+            ));
+        }
+        let field_of_view = optional(camera.get("field_of_view"), number, 60.0)?;
+        let from = optional(camera.get("from"), point, Point::new(0.0, 0.0, -5.0))?;
+        let to = optional(camera.get("to"), point, Point::new(0.0, 0.0, 0.0))?;
+        let up = optional(camera.get("up"), vector, Vector::new(0.0, 1.0, 0.0))?;
+
+        let transform = checked(json, Matrix::try_view_transform(&from, &to, &up))?;
+        let mut result = checked(
+            json,
+            Camera::try_new(width, height, field_of_view.to_radians(), Some(transform)),
+        )?;
+        result.samples = optional(camera.get("samples"), |json| count(json, "samples"), 1)?.max(1);
+        result.aperture = optional(camera.get("aperture"), number, 0.0)?;
+        result.focal_distance = optional(camera.get("focal_distance"), number, 1.0)?;
+        let max_depth = optional(camera.get("max_depth"), |json| count(json, "max_depth"), 5)?;
+        if let Some(integrator) = camera.get("integrator") {
+            result.integrator = match text(integrator)? {
+                "whitted" => Integrator::Whitted,
+                "path_tracer" => Integrator::PathTracer { max_depth },
+                other => {
+                    return Err(invalid(
+                        integrator,
+                        &format!(
+                            "unknown integrator \"{}\", expected whitted or path_tracer",
+                            other
+                        ),
+                    ))
+                }
+            };
+        }
+        Ok(result)
+    }
+
+    fn background(&self, json: &Json) -> Result<Background> {
+        if let JsonValue::Array(_) = json.value {
+            return Ok(Background::Color(color(json)?));
+        }
+        let background = fields(json, "the background")?;
+        if let Some(path) = background.get("equirectangular") {
+            check_keys(json, &["equirectangular"], "the background")?;
+            let image =
+                load_image(text(path)?).map_err(|error| invalid(path, &error.to_string()))?;
+            return Ok(Background::Equirectangular(Arc::new(image)));
+        }
+        check_keys(json, &["top", "bottom"], "the background")?;
+        match (background.get("top"), background.get("bottom")) {
+            (Some(top), Some(bottom)) => Ok(Background::Gradient {
+                top: color(top)?,
+                bottom: color(bottom)?,
+            }),
+            _ => Err(invalid(
+                json,
+                "the background needs both a top and a bottom color",
+            )),
+        }
+    }
+
+    fn light(&self, json: &Json) -> Result<Box<dyn Light>> {
+        let light = fields(json, "a light")?;
+        let intensity = optional(light.get("intensity"), color, Color::new(1.0, 1.0, 1.0))?;
+        let light: Box<dyn Light> = match kind(json, &light, "a light")? {
+            "point" => {
+                check_keys(json, &["type", "position", "intensity"], "a point light")?;
+                Box::new(PointLight::new(
+                    required(json, &light, "position", point)?,
+                    intensity,
+                ))
+            }
+            "area" => {
+                check_keys(
+                    json,
+                    &[
+                        "type",
+                        "corner",
+                        "uvec",
+                        "usteps",
+                        "vvec",
+                        "vsteps",
+                        "intensity",
+                        "jitter",
+                    ],
+                    "an area light",
+                )?;
+                let usteps = required(json, &light, "usteps", |json| count(json, "usteps"))?;
+                let vsteps = required(json, &light, "vsteps", |json| count(json, "vsteps"))?;
+                let mut area = checked(
+                    json,
+                    AreaLight::try_new(
+                        required(json, &light, "corner", point)?,
+                        required(json, &light, "uvec", vector)?,
+                        usteps,
+                        required(json, &light, "vvec", vector)?,
+                        vsteps,
+                        intensity,
+                    ),
+                )?;
+                area.jitter = optional(light.get("jitter"), boolean, area.jitter)?;
+                Box::new(area)
+            }
+            "spot" => {
+                check_keys(
+                    json,
+                    &[
+                        "type",
+                        "position",
+                        "direction",
+                        "inner_angle",
+                        "outer_angle",
+                        "intensity",
+                    ],
+                    "a spot light",
+                )?;
+                let inner_angle = required(json, &light, "inner_angle", number)?;
+                let outer_angle = required(json, &light, "outer_angle", number)?;
+                Box::new(checked(
+                    json,
+                    SpotLight::try_new(
+                        required(json, &light, "position", point)?,
+                        required(json, &light, "direction", vector)?,
+                        inner_angle.to_radians(),
+                        outer_angle.to_radians(),
+                        intensity,
+                    ),
+                )?)
+            }
+            "directional" => {
+                check_keys(
+                    json,
+                    &["type", "direction", "intensity"],
+                    "a directional light",
+                )?;
+                Box::new(checked(
+                    json,
+                    DirectionalLight::try_new(
+                        required(json, &light, "direction", vector)?,
+                        intensity,
+                    ),
+                )?)
+            }
+            other => {
+                return Err(invalid(
+                    json,
+                    &format!(
+                        "unknown light type \"{}\", expected point, area, spot or directional",
+                        other
+                    ),
+                ))
+            }
+        };
+        Ok(light)
+    }
+
+    fn material(&self, json: &Json, base: &Material) -> Result<Material> {
+        let entries = self.resolve(json, "a material", 0)?;
+        let material = Fields { entries };
+        material.check(
+            &[
+                "color",
+                "ambient",
+                "diffuse",
+                "specular",
+                "shininess",
+                "emission",
+                "metallic",
+                "roughness",
+                "pattern",
+            ],
+            "a material",
+        )?;
+        let mut result = base.clone();
+        result.color = optional(material.get("color"), color, result.color)?;
+        result.ambient = optional(material.get("ambient"), number, result.ambient)?;
+        result.diffuse = optional(material.get("diffuse"), number, result.diffuse)?;
+        result.specular = optional(material.get("specular"), number, result.specular)?;
+        result.shininess = optional(material.get("shininess"), number, result.shininess)?;
+        result.emission = optional(material.get("emission"), color, result.emission)?;
+        if material.get("metallic").is_some() || material.get("roughness").is_some() {
+            let (metallic, roughness) = match result.shading {
+                Shading::Microfacet(microfacet) => (microfacet.metallic, microfacet.roughness),
+                Shading::Phong => (0.0, 0.5),
+            };
+            result.shading = Shading::Microfacet(Microfacet::new(
+                optional(material.get("metallic"), number, metallic)?,
+                optional(material.get("roughness"), number, roughness)?,
+            ));
+        }
+        if let Some(pattern) = material.get("pattern") {
+            result.pattern = Some(self.pattern(pattern)?);
+        }
+        Ok(result)
+    }
+
+    fn pattern(&self, json: &Json) -> Result<Arc<dyn Pattern>> {
+        check_keys(json, &["type", "colors", "transform"], "a pattern")?;
+        let pattern = fields(json, "a pattern")?;
+        let colors = required(json, &pattern, "colors", |json| {
+            let colors = items(json, "colors")?;
+            if colors.len() != 2 {
+                return Err(invalid(json, "a pattern needs exactly 2 colors"));
+            }
+            Ok((color(&colors[0])?, color(&colors[1])?))
+        })?;
+        let transform = match pattern.get("transform") {
+            Some(transform) => Some(self.transform(transform, 0)?),
+            None => None,
+        };
+        if let Some(transform) = &transform {
+            if !transform.has_inverse() {
+                return Err(invalid(
+                    json,
+                    "the transform of the pattern can't be inverted",
+                ));
+            }
+        }
+        let (a, b) = colors;
+        let pattern: Arc<dyn Pattern> = match kind(json, &pattern, "a pattern")? {
+            "stripes" => Arc::new(StripePattern::new(a, b, transform)),
+            "gradient" => Arc::new(GradientPattern::new(a, b, transform)),
+            "rings" => Arc::new(RingPattern::new(a, b, transform)),
+            other => {
+                return Err(invalid(
+                    json,
+                    &format!(
+                        "unknown pattern type \"{}\", expected stripes, gradient or rings",
+                        other
+                    ),
+                ))
+            }
+        };
+        Ok(pattern)
+    }
+
+    /// The steps are applied in the order of the list, so the first one is the innermost
+    fn transform(&self, json: &Json, depth: usize) -> Result<Matrix> {
+        if depth > MAX_NESTING {
+            return Err(invalid(json, "the transform refers to itself"));
+        }
+        let mut result = Transform::identity();
+        for step in items(json, "a transform")? {
+            let matrix = match &step.value {
+                JsonValue::String(name) => {
+                    self.transform(self.definition(step, name)?, depth + 1)?
+                }
+                JsonValue::Array(values) if !values.is_empty() => {
+                    let arguments = values[1..]
+                        .iter()
+                        .map(number)
+                        .collect::<Result<Vec<Float>>>()?;
+                    transform_step(step, text(&values[0])?, &arguments)?
+                }
+                _ => {
+                    return Err(invalid(
+                        step,
+                        "a transform step must be a list like [\"scale\", 1, 2, 1] or a name",
+                    ))
+                }
+            };
+            result = result.then(&matrix);
+        }
+        Ok(result.build())
+    }
+
+    /// Shapes without a material get the one of their group, `depth` counts the groups
+    fn object(&self, json: &Json, inherited: &Material, depth: usize) -> Result<Box<dyn Object>> {
+        if depth > MAX_NESTING {
+            return Err(invalid(json, "groups are nested too deeply"));
+        }
+        let entries = self.resolve(json, "a shape", 0)?;
+        let shape = Fields { entries };
+        let material = match shape.get("material") {
+            Some(material) => self.material(material, &Material::default())?,
+            None => inherited.clone(),
+        };
+        let transform = match shape.get("transform") {
+            Some(transform) => Some(self.transform(transform, 0)?),
+            None => None,
+        };
+        let object: Box<dyn Object> = match kind(json, &shape, "a shape")? {
+            "sphere" => {
+                shape.check(&["type", "material", "transform"], "a sphere")?;
+                let mut sphere = checked(
+                    json,
+                    Sphere::try_new(Point::new(0.0, 0.0, 0.0), 1.0, transform),
+                )?;
+                sphere.material = material;
+                Box::new(sphere)
+            }
+            "triangle" => {
+                shape.check(&["type", "material", "transform", "points"], "a triangle")?;
+                let points = required(json, &shape, "points", |json| {
+                    let points = items(json, "points")?;
+                    if points.len() != 3 {
+                        return Err(invalid(json, "a triangle needs exactly 3 points"));
+                    }
+                    Ok([point(&points[0])?, point(&points[1])?, point(&points[2])?])
+                })?;
+                let mut triangle = checked(
+                    json,
+                    Triangle::try_new(points[0], points[1], points[2], transform),
+                )?;
+                triangle.material = material;
+                Box::new(triangle)
+            }
+            "mesh" => {
+                shape.check(
+                    &["type", "material", "transform", "vertices", "faces"],
+                    "a mesh",
+                )?;
+                let vertices: Vec<Point> = required(json, &shape, "vertices", |json| {
+                    items(json, "vertices")?.iter().map(point).collect()
+                })?;
+                let faces = required(json, &shape, "faces", |json| {
+                    items(json, "faces")?
+                        .iter()
+                        .map(|face| mesh_face(face, vertices.len()))
+                        .collect::<Result<Vec<[usize; 3]>>>()
+                })?;
+                Box::new(checked(
+                    json,
+                    Group::try_mesh(&vertices, &faces, material, transform),
+                )?)
+            }
+            "group" => {
+                shape.check(&["type", "material", "transform", "children"], "a group")?;
+                let mut group = checked(json, Group::try_new(transform))?;
+                if let Some(children) = shape.get("children") {
+                    for child in items(children, "children")? {
+                        group.add_child(self.object(child, &material, depth + 1)?);
+                    }
+                }
+                Box::new(group)
+            }
+            other => {
+                return Err(invalid(
+                    json,
+                    &format!(
+                        "unknown shape type \"{}\", expected sphere, triangle, mesh or group",
+                        other
+                    ),
+                ))
+            }
+        };
+        Ok(object)
+    }
+}
+
+fn transform_step(json: &Json, name: &str, arguments: &[Float]) -> Result<Matrix> {
+    let expected = match name {
+        "translate" | "scale" => 3,
+        "rotate_x" | "rotate_y" | "rotate_z" => 1,
+        "rotate" => 4,
+        "shear" => 6,
+        _ => {
+            return Err(invalid(
+                json,
+                &format!(
+                    "unknown transform \"{}\", expected translate, scale, rotate_x, rotate_y, rotate_z, rotate or shear",
+                    name
+                ),
+            ))
+        }
+    };
+    if arguments.len() != expected {
+        return Err(invalid(
+            json,
+            &format!(
+                "{} takes {} numbers, got {}",
+                name,
+                expected,
+                arguments.len()
+            ),
+        ));
+    }
+    let a = arguments;
+    if name == "rotate" && a[..3] == [0.0, 0.0, 0.0] {
+        return Err(invalid(json, "rotate needs an axis that isn't 0, 0, 0"));
+    }
+    Ok(match name {
+        "translate" => Matrix::translation_3d(a[0], a[1], a[2]),
+        "scale" => Matrix::scaling_3d(a[0], a[1], a[2]),
+        "rotate_x" => Matrix::rotate_x_3d(a[0].to_radians()),
+        "rotate_y" => Matrix::rotate_y_3d(a[0].to_radians()),
+        "rotate_z" => Matrix::rotate_z_3d(a[0].to_radians()),
+        "rotate" => Matrix::rotate_axis_3d(&Vector::new(a[0], a[1], a[2]), a[3].to_radians()),
+        _ => Matrix::shear_3d(a[0], a[1], a[2], a[3], a[4], a[5]),
+    })
+}
+
+fn mesh_face(json: &Json, vertex_count: usize) -> Result<[usize; 3]> {
+    let indices = items(json, "a face")?;
+    if indices.len() != 3 {
+        return Err(invalid(json, "a face needs exactly 3 vertex indices"));
+    }
+    let mut face = [0; 3];
+    for (slot, index) in face.iter_mut().zip(indices.iter()) {
+        *slot = count(index, "a vertex index")?;
+        if *slot >= vertex_count {
+            return Err(invalid(
+                index,
+                &format!(
+                    "vertex {} doesn't exist, the mesh has {}",
+                    slot, vertex_count
+                ),
+            ));
+        }
+    }
+    Ok(face)
+}
+
+/// Gives the error of a `try_` constructor the line of the value it was built from
+fn checked<T>(json: &Json, result: std::result::Result<T, RayTracerError>) -> Result<T> {
+    result.map_err(|error| invalid(json, &error.to_string()))
+}
+
+fn invalid(json: &Json, message: &str) -> SceneError {
+    SceneError::Invalid {
+        line: json.line,
+        message: message.to_string(),
+    }
+}
+
+/// Keys of a JSON object
+struct Fields {
+    entries: Vec<(String, Json)>,
+}
+
+impl Fields {
+    fn get(&self, key: &str) -> Option<&Json> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Typos in key names are reported instead of silently ignored
+    fn check(&self, allowed: &[&str], what: &str) -> Result<()> {
+        match self
+            .entries
+            .iter()
+            .find(|(key, _)| !allowed.contains(&key.as_str()))
+        {
+            Some((key, value)) => Err(invalid(
+                value,
+                &format!(
+                    "unknown key \"{}\" in {}, expected one of {}",
+                    key,
+                    what,
+                    allowed.join(", ")
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn fields(json: &Json, what: &str) -> Result<Fields> {
+    match &json.value {
+        JsonValue::Object(entries) => Ok(Fields {
+            entries: entries.clone(),
+        }),
+        _ => Err(invalid(
+            json,
+            &format!("{} must be an object, found {}", what, json.kind()),
+        )),
+    }
+}
+
+fn check_keys(json: &Json, allowed: &[&str], what: &str) -> Result<()> {
+    fields(json, what)?.check(allowed, what)
+}
+
+fn kind<'a>(json: &Json, fields: &'a Fields, what: &str) -> Result<&'a str> {
+    match fields.get("type") {
+        Some(kind) => text(kind),
+        None => Err(invalid(json, &format!("{} has no type", what))),
+    }
+}
+
+fn items<'a>(json: &'a Json, what: &str) -> Result<&'a [Json]> {
+    match &json.value {
+        JsonValue::Array(items) => Ok(items),
+        _ => Err(invalid(
+            json,
+            &format!("{} must be an array, found {}", what, json.kind()),
+        )),
+    }
+}
+
+fn required<T>(
+    json: &Json,
+    fields: &Fields,
+    key: &str,
+    read: impl Fn(&Json) -> Result<T>,
+) -> Result<T> {
+    match fields.get(key) {
+        Some(value) => read(value),
+        None => Err(invalid(json, &format!("missing key \"{}\"", key))),
+    }
+}
+
+fn optional<T>(json: Option<&Json>, read: impl Fn(&Json) -> Result<T>, default: T) -> Result<T> {
+    match json {
+        Some(value) => read(value),
+        None => Ok(default),
+    }
+}
+
+fn number(json: &Json) -> Result<Float> {
+    match json.value {
+        JsonValue::Number(number) => Ok(number as Float),
+        _ => Err(invalid(
+            json,
+            &format!("expected a number, found {}", json.kind()),
+        )),
+    }
+}
+
+fn count(json: &Json, what: &str) -> Result<usize> {
+    match json.value {
+        JsonValue::Number(number) if number >= 0.0 && number.fract() == 0.0 => Ok(number as usize),
+        _ => Err(invalid(
+            json,
+            &format!("{} must be a whole number of at least 0", what),
+        )),
+    }
+}
+
+fn boolean(json: &Json) -> Result<bool> {
+    match json.value {
+        JsonValue::Bool(value) => Ok(value),
+        _ => Err(invalid(
+            json,
+            &format!("expected true or false, found {}", json.kind()),
+        )),
+    }
+}
+
+fn text(json: &Json) -> Result<&str> {
+    match &json.value {
+        JsonValue::String(text) => Ok(text),
+        _ => Err(invalid(
+            json,
+            &format!("expected a string, found {}", json.kind()),
+        )),
+    }
+}
+
+fn triple(json: &Json, what: &str) -> Result<(Float, Float, Float)> {
+    match &json.value {
+        JsonValue::Array(values) if values.len() == 3 => Ok((
+            number(&values[0])?,
+            number(&values[1])?,
+            number(&values[2])?,
+        )),
+        _ => Err(invalid(
+            json,
+            &format!("{} must be an array of 3 numbers", what),
+        )),
+    }
+}
+
+fn color(json: &Json) -> Result<Color> {
+    let (r, g, b) = triple(json, "a color")?;
+    Ok(Color::new(r, g, b))
+}
+
+fn point(json: &Json) -> Result<Point> {
+    let (x, y, z) = triple(json, "a point")?;
+    Ok(Point::new(x, y, z))
+}
+
+fn vector(json: &Json) -> Result<Vector> {
+    let (x, y, z) = triple(json, "a vector")?;
+    Ok(Vector::new(x, y, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    fn error_line(text: &str) -> usize {
+        match parse_scene(text) {
+            Err(SceneError::Invalid { line, .. }) => line,
+            other => panic!("expected an invalid scene, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_load_the_example_scene() {
+        let scene = parse_scene(include_str!("../scenes/example.json")).unwrap();
+        assert_eq!(scene.camera.hsize, 200);
+        assert_eq!(scene.camera.vsize, 100);
+        assert_eq!(scene.world.lights.len(), 1);
+        assert_eq!(scene.world.objects().len(), 4);
+        // the camera looks at the middle sphere, which extends the shiny red material
+        let ray = scene.camera.ray_for_pixel(100, 50);
+        let hits = scene.world.intersect(&ray);
+        let material = hits[0].object.material();
+        assert_eq!(material.color, Color::new(1.0, 0.2, 0.2));
+        assert_eq!(material.shininess, 300.0);
+        assert_eq!(material.diffuse, 0.5);
+    }
+
+    #[test]
+    fn should_apply_transforms_in_order() {
+        let scene = parse_scene(
+            r#"{
+                "camera": {"width": 10, "height": 10},
+                "define": {"lift": [["translate", 0, 2, 0]]},
+                "objects": [
+                    {"type": "sphere", "transform": [["scale", 2, 2, 2], "lift"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        // scaled around the origin first, so it spans y from 0 to 4
+        let ray = Ray::new(Point::new(0.0, 10.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let hits = scene.world.intersect(&ray);
+        assert!((hits[0].t - 6.0).abs() < 0.001);
+        assert!((hits[1].t - 10.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn should_rotate_around_any_axis() {
+        let scene = parse_scene(
+            r#"{
+                "camera": {"width": 10, "height": 10},
+                "objects": [{"type": "sphere", "transform": [
+                    ["scale", 3, 1, 1], ["rotate", 0, 0, 1, 90]
+                ]}]
+            }"#,
+        )
+        .unwrap();
+        // the long side of the ellipsoid now lies along y
+        let ray = Ray::new(Point::new(0.0, 10.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let hits = scene.world.intersect(&ray);
+        assert!((hits[0].t - 7.0).abs() < 0.001);
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"objects\": [{\"type\": \"sphere\",\n\"transform\": [[\"rotate\", 0, 0, 0, 90]]}]}"
+            ),
+            3
+        );
+    }
+
+    #[test]
+    fn should_give_group_materials_to_the_children() {
+        let scene = parse_scene(
+            r#"{
+                "camera": {"width": 10, "height": 10},
+                "objects": [{
+                    "type": "group",
+                    "material": {"color": [0, 1, 0]},
+                    "children": [
+                        {"type": "sphere", "transform": [["translate", -3, 0, 0]]},
+                        {"type": "sphere", "material": {"metallic": 1, "roughness": 0.2}}
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap();
+        let group = &scene.world.objects()[0];
+        let children = group.children();
+        assert_eq!(children[0].material().color, Color::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            children[1].material().shading,
+            Shading::Microfacet(Microfacet::new(1.0, 0.2))
+        );
+    }
+
+    #[test]
+    fn should_report_the_line_of_mistakes() {
+        assert_eq!(
+            error_line("{\n\"camera\": {\"width\": 10,\n\"height\": }}"),
+            3
+        );
+        assert_eq!(error_line("{\n\"objects\": []\n}"), 1);
+        assert_eq!(
+            error_line("{\"camera\": {\"width\": 1, \"height\": 1},\n\"objects\": [\n{\"type\": \"cube\"}]}"),
+            3
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"objects\": [{\"type\": \"sphere\",\n\"material\": {\"colour\": [1, 0, 0]}}]}"
+            ),
+            3
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"objects\": [{\"type\": \"sphere\",\n\n\"transform\": [[\"rotate_x\", 1, 2]]}]}"
+            ),
+            4
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"define\": {\"a\": {\"extends\": \"a\"}},\n\"objects\": [{\"type\": \"sphere\", \"material\": \"a\"}]}"
+            ),
+            2
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"lights\": [{\"type\": \"point\"}]}"
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn should_reject_what_would_fail_while_rendering() {
+        assert_eq!(
+            error_line("{\n\"camera\": {\"width\": 70000, \"height\": 2}}"),
+            2
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"objects\": [\n{\"type\": \"sphere\", \"transform\": [[\"scale\", 1, 0, 1]]}]}"
+            ),
+            3
+        );
+        assert_eq!(
+            error_line(
+                "{\n\"camera\": {\"width\": 1, \"height\": 1, \"from\": [0, 0, 0], \"to\": [0, 0, 0]}}"
+            ),
+            2
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"lights\": [{\"type\": \"spot\", \"position\": [0, 0, 0],\n\"direction\": [0, 0, 1], \"inner_angle\": 40, \"outer_angle\": 20}]}"
+            ),
+            2
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"lights\": [{\"type\": \"directional\", \"direction\": [0, 0, 0]}]}"
+            ),
+            2
+        );
+        assert_eq!(
+            error_line(
+                "{\"camera\": {\"width\": 1, \"height\": 1},\n\"objects\": [\n{\"type\": \"triangle\", \"points\": [[0, 0, 0], [1, 1, 1], [2, 2, 2]]}]}"
+            ),
+            3
+        );
+
+        let path = std::env::temp_dir().join(format!("empty-{}.ppm", std::process::id()));
+        std::fs::write(&path, "P3\n0 0\n255\n").unwrap();
+        let line = error_line(&format!(
+            "{{\"camera\": {{\"width\": 1, \"height\": 1}},\n\"background\": {{\"equirectangular\": {:?}}}}}",
+            path.to_str().unwrap()
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(line, 2);
+    }
+}
@@ -1,16 +1,29 @@
-use std::cmp::Ordering;
-
 use crate::{
-    intersection::Intersection, matrix::Matrix, object::Object, point::Point, ray::Ray,
+    animation::Interpolate,
+    bounds::Bounds,
+    error::{RayTracerError, Result},
+    intersection::Intersection,
+    material::Material,
+    matrix::Matrix,
+    object::{Object, SurfaceSample},
+    point::Point,
+    ray::Ray,
+    utils::{consts::PI, random_unit_vector, Float},
     vector::Vector,
 };
 
+/// Samples of the exposure used for the bounds of a turning sphere
+const MOTION_BOUNDS_STEPS: usize = 16;
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Sphere {
     pub origin: Point,
-    pub radius: f32,
+    pub radius: Float,
     pub transform: Matrix,
+    /// Transform at the end of the exposure, the sphere moves from `transform` to this one
+    pub end_transform: Option<Matrix>,
+    pub material: Material,
 }
 
 impl Clone for Sphere {
@@ -19,82 +32,232 @@ impl Clone for Sphere {
             origin: self.origin,
             radius: self.radius,
             transform: self.transform.clone(),
+            end_transform: self.end_transform.clone(),
+            material: self.material.clone(),
         }
     }
 }
 
 #[allow(dead_code)]
 impl Sphere {
-    pub fn new(origin: Point, radius: f32, transform: Option<Matrix>) -> Self {
+    pub fn new(origin: Point, radius: Float, transform: Option<Matrix>) -> Self {
+        match Sphere::try_new(origin, radius, transform) {
+            Ok(sphere) => sphere,
+            Err(error) => panic!("Sphere::new {}", error),
+        }
+    }
+
+    /// Rejects transforms without an inverse, the sphere couldn't be intersected
+    pub fn try_new(origin: Point, radius: Float, transform: Option<Matrix>) -> Result<Self> {
         let t = match transform {
             Some(t) => t,
             None => Matrix::identity(4),
         };
-        Sphere {
+        if !t.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "sphere" });
+        }
+        Ok(Sphere {
             origin,
             radius,
             transform: t,
-        }
+            end_transform: None,
+            material: Material::default(),
+        })
     }
 
     /// Always return the intersections vector sorted
     pub fn intersections(&self, ray: &Ray) -> Vec<Intersection<Sphere>> {
-        let mut intersections: Vec<Intersection<Sphere>> = vec![];
-        let transformed_ray = ray.transform(&self.transform.inverse());
+        self.intersection_distances(ray)
+            .into_iter()
+            .map(|t| Intersection::new(t, self.clone()))
+            .collect()
+    }
+
+    /// Makes the sphere move from `transform` to `end` during the exposure, panics when
+    /// the motion can't be interpolated (see `try_set_end_transform`)
+    pub fn set_end_transform(&mut self, end: Matrix) {
+        if let Err(error) = self.try_set_end_transform(end) {
+            panic!("Sphere::set_end_transform {}", error);
+        }
+    }
+
+    /// Rejects an end transform without an inverse, with a shear, or mirrored when the
+    /// start isn't (or the other way around), the sphere would go flat during the motion
+    pub fn try_set_end_transform(&mut self, end: Matrix) -> Result<()> {
+        if !end.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "sphere" });
+        }
+        let (from, to) = match (self.transform.decompose(), end.decompose()) {
+            (Ok(from), Ok(to)) => (from, to),
+            _ => return Err(RayTracerError::InvalidArgument(
+                "a moving sphere needs transforms made of a translation, a rotation and a scale"
+                    .to_string(),
+            )),
+        };
+        if from.scale.x * to.scale.x < 0.0 {
+            return Err(RayTracerError::InvalidArgument(
+                "the sphere can't turn into its mirror image during the motion".to_string(),
+            ));
+        }
+        self.end_transform = Some(end);
+        Ok(())
+    }
+
+    /// A transform without an inverse set through the public fields makes the sphere
+    /// invisible instead of panicking in the middle of a render
+    fn intersection_distances(&self, ray: &Ray) -> Vec<Float> {
+        let inverse = match self.transform_at(ray.time).try_inverse() {
+            Ok(inverse) => inverse,
+            Err(_) => return vec![],
+        };
+        let transformed_ray = ray.transform(&inverse);
 
         let sphere_to_ray = &transformed_ray.origin - &self.origin;
         let a = transformed_ray
             .direction
             .dot_product(&transformed_ray.direction);
         let b = 2.0 * transformed_ray.direction.dot_product(&sphere_to_ray);
-        let c = &sphere_to_ray.dot_product(&sphere_to_ray) - (&self.radius.powf(2.0));
+        let c = sphere_to_ray.dot_product(&sphere_to_ray) - self.radius.powf(2.0);
         let discriminant = b.powf(2.0) - 4.0 * a * c;
-        if discriminant >= 0.0 {
-            let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
-            let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-            intersections.push(Intersection::new(t1, self.clone()));
-            intersections.push(Intersection::new(t2, self.clone()));
+        if discriminant < 0.0 {
+            return vec![];
+        }
+        let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+        let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+        if t1 <= t2 {
+            vec![t1, t2]
+        } else {
+            vec![t2, t1]
         }
-        intersections.sort_by(|a, b| {
-            if a.t < b.t {
-                Ordering::Less
-            } else if a.t > b.t {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            }
-        });
-        intersections
     }
 
     pub fn hit<'a>(
         &self,
-        intersections: &'a Vec<Intersection<Sphere>>,
+        intersections: &'a [Intersection<Sphere>],
     ) -> Option<&'a Intersection<Sphere>> {
-        for intersec in intersections {
-            if intersec.t >= 0.0 {
-                return Some(&intersec);
-            }
-        }
-        None
+        intersections.iter().find(|intersec| intersec.t >= 0.0)
     }
 }
 
 impl Object for Sphere {
     fn normal_at(&self, point: &Point) -> Vector {
-        let transformed_point = &self.transform.inverse() * point;
+        self.normal_at_time(point, 0.0)
+    }
+
+    /// Translation, rotation and scale are interpolated separately so a turning sphere
+    /// keeps its size through the exposure
+    fn transform_at(&self, time: Float) -> Matrix {
+        match &self.end_transform {
+            Some(end) => self.transform.interpolate(end, time),
+            None => self.transform.clone(),
+        }
+    }
+
+    fn normal_at_time(&self, point: &Point, time: Float) -> Vector {
+        let transform = self.transform_at(time);
+        let transformed_point = &transform.inverse() * point;
         let transformed_vector = (&transformed_point - &self.origin).normalize();
-        let mut world_vector =
-            (&self.transform.transpose().inverse() * &transformed_vector).normalize();
+        let mut world_vector = (&transform.transpose().inverse() * &transformed_vector).normalize();
         world_vector.w = 0.0;
         world_vector
     }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object>> {
+        self.intersection_distances(ray)
+            .into_iter()
+            .map(|t| Intersection::new(t, self as &dyn Object))
+            .collect()
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn apply_transform(&mut self, matrix: &Matrix) {
+        self.transform = matrix * &self.transform;
+        if let Some(end) = &self.end_transform {
+            self.end_transform = Some(matrix * end);
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn mapping_center(&self) -> Point {
+        self.origin
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            Point::new(
+                self.origin.x - self.radius,
+                self.origin.y - self.radius,
+                self.origin.z - self.radius,
+            ),
+            Point::new(
+                self.origin.x + self.radius,
+                self.origin.y + self.radius,
+                self.origin.z + self.radius,
+            ),
+        )
+    }
+
+    /// Covers the whole path when the sphere moves. Without rotation the center moves in a
+    /// straight line and the boxes at both ends are enough, a turning sphere is sampled
+    /// along the exposure and each box is grown by how far the center goes until the next
+    /// sample
+    fn parent_space_bounds(&self) -> Bounds {
+        let bounds = self.bounds().transform(&self.transform);
+        let end = match &self.end_transform {
+            Some(end) => end,
+            None => return bounds,
+        };
+        let turns = match (self.transform.decompose(), end.decompose()) {
+            (Ok(from), Ok(to)) => !from.rotation.same_rotation(&to.rotation),
+            _ => true,
+        };
+        if !turns {
+            return bounds.merge(&self.bounds().transform(end));
+        }
+        let center_at = |time: Float| &self.transform_at(time) * &self.origin;
+        (0..MOTION_BOUNDS_STEPS).fold(bounds, |bounds, step| {
+            let time = step as Float / MOTION_BOUNDS_STEPS as Float;
+            let next = (step + 1) as Float / MOTION_BOUNDS_STEPS as Float;
+            let (start, middle, stop) = (
+                center_at(time),
+                center_at((time + next) / 2.0),
+                center_at(next),
+            );
+            let travel = (&middle - &start).magnitude() + (&stop - &middle).magnitude();
+            bounds.merge(
+                &self
+                    .bounds()
+                    .transform(&self.transform_at(time))
+                    .padded(travel),
+            )
+        })
+    }
+
+    /// The area is exact for uniform scaling, other transforms use the average scale factor
+    fn sample_surface(&self, time: Float) -> Option<SurfaceSample> {
+        let transform = self.transform_at(time);
+        if !transform.has_inverse() {
+            return None;
+        }
+        let object_point = &self.origin + &(&random_unit_vector() * self.radius);
+        let point = &transform * &object_point;
+        let scale = transform.determinant().abs().powf(1.0 / 3.0);
+        Some(SurfaceSample {
+            point,
+            normal: self.normal_at_time(&point, time),
+            area: 4.0 * PI * (self.radius * scale).powi(2),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f32::consts::PI;
-
     use super::*;
     use crate::{utils::EPSILON, vector::Vector};
 
@@ -105,6 +268,7 @@ mod tests {
         assert_eq!(s.origin.y, 2.0);
         assert_eq!(s.origin.z, 3.0);
         assert_eq!(s.radius, 1.0);
+        assert_eq!(s.material.ambient, Material::default().ambient);
     }
 
     #[test]
@@ -204,6 +368,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn should_get_the_normal_of_tranformed_sphere() {
         let sphere = Sphere::new(
             Point::new(0.0, 0.0, 0.0),
@@ -221,10 +386,191 @@ mod tests {
             1.0,
             Some(&Matrix::scaling_3d(1.0, 0.5, 1.0) * &Matrix::rotate_z_3d(PI / 5.0)),
         );
-        let normal = sphere.normal_at(&Point::new(0.0, 2.0f32.sqrt() / 2.0, -2.0f32.sqrt() / 2.0));
+        let normal = sphere.normal_at(&Point::new(
+            0.0,
+            (2.0 as Float).sqrt() / 2.0,
+            -(2.0 as Float).sqrt() / 2.0,
+        ));
         assert!((normal.x - 0.0).abs() < EPSILON);
         assert!((normal.y - 0.97014).abs() < EPSILON);
         assert!((normal.z - -0.24254).abs() < EPSILON);
         assert!((normal.w - 0.0).abs() < EPSILON);
     }
+
+    #[test]
+    fn should_get_the_bounds() {
+        let sphere = Sphere::new(Point::new(1.0, 2.0, 3.0), 2.0, None);
+        let bounds = sphere.bounds();
+        assert_eq!(bounds.min.x, -1.0);
+        assert_eq!(bounds.min.y, 0.0);
+        assert_eq!(bounds.min.z, 1.0);
+        assert_eq!(bounds.max.x, 3.0);
+        assert_eq!(bounds.max.y, 4.0);
+        assert_eq!(bounds.max.z, 5.0);
+    }
+
+    #[test]
+    fn should_get_the_parent_space_bounds() {
+        let sphere = Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(&Matrix::translation_3d(1.0, -3.0, 5.0) * &Matrix::scaling_3d(0.5, 2.0, 4.0)),
+        );
+        let bounds = sphere.parent_space_bounds();
+        assert_eq!(bounds.min.x, 0.5);
+        assert_eq!(bounds.min.y, -5.0);
+        assert_eq!(bounds.min.z, 1.0);
+        assert_eq!(bounds.max.x, 1.5);
+        assert_eq!(bounds.max.y, -1.0);
+        assert_eq!(bounds.max.z, 9.0);
+    }
+
+    #[test]
+    fn should_move_the_sphere_during_the_exposure() {
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.end_transform = Some(Matrix::translation_3d(4.0, 0.0, 0.0));
+        assert_eq!(
+            sphere.transform_at(0.5),
+            Matrix::translation_3d(2.0, 0.0, 0.0)
+        );
+
+        let ray = Ray::with_time(Point::new(2.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0.0);
+        assert_eq!(sphere.intersections(&ray).len(), 0);
+        let ray = Ray::with_time(Point::new(2.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0.5);
+        let intersections = sphere.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].t, 4.0);
+        assert_eq!(intersections[1].t, 6.0);
+    }
+
+    #[test]
+    fn should_turn_the_sphere_during_the_exposure() {
+        let mut sphere = Sphere::new(Point::new(2.0, 0.0, 0.0), 1.0, None);
+        // half a turn around y, the sphere swings to the other side of the origin
+        sphere.set_end_transform(Matrix::scaling_3d(-1.0, 1.0, -1.0));
+        let halfway = sphere.transform_at(0.5);
+        assert!(halfway.has_inverse());
+        let center = &halfway * &sphere.origin;
+        assert!(center.x.abs() < EPSILON);
+        assert!((center.z.abs() - 2.0).abs() < EPSILON);
+        let ray = Ray::with_time(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, center.z.signum()),
+            0.5,
+        );
+        let intersections = sphere.intersections(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert!((intersections[0].t - 1.0).abs() < EPSILON);
+        assert!((intersections[1].t - 3.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_get_the_bounds_of_a_turning_sphere() {
+        let mut sphere = Sphere::new(Point::new(2.0, 0.0, 0.0), 1.0, None);
+        sphere.set_end_transform(Matrix::scaling_3d(-1.0, 1.0, -1.0));
+        let bounds = sphere.parent_space_bounds();
+        for step in 0..=100 {
+            let center = &sphere.transform_at(step as Float / 100.0) * &sphere.origin;
+            for offset in [-1.0, 1.0] {
+                let x = Point::new(center.x + offset, center.y, center.z);
+                let z = Point::new(center.x, center.y, center.z + offset);
+                assert!(bounds.contains_point(&x) && bounds.contains_point(&z));
+            }
+        }
+    }
+
+    #[test]
+    fn should_reject_motions_that_flatten_the_sphere() {
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        assert_eq!(
+            sphere.try_set_end_transform(Matrix::scaling_3d(1.0, 0.0, 1.0)),
+            Err(RayTracerError::NonInvertibleTransform { object: "sphere" })
+        );
+        assert!(sphere
+            .try_set_end_transform(Matrix::scaling_3d(-1.0, 1.0, 1.0))
+            .is_err());
+        assert!(sphere
+            .try_set_end_transform(Matrix::shear_3d(1.0, 0.0, 0.0, 0.0, 0.0, 0.0))
+            .is_err());
+        assert!(sphere.end_transform.is_none());
+        assert!(sphere
+            .try_set_end_transform(Matrix::translation_3d(1.0, 0.0, 0.0))
+            .is_ok());
+    }
+
+    #[test]
+    fn should_get_the_normal_of_a_moving_sphere() {
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.end_transform = Some(Matrix::translation_3d(4.0, 0.0, 0.0));
+        let normal = sphere.normal_at_time(&Point::new(3.0, 0.0, 0.0), 0.5);
+        assert_eq!(normal.x, 1.0);
+        let normal = sphere.normal_at(&Point::new(1.0, 0.0, 0.0));
+        assert_eq!(normal.x, 1.0);
+    }
+
+    #[test]
+    fn should_get_the_bounds_of_the_whole_motion() {
+        let mut sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sphere.end_transform = Some(Matrix::translation_3d(4.0, 0.0, 0.0));
+        sphere.apply_transform(&Matrix::translation_3d(0.0, 1.0, 0.0));
+        let bounds = sphere.parent_space_bounds();
+        assert_eq!(bounds.min.x, -1.0);
+        assert_eq!(bounds.max.x, 5.0);
+        assert_eq!(bounds.min.y, 0.0);
+        assert_eq!(bounds.max.y, 2.0);
+    }
+
+    #[test]
+    fn should_sample_points_on_the_surface() {
+        let sphere = Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(&Matrix::translation_3d(0.0, 3.0, 0.0) * &Matrix::scaling_3d(2.0, 2.0, 2.0)),
+        );
+        for _ in 0..20 {
+            let sample = sphere.sample_surface(0.0).unwrap();
+            let from_center = &sample.point - &Point::new(0.0, 3.0, 0.0);
+            assert!((from_center.magnitude() - 2.0).abs() < EPSILON);
+            assert!((sample.normal.dot_product(&from_center.normalize()) - 1.0).abs() < EPSILON);
+            assert!((sample.area - 16.0 * PI).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn should_reject_a_transform_without_inverse() {
+        let flat = Sphere::try_new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::scaling_3d(1.0, 0.0, 1.0)),
+        );
+        assert_eq!(
+            flat.unwrap_err(),
+            RayTracerError::NonInvertibleTransform { object: "sphere" }
+        );
+        assert!(Sphere::try_new(Point::new(0.0, 0.0, 0.0), 1.0, None).is_ok());
+    }
+
+    #[test]
+    fn should_not_panic_on_transforms_set_without_inverse() {
+        let ray = || Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let mut flat = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        flat.transform = Matrix::scaling_3d(1.0, 0.0, 1.0);
+        assert!(flat.intersect(&ray()).is_empty());
+        assert!(flat.sample_surface(0.0).is_none());
+
+        let mut moving = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        moving.end_transform = Some(Matrix::scaling_3d(0.0, 0.0, 0.0));
+        let late = Ray::with_time(ray().origin, ray().direction, 1.0);
+        assert!(moving.intersect(&late).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Sphere::new the transform of the sphere can't be inverted")]
+    fn should_panic_on_a_transform_without_inverse() {
+        Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::scaling_3d(0.0, 0.0, 0.0)),
+        );
+    }
 }
@@ -0,0 +1,374 @@
+use std::sync::Arc;
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    error::{RayTracerError, Result},
+    matrix::Matrix,
+    object::Object,
+    pattern::Pattern,
+    point::Point,
+    utils::{consts::PI, Float},
+};
+
+/// How a point in pattern space becomes texture coordinates in [0, 1]
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UvMapping {
+    /// Wraps the image around the unit sphere, u follows the longitude and v the latitude
+    Spherical,
+    /// Tiles the image on the xz plane, one copy per unit square
+    Planar,
+    /// Wraps the image around the y axis, repeating every unit of height
+    Cylindrical,
+    /// Puts the whole image on every face of the cube from -1 to 1
+    Cube,
+    /// Uses the coordinates stored on the shape (triangle vertices), planar when there are none
+    Surface,
+}
+
+/// Lookup of the texels around the texture coordinates
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// What happens to texture coordinates outside of [0, 1]
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+}
+
+#[allow(dead_code)]
+impl UvMapping {
+    pub fn map(&self, point: &Point, uv: Option<(Float, Float)>) -> (Float, Float) {
+        match self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cube => cube_map(point),
+            UvMapping::Surface => match uv {
+                Some(uv) => uv,
+                None => planar_map(point),
+            },
+        }
+    }
+}
+
+/// Pattern that samples an image
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    pub image: Arc<Canvas>,
+    pub mapping: UvMapping,
+    pub filter: Filter,
+    pub wrap: Wrap,
+    pub transform: Matrix,
+}
+
+#[allow(dead_code)]
+impl ImageTexture {
+    /// Bilinear and repeating by default
+    pub fn new(image: Arc<Canvas>, mapping: UvMapping, transform: Option<Matrix>) -> Self {
+        match ImageTexture::try_new(image, mapping, transform) {
+            Ok(texture) => texture,
+            Err(error) => panic!("ImageTexture::new {}", error),
+        }
+    }
+
+    /// Rejects empty images and transforms without an inverse
+    pub fn try_new(
+        image: Arc<Canvas>,
+        mapping: UvMapping,
+        transform: Option<Matrix>,
+    ) -> Result<Self> {
+        if image.width == 0 || image.height == 0 {
+            return Err(RayTracerError::InvalidArgument(
+                "the image can't be empty".to_string(),
+            ));
+        }
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        if !t.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "texture" });
+        }
+        Ok(ImageTexture {
+            image,
+            mapping,
+            filter: Filter::Bilinear,
+            wrap: Wrap::Repeat,
+            transform: t,
+        })
+    }
+
+    pub fn uv_color(&self, u: Float, v: Float) -> Color {
+        sample_image(&self.image, u, v, self.filter, self.wrap)
+    }
+
+    /// Point in pattern space given to the mapping, measured from the center of the object
+    /// so a sphere built around another point still gets the whole image
+    pub fn mapping_point(&self, object: &dyn Object, world_point: &Point, time: Float) -> Point {
+        let object_point = &object.transform_at(time).inverse() * world_point;
+        let offset = &object_point - &object.mapping_center();
+        &self.transform.inverse() * &Point::new(offset.x, offset.y, offset.z)
+    }
+}
+
+/// Color of an image at texture coordinates, v goes up so (0, 0) is the bottom left
+pub fn sample_image(image: &Canvas, u: Float, v: Float, filter: Filter, wrap: Wrap) -> Color {
+    let texel = |x: i64, y: i64| {
+        let (width, height) = (image.width as i64, image.height as i64);
+        let (x, y) = match wrap {
+            Wrap::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            Wrap::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+        };
+        image.pixels[y as usize][x as usize]
+    };
+    let x = u * image.width as Float;
+    let y = (1.0 - v) * image.height as Float;
+    match filter {
+        Filter::Nearest => texel(x.floor() as i64, y.floor() as i64),
+        Filter::Bilinear => {
+            // texel centers are at half coordinates
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (tx, ty) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top = lerp_color(&texel(x0, y0), &texel(x0 + 1, y0), tx);
+            let bottom = lerp_color(&texel(x0, y0 + 1), &texel(x0 + 1, y0 + 1), tx);
+            lerp_color(&top, &bottom, ty)
+        }
+    }
+}
+
+impl Pattern for ImageTexture {
+    fn pattern_at(&self, point: &Point, uv: Option<(Float, Float)>) -> Color {
+        let (u, v) = self.mapping.map(point, uv);
+        self.uv_color(u, v)
+    }
+
+    fn pattern_at_object(
+        &self,
+        object: &dyn Object,
+        world_point: &Point,
+        uv: Option<(Float, Float)>,
+        time: Float,
+    ) -> Color {
+        self.pattern_at(&self.mapping_point(object, world_point, time), uv)
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+}
+
+fn lerp_color(a: &Color, b: &Color, t: Float) -> Color {
+    &(a * (1.0 - t)) + &(b * t)
+}
+
+fn spherical_map(point: &Point) -> (Float, Float) {
+    let theta = point.x.atan2(point.z);
+    let radius = (point.x * point.x + point.y * point.y + point.z * point.z).sqrt();
+    if radius == 0.0 {
+        return (0.5, 0.5);
+    }
+    let phi = (point.y / radius).clamp(-1.0, 1.0).acos();
+    let raw_u = theta / (2.0 * PI);
+    (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+fn planar_map(point: &Point) -> (Float, Float) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+fn cylindrical_map(point: &Point) -> (Float, Float) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+    (1.0 - (raw_u + 0.5), point.y.rem_euclid(1.0))
+}
+
+fn cube_map(point: &Point) -> (Float, Float) {
+    let face = |a: Float| (a + 1.0).rem_euclid(2.0) / 2.0;
+    let (abs_x, abs_y, abs_z) = (point.x.abs(), point.y.abs(), point.z.abs());
+    let coord = abs_x.max(abs_y).max(abs_z);
+    if coord == point.x {
+        // right
+        (face(-point.z), face(point.y))
+    } else if coord == -point.x {
+        // left
+        (face(point.z), face(point.y))
+    } else if coord == point.y {
+        // up
+        (face(point.x), face(-point.z))
+    } else if coord == -point.y {
+        // down
+        (face(point.x), face(point.z))
+    } else if coord == point.z {
+        // front
+        (face(point.x), face(point.y))
+    } else {
+        // back
+        (face(-point.x), face(point.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::EPSILON;
+
+    /// 2x2 image, red and green on the top row, blue and white at the bottom
+    fn checker_image() -> Arc<Canvas> {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(&Color::new(1.0, 0.0, 0.0), 0, 0);
+        canvas.write_pixel(&Color::new(0.0, 1.0, 0.0), 1, 0);
+        canvas.write_pixel(&Color::new(0.0, 0.0, 1.0), 0, 1);
+        canvas.write_pixel(&Color::new(1.0, 1.0, 1.0), 1, 1);
+        Arc::new(canvas)
+    }
+
+    fn assert_uv(actual: (Float, Float), expected: (Float, Float)) {
+        assert!((actual.0 - expected.0).abs() < EPSILON);
+        assert!((actual.1 - expected.1).abs() < EPSILON);
+    }
+
+    #[test]
+    fn should_map_points_on_a_sphere() {
+        let half = (2.0 as Float).sqrt() / 2.0;
+        let cases = [
+            (Point::new(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Point::new(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Point::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Point::new(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Point::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Point::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (Point::new(half, half, 0.0), (0.25, 0.75)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(UvMapping::Spherical.map(&point, None), expected);
+        }
+    }
+
+    #[test]
+    fn should_map_points_on_a_plane() {
+        let cases = [
+            (Point::new(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (Point::new(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (Point::new(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (Point::new(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (Point::new(-0.25, 0.0, -1.75), (0.75, 0.25)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(UvMapping::Planar.map(&point, None), expected);
+        }
+    }
+
+    #[test]
+    fn should_map_points_on_a_cylinder() {
+        let cases = [
+            (Point::new(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (Point::new(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (Point::new(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (Point::new(1.0, 0.5, 0.0), (0.25, 0.5)),
+            (Point::new(0.0, 0.5, 1.0), (0.5, 0.5)),
+            (Point::new(-1.0, 0.25, 0.0), (0.75, 0.25)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(UvMapping::Cylindrical.map(&point, None), expected);
+        }
+    }
+
+    #[test]
+    fn should_map_points_on_a_cube() {
+        let cases = [
+            (Point::new(-0.5, 0.5, 1.0), (0.25, 0.75)),
+            (Point::new(0.5, -0.5, 1.0), (0.75, 0.25)),
+            (Point::new(0.5, 0.5, -1.0), (0.25, 0.75)),
+            (Point::new(-1.0, 0.5, -0.5), (0.25, 0.75)),
+            (Point::new(1.0, 0.5, 0.5), (0.25, 0.75)),
+            (Point::new(-0.5, 1.0, -0.5), (0.25, 0.75)),
+            (Point::new(-0.5, -1.0, 0.5), (0.25, 0.75)),
+        ];
+        for (point, expected) in cases {
+            assert_uv(UvMapping::Cube.map(&point, None), expected);
+        }
+    }
+
+    #[test]
+    fn should_prefer_the_surface_uvs() {
+        let point = Point::new(0.25, 0.0, 0.5);
+        assert_uv(UvMapping::Surface.map(&point, Some((0.9, 0.1))), (0.9, 0.1));
+        assert_uv(UvMapping::Surface.map(&point, None), (0.25, 0.5));
+    }
+
+    #[test]
+    fn should_sample_the_nearest_texel() {
+        let mut texture = ImageTexture::new(checker_image(), UvMapping::Planar, None);
+        texture.filter = Filter::Nearest;
+        assert_eq!(texture.uv_color(0.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.uv_color(0.75, 0.75), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(texture.uv_color(0.25, 0.25), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(texture.uv_color(0.75, 0.25), Color::new(1.0, 1.0, 1.0));
+        // repeating, one tile to the right is the same red texel
+        assert_eq!(texture.uv_color(1.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        texture.wrap = Wrap::Clamp;
+        assert_eq!(texture.uv_color(1.25, 0.75), Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn should_blend_texels_with_bilinear_filtering() {
+        let mut texture = ImageTexture::new(checker_image(), UvMapping::Planar, None);
+        // texel centers give back the texel
+        assert_eq!(texture.uv_color(0.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        // half way between the top texels
+        assert_eq!(texture.uv_color(0.5, 0.75), Color::new(0.5, 0.5, 0.0));
+        // the center of the image mixes all four
+        assert_eq!(texture.uv_color(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+        // repeating mixes the left edge with the right one
+        assert_eq!(texture.uv_color(0.0, 0.75), Color::new(0.5, 0.5, 0.0));
+        texture.wrap = Wrap::Clamp;
+        assert_eq!(texture.uv_color(0.0, 0.75), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_apply_the_pattern_transform() {
+        let mut texture = ImageTexture::new(
+            checker_image(),
+            UvMapping::Planar,
+            Some(Matrix::scaling_3d(2.0, 2.0, 2.0)),
+        );
+        texture.filter = Filter::Nearest;
+        let sphere = crate::sphere::Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        // (1.5, 0, 0.5) is (0.75, 0, 0.25) in pattern space, the bottom right texel
+        assert_eq!(
+            texture.pattern_at_object(&sphere, &Point::new(1.5, 0.0, 0.5), None, 0.0),
+            Color::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn should_wrap_around_the_center_of_the_sphere() {
+        let mut texture = ImageTexture::new(checker_image(), UvMapping::Spherical, None);
+        texture.filter = Filter::Nearest;
+        let centered = crate::sphere::Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        let moved = crate::sphere::Sphere::new(Point::new(0.0, 5.0, 0.0), 1.0, None);
+        for (x, y, z) in [(0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (0.0, -0.5, -0.8)] {
+            assert_eq!(
+                texture.pattern_at_object(&moved, &Point::new(x, y + 5.0, z), None, 0.0),
+                texture.pattern_at_object(&centered, &Point::new(x, y, z), None, 0.0)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ImageTexture::new the image can't be empty")]
+    fn should_panic_on_empty_images() {
+        ImageTexture::new(Arc::new(Canvas::new(0, 3)), UvMapping::Planar, None);
+    }
+}
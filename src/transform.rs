@@ -0,0 +1,189 @@
+use crate::{matrix::Matrix, quaternion::Quaternion, utils::Float, vector::Vector};
+
+/// Transformation matrix built one step at a time, in reading order: each step happens
+/// after the ones before it, so `Transform::identity().scale(2.0, 2.0, 2.0).translate(1.0,
+/// 0.0, 0.0)` scales then moves, which is `translation * scaling` as a product
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub matrix: Matrix,
+}
+
+#[allow(dead_code)]
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            matrix: Matrix::identity(4),
+        }
+    }
+
+    /// Continues from an existing transform
+    pub fn from_matrix(matrix: Matrix) -> Self {
+        Transform { matrix }
+    }
+
+    /// Applies `matrix` after the steps so far
+    pub fn then(self, matrix: &Matrix) -> Self {
+        Transform {
+            matrix: matrix * &self.matrix,
+        }
+    }
+
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
+        self.then(&Matrix::translation_3d(x, y, z))
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
+        self.then(&Matrix::scaling_3d(x, y, z))
+    }
+
+    /// Angles are in radians, like for `Matrix::rotate_x_3d`
+    pub fn rotate_x(self, rad: Float) -> Self {
+        self.then(&Matrix::rotate_x_3d(rad))
+    }
+
+    pub fn rotate_y(self, rad: Float) -> Self {
+        self.then(&Matrix::rotate_y_3d(rad))
+    }
+
+    pub fn rotate_z(self, rad: Float) -> Self {
+        self.then(&Matrix::rotate_z_3d(rad))
+    }
+
+    pub fn rotate_axis(self, axis: &Vector, rad: Float) -> Self {
+        self.then(&Matrix::rotate_axis_3d(axis, rad))
+    }
+
+    pub fn rotate(self, rotation: &Quaternion) -> Self {
+        self.then(&rotation.to_matrix())
+    }
+
+    pub fn shear(
+        self,
+        x_to_y: Float,
+        x_to_z: Float,
+        y_to_x: Float,
+        y_to_z: Float,
+        z_to_x: Float,
+        z_to_y: Float,
+    ) -> Self {
+        self.then(&Matrix::shear_3d(
+            x_to_y, x_to_z, y_to_x, y_to_z, z_to_x, z_to_y,
+        ))
+    }
+
+    pub fn build(self) -> Matrix {
+        self.matrix
+    }
+}
+
+impl From<Transform> for Matrix {
+    fn from(transform: Transform) -> Matrix {
+        transform.matrix
+    }
+}
+
+/// An affine matrix split in its parts, applied as scale, then rotation, then translation
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Decomposition {
+    pub translation: Vector,
+    pub rotation: Quaternion,
+    /// A mirrored matrix gets a negative x scale
+    pub scale: Vector,
+}
+
+#[allow(dead_code)]
+impl Decomposition {
+    /// The matrix the parts came from
+    pub fn to_matrix(&self) -> Matrix {
+        Transform::identity()
+            .scale(self.scale.x, self.scale.y, self.scale.z)
+            .rotate(&self.rotation)
+            .translate(self.translation.x, self.translation.y, self.translation.z)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::RayTracerError, point::Point, utils::consts::PI, utils::EPSILON};
+
+    fn assert_vector(actual: Vector, expected: Vector) {
+        assert!(
+            (actual.x - expected.x).abs() < EPSILON
+                && (actual.y - expected.y).abs() < EPSILON
+                && (actual.z - expected.z).abs() < EPSILON,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn should_apply_the_steps_in_reading_order() {
+        let transform = Transform::identity()
+            .rotate_x(PI / 2.0)
+            .scale(5.0, 5.0, 5.0)
+            .translate(10.0, 5.0, 7.0);
+        let expected = &(&Matrix::translation_3d(10.0, 5.0, 7.0)
+            * &Matrix::scaling_3d(5.0, 5.0, 5.0))
+            * &Matrix::rotate_x_3d(PI / 2.0);
+        assert_eq!(transform.matrix, expected);
+        let point = &Matrix::from(transform) * &Point::new(1.0, 0.0, 1.0);
+        assert_vector(
+            &point - &Point::new(0.0, 0.0, 0.0),
+            Vector::new(15.0, 0.0, 7.0),
+        );
+    }
+
+    #[test]
+    fn should_decompose_an_affine_matrix() {
+        let rotation =
+            Quaternion::from_matrix(&(&Matrix::rotate_y_3d(0.7) * &Matrix::rotate_x_3d(-1.2)));
+        let matrix = Transform::identity()
+            .scale(2.0, 3.0, 0.5)
+            .rotate(&rotation)
+            .translate(1.0, -2.0, 3.0)
+            .build();
+        let parts = matrix.decompose().unwrap();
+        assert_vector(parts.translation, Vector::new(1.0, -2.0, 3.0));
+        assert_vector(parts.scale, Vector::new(2.0, 3.0, 0.5));
+        assert!(parts.rotation.same_rotation(&rotation));
+        assert_eq!(parts.to_matrix(), matrix);
+    }
+
+    #[test]
+    fn should_decompose_a_mirror() {
+        let matrix = Transform::identity()
+            .scale(1.0, -1.0, 1.0)
+            .rotate_z(0.3)
+            .build();
+        let parts = matrix.decompose().unwrap();
+        assert!(parts.scale.x < 0.0);
+        assert_eq!(parts.to_matrix(), matrix);
+    }
+
+    #[test]
+    fn should_reject_matrices_without_parts() {
+        let sheared = Transform::identity()
+            .shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+            .build();
+        assert!(matches!(
+            sheared.decompose(),
+            Err(RayTracerError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            Matrix::scaling_3d(1.0, 0.0, 1.0).decompose().unwrap_err(),
+            RayTracerError::SingularMatrix
+        );
+        assert_eq!(
+            Matrix::identity(3).decompose().unwrap_err(),
+            RayTracerError::DimensionMismatch {
+                expected: (4, 4),
+                found: (3, 3)
+            }
+        );
+    }
+}
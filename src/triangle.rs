@@ -0,0 +1,348 @@
+use crate::{
+    bounds::Bounds,
+    error::{RayTracerError, Result},
+    intersection::Intersection,
+    material::Material,
+    matrix::Matrix,
+    object::{Object, SurfaceSample},
+    point::Point,
+    ray::Ray,
+    utils::{random_float, Float, EPSILON},
+    vector::Vector,
+};
+
+/// Flat triangle, the building block of meshes
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    pub e1: Vector,
+    pub e2: Vector,
+    /// Normal in object space, the same everywhere on the triangle
+    pub normal: Vector,
+    /// Texture coordinates of p1, p2 and p3, interpolated over the face
+    pub uvs: Option<[(Float, Float); 3]>,
+    pub transform: Matrix,
+    pub material: Material,
+}
+
+#[allow(dead_code)]
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point, transform: Option<Matrix>) -> Self {
+        match Triangle::try_new(p1, p2, p3, transform) {
+            Ok(triangle) => triangle,
+            Err(error) => panic!("Triangle::new {}", error),
+        }
+    }
+
+    /// Rejects transforms without an inverse and points on a line, the triangle would
+    /// have no normal
+    pub fn try_new(p1: Point, p2: Point, p3: Point, transform: Option<Matrix>) -> Result<Self> {
+        let t = match transform {
+            Some(t) => t,
+            None => Matrix::identity(4),
+        };
+        if !t.has_inverse() {
+            return Err(RayTracerError::NonInvertibleTransform { object: "triangle" });
+        }
+        let e1 = &p2 - &p1;
+        let e2 = &p3 - &p1;
+        let cross = e2.cross_product(&e1);
+        // relative to the edges so that small triangles are kept, the sine of the angle
+        // between the edges is compared
+        if cross.magnitude() <= EPSILON * e1.magnitude() * e2.magnitude() {
+            return Err(RayTracerError::InvalidArgument(
+                "the points of a triangle can't be on the same line".to_string(),
+            ));
+        }
+        let normal = cross.normalize();
+        Ok(Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal,
+            uvs: None,
+            transform: t,
+            material: Material::default(),
+        })
+    }
+
+    /// Möller–Trumbore, returns the distance along the ray in object space and the
+    /// barycentric coordinates of the hit (weights of p2 and p3)
+    /// A transform without an inverse set through the public fields makes the triangle
+    /// invisible instead of panicking
+    fn local_intersection(&self, ray: &Ray) -> Option<(Float, Float, Float)> {
+        let ray = ray.transform(&self.transform.try_inverse().ok()?);
+        let dir_cross_e2 = ray.direction.cross_product(&self.e2);
+        let determinant = self.e1.dot_product(&dir_cross_e2);
+        // the ray is parallel to the plane, relative to the size of the triangle and of
+        // the direction so that the scale of the scene doesn't matter
+        let scale = self.e1.magnitude() * self.e2.magnitude() * ray.direction.magnitude();
+        if determinant.abs() <= EPSILON * scale {
+            return None;
+        }
+        let f = 1.0 / determinant;
+        let p1_to_origin = &ray.origin - &self.p1;
+        let u = f * p1_to_origin.dot_product(&dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let origin_cross_e1 = p1_to_origin.cross_product(&self.e1);
+        let v = f * ray.direction.dot_product(&origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        Some((f * self.e2.dot_product(&origin_cross_e1), u, v))
+    }
+
+    /// Texture coordinates at the barycentric coordinates `u` and `v`
+    pub fn uv_at(&self, u: Float, v: Float) -> Option<(Float, Float)> {
+        self.uvs.map(|[uv1, uv2, uv3]| {
+            let w = 1.0 - u - v;
+            (
+                uv1.0 * w + uv2.0 * u + uv3.0 * v,
+                uv1.1 * w + uv2.1 * u + uv3.1 * v,
+            )
+        })
+    }
+}
+
+impl Object for Triangle {
+    fn normal_at(&self, _point: &Point) -> Vector {
+        let mut world_normal = &self.transform.inverse().transpose() * &self.normal;
+        world_normal.w = 0.0;
+        world_normal.normalize()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object>> {
+        match self.local_intersection(ray) {
+            Some((t, u, v)) => match self.uv_at(u, v) {
+                Some(uv) => vec![Intersection::with_uv(t, self as &dyn Object, uv)],
+                None => vec![Intersection::new(t, self as &dyn Object)],
+            },
+            None => vec![],
+        }
+    }
+
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn apply_transform(&mut self, matrix: &Matrix) {
+        self.transform = matrix * &self.transform;
+    }
+
+    fn bounds(&self) -> Bounds {
+        let mut bounds = Bounds::empty();
+        bounds.add_point(&self.p1);
+        bounds.add_point(&self.p2);
+        bounds.add_point(&self.p3);
+        bounds
+    }
+
+    fn uv_directions(&self, time: Float) -> Option<(Vector, Vector)> {
+        let [uv1, uv2, uv3] = self.uvs?;
+        let (du1, dv1) = (uv2.0 - uv1.0, uv2.1 - uv1.1);
+        let (du2, dv2) = (uv3.0 - uv1.0, uv3.1 - uv1.1);
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() < EPSILON {
+            return None;
+        }
+        // solves e1 = du1 * u_direction + dv1 * v_direction and the same for e2
+        let u_direction = &(&(&self.e1 * dv2) - &(&self.e2 * dv1)) * (1.0 / determinant);
+        let v_direction = &(&(&self.e2 * du1) - &(&self.e1 * du2)) * (1.0 / determinant);
+        let transform = self.transform_at(time);
+        Some((
+            (&transform * &u_direction).normalize(),
+            (&transform * &v_direction).normalize(),
+        ))
+    }
+
+    fn sample_surface(&self, _time: Float) -> Option<SurfaceSample> {
+        if !self.transform.has_inverse() {
+            return None;
+        }
+        let p1 = &self.transform * &self.p1;
+        let e1 = &(&self.transform * &self.p2) - &p1;
+        let e2 = &(&self.transform * &self.p3) - &p1;
+        // folding the square onto the triangle keeps the distribution uniform
+        let (mut u, mut v) = (random_float(), random_float());
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        Some(SurfaceSample {
+            point: &p1 + &(&(&e1 * u) + &(&e2 * v)),
+            normal: self.normal_at(&p1),
+            area: e1.cross_product(&e2).magnitude() / 2.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_triangle() -> Triangle {
+        Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            None,
+        )
+    }
+
+    #[test]
+    fn should_create_a_triangle() {
+        let triangle = default_triangle();
+        assert_eq!(triangle.e1.x, -1.0);
+        assert_eq!(triangle.e1.y, -1.0);
+        assert_eq!(triangle.e2.x, 1.0);
+        assert_eq!(triangle.e2.y, -1.0);
+        assert_eq!(triangle.normal.z, -1.0);
+        let normal = triangle.normal_at(&Point::new(0.0, 0.5, 0.0));
+        assert_eq!(normal.x, 0.0);
+        assert_eq!(normal.y, 0.0);
+        assert_eq!(normal.z, -1.0);
+    }
+
+    #[test]
+    fn should_miss_a_parallel_ray() {
+        let triangle = default_triangle();
+        let ray = Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(triangle.intersect(&ray).len(), 0);
+    }
+
+    #[test]
+    fn should_miss_outside_the_edges() {
+        let triangle = default_triangle();
+        for origin in [
+            Point::new(1.0, 1.0, -2.0),
+            Point::new(-1.0, 1.0, -2.0),
+            Point::new(0.0, -1.0, -2.0),
+        ] {
+            let ray = Ray::new(origin, Vector::new(0.0, 0.0, 1.0));
+            assert_eq!(triangle.intersect(&ray).len(), 0);
+        }
+    }
+
+    #[test]
+    fn should_intersect_a_triangle() {
+        let triangle = default_triangle();
+        let ray = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = triangle.intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].t, 2.0);
+    }
+
+    #[test]
+    fn should_intersect_a_transformed_triangle() {
+        let triangle = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Some(Matrix::translation_3d(0.0, 0.0, 3.0)),
+        );
+        let ray = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = triangle.intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].t, 5.0);
+        let bounds = triangle.parent_space_bounds();
+        assert_eq!(bounds.min.z, 3.0);
+        assert_eq!(bounds.min.x, -1.0);
+        assert_eq!(bounds.max.y, 1.0);
+    }
+
+    #[test]
+    fn should_sample_points_on_the_surface() {
+        let triangle = Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Some(Matrix::scaling_3d(2.0, 2.0, 2.0)),
+        );
+        for _ in 0..20 {
+            let sample = triangle.sample_surface(0.0).unwrap();
+            assert_eq!(sample.point.z, 0.0);
+            assert!(sample.point.y >= 0.0);
+            assert!(sample.point.y <= 2.0 - sample.point.x.abs() + EPSILON);
+            assert_eq!(sample.normal.z, -1.0);
+            assert_eq!(sample.area, 4.0);
+        }
+    }
+
+    #[test]
+    fn should_interpolate_the_vertex_uvs() {
+        let mut triangle = default_triangle();
+        let ray = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(triangle.intersect(&ray)[0].uv, None);
+
+        triangle.uvs = Some([(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+        let intersections = triangle.intersect(&ray);
+        assert_eq!(intersections[0].uv, Some((0.5, 0.5)));
+        assert_eq!(triangle.uv_at(1.0, 0.0), Some((0.0, 0.0)));
+        assert_eq!(triangle.uv_at(0.0, 1.0), Some((1.0, 0.0)));
+    }
+
+    #[test]
+    fn should_reject_points_on_a_line() {
+        let line = Triangle::try_new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 1.0),
+            Point::new(2.0, 2.0, 2.0),
+            None,
+        );
+        assert_eq!(
+            line.unwrap_err().to_string(),
+            "the points of a triangle can't be on the same line"
+        );
+        let point = Triangle::try_new(
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            None,
+        );
+        assert!(point.is_err());
+    }
+
+    #[test]
+    fn should_keep_small_triangles() {
+        let triangle = Triangle::try_new(
+            Point::new(0.0, 0.003, 0.0),
+            Point::new(-0.003, 0.0, 0.0),
+            Point::new(0.003, 0.0, 0.0),
+            None,
+        )
+        .unwrap();
+        let ray = Ray::new(Point::new(0.0, 0.001, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = triangle.intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert!((intersections[0].t - 2.0).abs() < EPSILON);
+
+        // large but almost on a line
+        assert!(Triangle::try_new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1000.0, 0.0, 0.0),
+            Point::new(2000.0, 0.001, 0.0),
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_not_panic_on_a_transform_set_without_inverse() {
+        let mut triangle = default_triangle();
+        triangle.transform = Matrix::scaling_3d(0.0, 1.0, 1.0);
+        let ray = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(triangle.intersect(&ray).len(), 0);
+        assert!(triangle.sample_surface(0.0).is_none());
+    }
+}
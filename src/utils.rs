@@ -1,39 +1,68 @@
+use std::cell::RefCell;
 use std::fs::File;
-use rand::Rng;
-use crate::canvas::Canvas;
-use crate::color::Color;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::vector::Vector;
 use std::io::{Result, Write};
 
+/// Scalar of every point, vector, color and matrix, `f64` when the crate is built with the
+/// `f64` feature. Single precision is enough for most scenes, double precision avoids
+/// self intersections when the coordinates get large
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// Mathematical constants with the precision of `Float`
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
 #[allow(dead_code)]
-pub const EPSILON: f32 = 0.00001;
+pub const EPSILON: Float = 0.00001;
+
+thread_local! {
+    /// Generator behind every random function, seeded by the OS until `seed_random` is called
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
 
-pub fn write_to_file(path: &str, content: &str) -> Result<()>{
+pub fn write_to_file(path: &str, content: &[u8]) -> Result<()>{
     let mut file = File::create(path)?;
-    file.write(content.as_bytes())?;
+    file.write_all(content)?;
     Ok(())
 }
 
-#[allow(dead_code)]
-pub fn get_random_color() -> Color {
-    let mut rng = rand::thread_rng();
-    Color::new(
-        rng.gen_range(0.0..1.0),
-        rng.gen_range(0.0..1.0),
-        rng.gen_range(0.0..1.0),
-    )
+/// Makes the random numbers of the current thread reproducible
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
 }
 
-#[allow(dead_code)]
-pub fn paint_square(canvas: &mut Canvas, x: i32, y: i32, size: i32) {
-    let initial_pos_x = x - size;
-    let initial_pos_y = y - size;
-    let final_pos_x = initial_pos_x + (2 * size + 1);
-    let final_pos_y = initial_pos_y + (2 * size + 1);
-
-    let color = get_random_color();
-    for row_index in initial_pos_y..final_pos_y {
-        for col_index in initial_pos_x..final_pos_x {
-            canvas.write_pixel(&color, col_index as usize, row_index as usize);
-        }
-    }
+/// Uniform random number in [0, 1)
+pub fn random_float() -> Float {
+    RNG.with(|rng| rng.borrow_mut().gen_range(0.0..1.0))
+}
+
+/// Uniform random point inside a disk of radius 1 centered in the origin
+pub fn random_in_unit_disk() -> (Float, Float) {
+    let radius = random_float().sqrt();
+    let theta = 2.0 * consts::PI * random_float();
+    (radius * theta.cos(), radius * theta.sin())
+}
+
+/// Random direction in the hemisphere around `normal`, directions close to the normal are
+/// more likely (pdf = cos(theta) / PI)
+pub fn random_cosine_direction(normal: &Vector) -> Vector {
+    let (x, y) = random_in_unit_disk();
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    let (tangent, bitangent) = normal.orthonormal_basis();
+    &(&(&tangent * x) + &(&bitangent * y)) + &(normal * z)
+}
+
+/// Uniform random direction on the unit sphere
+pub fn random_unit_vector() -> Vector {
+    let z = 1.0 - 2.0 * random_float();
+    let radius = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * consts::PI * random_float();
+    Vector::new(radius * phi.cos(), radius * phi.sin(), z)
 }
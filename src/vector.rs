@@ -1,27 +1,27 @@
 use crate::matrix::Matrix;
 use crate::point::Point;
 #[allow(unused_imports)]
-use crate::utils::EPSILON;
+use crate::utils::{Float, EPSILON};
 use std::ops::Add;
 use std::ops::Div;
 use std::ops::Mul;
 use std::ops::Neg;
 use std::ops::Sub;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct Vector {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub w: f32,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+    pub w: Float,
 }
 
 impl Vector {
-    pub fn new(x: f32, y: f32, z: f32) -> Vector {
+    pub fn new(x: Float, y: Float, z: Float) -> Vector {
         Vector { x, y, z, w: 0.0 }
     }
 
-    pub fn magnitude(&self) -> f32 {
+    pub fn magnitude(&self) -> Float {
         (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).powf(0.5)
     }
 
@@ -33,7 +33,7 @@ impl Vector {
         Vector::new(self.x / magnitude, self.y / magnitude, self.z / magnitude)
     }
 
-    pub fn dot_product(&self, other: &Vector) -> f32 {
+    pub fn dot_product(&self, other: &Vector) -> Float {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
@@ -45,7 +45,7 @@ impl Vector {
         )
     }
 
-    pub fn to_matrix(&self) -> Matrix {
+    pub fn to_matrix(self) -> Matrix {
         let mut m = Matrix::new(4, 1);
         m.patch(vec![vec![self.x], vec![self.y], vec![self.z], vec![self.w]]);
         m
@@ -54,6 +54,18 @@ impl Vector {
     pub fn reflect(&self, normal: &Vector) -> Vector {
         self - &(normal * (self.dot_product(normal) * 2.0))
     }
+
+    /// Two unit vectors perpendicular to this one (and to each other), expects it normalized
+    pub fn orthonormal_basis(&self) -> (Vector, Vector) {
+        let helper = if self.x.abs() > 0.9 {
+            Vector::new(0.0, 1.0, 0.0)
+        } else {
+            Vector::new(1.0, 0.0, 0.0)
+        };
+        let tangent = helper.cross_product(self).normalize();
+        let bitangent = self.cross_product(&tangent);
+        (tangent, bitangent)
+    }
 }
 
 // vector + vector
@@ -107,17 +119,17 @@ impl Neg for &Vector {
 }
 
 // vector * scalar
-impl Mul<f32> for &Vector {
+impl Mul<Float> for &Vector {
     type Output = Vector;
-    fn mul(self, scalar: f32) -> Self::Output {
+    fn mul(self, scalar: Float) -> Self::Output {
         Vector::new(self.x * scalar, self.y * scalar, self.z * scalar)
     }
 }
 
 // vector / scalar
-impl Div<f32> for &Vector {
+impl Div<Float> for &Vector {
     type Output = Vector;
-    fn div(self, scalar: f32) -> Self::Output {
+    fn div(self, scalar: Float) -> Self::Output {
         if scalar == 0.0 {
             panic!("Dividing by 0");
         }
@@ -210,7 +222,7 @@ mod tests {
     fn should_be_able_to_get_the_magnitude() {
         let v = Vector::new(1.0, 2.0, 3.0);
         let magnitude = v.magnitude();
-        let base: f32 = 14.0;
+        let base: Float = 14.0;
         assert_eq!(magnitude, base.powf(0.5));
     }
 
@@ -259,11 +271,27 @@ mod tests {
         assert_eq!(reflected.w, 0.0);
 
         let v = Vector::new(0.0, -1.0, 0.0);
-        let n = Vector::new(2.0f32.sqrt() / 2.0, 2.0f32.sqrt() / 2.0, 0.0);
+        let n = Vector::new((2.0 as Float).sqrt() / 2.0, (2.0 as Float).sqrt() / 2.0, 0.0);
         let reflected = v.reflect(&n);
         assert!(reflected.x -  1.0 < EPSILON);
         assert!(reflected.y -  0.0 < EPSILON);
         assert!(reflected.z -  0.0 < EPSILON);
         assert!(reflected.w -  0.0 < EPSILON);
     }
+
+    #[test]
+    fn should_get_an_orthonormal_basis() {
+        for v in [
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(1.0, 2.0, 3.0).normalize(),
+        ] {
+            let (t, b) = v.orthonormal_basis();
+            assert!(t.dot_product(&v).abs() < EPSILON);
+            assert!(b.dot_product(&v).abs() < EPSILON);
+            assert!(t.dot_product(&b).abs() < EPSILON);
+            assert!((t.magnitude() - 1.0).abs() < EPSILON);
+            assert!((b.magnitude() - 1.0).abs() < EPSILON);
+        }
+    }
 }
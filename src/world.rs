@@ -0,0 +1,433 @@
+use crate::{
+    background::Background,
+    bvh::{Bvh, BvhStats, SplitMethod},
+    color::Color,
+    computations::Computations,
+    intersection::{hit, sort_intersections, Intersection},
+    light::{sample_vectors, Light},
+    object::Object,
+    point::Point,
+    ray::Ray,
+    utils::Float,
+    vector::Vector,
+};
+
+#[derive(Debug, Default)]
+pub struct World {
+    /// Private so every change goes through methods that drop the stale BVH
+    objects: Vec<Box<dyn Object>>,
+    pub lights: Vec<Box<dyn Light>>,
+    /// Seen by rays that miss every object
+    pub background: Background,
+    bvh: Option<Bvh>,
+}
+
+#[allow(dead_code)]
+impl World {
+    pub fn new() -> Self {
+        World {
+            objects: vec![],
+            lights: vec![],
+            background: Background::default(),
+            bvh: None,
+        }
+    }
+
+    pub fn add_light(&mut self, light: Box<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn add_object(&mut self, object: Box<dyn Object>) {
+        self.objects.push(object);
+        self.bvh = None;
+    }
+
+    pub fn objects(&self) -> &[Box<dyn Object>] {
+        &self.objects
+    }
+
+    /// To replace or remove objects, the BVH is dropped and has to be built again
+    pub fn objects_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        self.bvh = None;
+        &mut self.objects
+    }
+
+    /// Accelerates `intersect`, it has to be called again after adding new objects
+    pub fn build_bvh(&mut self, method: SplitMethod) -> BvhStats {
+        let bvh = Bvh::build(&self.objects, method);
+        let stats = bvh.stats;
+        self.bvh = Some(bvh);
+        stats
+    }
+
+    pub fn bvh_stats(&self) -> Option<BvhStats> {
+        self.bvh.as_ref().map(|bvh| bvh.stats)
+    }
+
+    /// Always return the intersections sorted
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object>> {
+        match &self.bvh {
+            Some(bvh) => bvh.intersect(&self.objects, ray),
+            None => {
+                let mut intersections = vec![];
+                for object in self.objects.iter() {
+                    intersections.append(&mut object.intersect(ray));
+                }
+                sort_intersections(&mut intersections);
+                intersections
+            }
+        }
+    }
+
+    /// Objects with an emissive material, children of groups included, they are sampled
+    /// as lights by the path tracer
+    pub fn emitters(&self) -> Vec<&dyn Object> {
+        let mut emitters = vec![];
+        for object in self.objects.iter() {
+            collect_emitters(object.as_ref(), &mut emitters);
+        }
+        emitters
+    }
+
+    /// Whether something blocks the path between the point and the light position
+    pub fn is_shadowed(&self, point: &Point, light_position: &Point, time: Float) -> bool {
+        let v = light_position - point;
+        let distance = v.magnitude();
+        let ray = Ray::with_time(*point, v.normalize(), time);
+        let intersections = self.intersect(&ray);
+        match hit(&intersections) {
+            Some(intersection) => intersection.t < distance,
+            None => false,
+        }
+    }
+
+    /// Whether anything at all is found by leaving the point in that direction, used by
+    /// lights that are infinitely far away
+    pub fn is_shadowed_towards(&self, point: &Point, direction: &Vector, time: Float) -> bool {
+        let ray = Ray::with_time(*point, direction.normalize(), time);
+        hit(&self.intersect(&ray)).is_some()
+    }
+
+    pub fn shade_hit(&self, comps: &Computations) -> Color {
+        let material = comps.object.material().surface_at(comps);
+        let mut color = material.emission;
+        for light in self.lights.iter() {
+            let samples = light.light_samples(&comps.over_point);
+            let intensity = light.sampled_intensity(&comps.over_point, &samples, self, comps.time);
+            let light_color = material.lighting_from(
+                light.as_ref(),
+                &sample_vectors(&comps.over_point, &samples),
+                &comps.eyev,
+                &comps.normalv,
+                intensity,
+            );
+            color = &color + &light_color;
+        }
+        color
+    }
+
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        let intersections = self.intersect(ray);
+        match hit(&intersections) {
+            Some(intersection) => self.shade_hit(&Computations::prepare(intersection, ray)),
+            None => self.background.color_in(&ray.direction),
+        }
+    }
+}
+
+fn collect_emitters<'a>(object: &'a dyn Object, emitters: &mut Vec<&'a dyn Object>) {
+    let children = object.children();
+    if children.is_empty() {
+        if object.material().is_emissive() {
+            emitters.push(object);
+        }
+        return;
+    }
+    for child in children.iter() {
+        collect_emitters(child.as_ref(), emitters);
+    }
+}
+
+/// Two concentric spheres lit from the upper left, shared by the shading tests
+#[cfg(test)]
+pub fn default_world() -> World {
+    use crate::{light::PointLight, material::Material, matrix::Matrix, sphere::Sphere};
+
+    let mut world = World::new();
+    world.add_light(Box::new(PointLight::new(
+        Point::new(-10.0, 10.0, -10.0),
+        Color::new(1.0, 1.0, 1.0),
+    )));
+    let mut outer = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+    outer.material = Material {
+        color: Color::new(0.8, 1.0, 0.6),
+        diffuse: 0.7,
+        specular: 0.2,
+        ..Default::default()
+    };
+    world.add_object(Box::new(outer));
+    world.add_object(Box::new(Sphere::new(
+        Point::new(0.0, 0.0, 0.0),
+        1.0,
+        Some(Matrix::scaling_3d(0.5, 0.5, 0.5)),
+    )));
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::consts::PI;
+
+    use super::*;
+    use crate::{
+        group::Group,
+        light::{AreaLight, DirectionalLight, PointLight, SpotLight},
+        material::Material,
+        matrix::Matrix,
+        sphere::Sphere,
+    };
+
+    fn two_spheres_world() -> World {
+        let mut world = World::new();
+        world.add_object(Box::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None)));
+        world.add_object(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::scaling_3d(0.5, 0.5, 0.5)),
+        )));
+        world
+    }
+
+    #[test]
+    fn should_create_an_empty_world() {
+        let world = World::new();
+        assert_eq!(world.objects.len(), 0);
+        assert!(world.bvh_stats().is_none());
+    }
+
+    #[test]
+    fn should_intersect_the_world() {
+        let world = two_spheres_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = world.intersect(&ray);
+        assert_eq!(intersections.len(), 4);
+        assert_eq!(intersections[0].t, 4.0);
+        assert_eq!(intersections[1].t, 4.5);
+        assert_eq!(intersections[2].t, 5.5);
+        assert_eq!(intersections[3].t, 6.0);
+    }
+
+    #[test]
+    fn should_intersect_the_world_through_the_bvh() {
+        let mut world = two_spheres_world();
+        let stats = world.build_bvh(SplitMethod::Median);
+        assert_eq!(stats.object_count, 2);
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = world.intersect(&ray);
+        assert_eq!(intersections.len(), 4);
+        assert_eq!(intersections[0].t, 4.0);
+        assert_eq!(intersections[3].t, 6.0);
+    }
+
+    #[test]
+    fn should_drop_the_bvh_when_objects_change() {
+        let mut world = two_spheres_world();
+        world.build_bvh(SplitMethod::Median);
+        // same number of objects, but the first sphere moved out of the way
+        world.objects_mut()[0] = Box::new(Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::translation_3d(0.0, 10.0, 0.0)),
+        ));
+        assert!(world.bvh_stats().is_none());
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersections = world.intersect(&ray);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].t, 4.5);
+    }
+
+    #[test]
+    fn should_not_be_shadowed_when_nothing_is_collinear() {
+        let world = default_world();
+        let light_position = Point::new(-10.0, 10.0, -10.0);
+        assert!(!world.is_shadowed(&Point::new(0.0, 10.0, 0.0), &light_position, 0.0));
+        assert!(world.is_shadowed(&Point::new(10.0, -10.0, 10.0), &light_position, 0.0));
+        assert!(!world.is_shadowed(&Point::new(-20.0, 20.0, -20.0), &light_position, 0.0));
+        assert!(!world.is_shadowed(&Point::new(-2.0, 2.0, -2.0), &light_position, 0.0));
+    }
+
+    #[test]
+    fn should_shade_an_intersection() {
+        let world = default_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(4.0, world.objects[0].as_ref());
+        let comps = Computations::prepare(&intersection, &ray);
+        let color = world.shade_hit(&comps);
+        assert!((color.r - 0.38066).abs() < 0.0001);
+        assert!((color.g - 0.47583).abs() < 0.0001);
+        assert!((color.b - 0.2855).abs() < 0.0001);
+    }
+
+    #[test]
+    fn should_shade_an_intersection_in_shadow() {
+        let mut world = World::new();
+        world.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        world.add_object(Box::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None)));
+        world.add_object(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(Matrix::translation_3d(0.0, 0.0, 10.0)),
+        )));
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(4.0, world.objects[1].as_ref());
+        let comps = Computations::prepare(&intersection, &ray);
+        assert_eq!(world.shade_hit(&comps), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn should_get_the_color_when_a_ray_misses() {
+        let world = default_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(world.color_at(&ray), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_see_the_background_when_a_ray_misses() {
+        let mut world = default_world();
+        world.background = Background::Gradient {
+            top: Color::new(0.2, 0.4, 1.0),
+            bottom: Color::new(1.0, 1.0, 1.0),
+        };
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(world.color_at(&ray), Color::new(0.2, 0.4, 1.0));
+    }
+
+    #[test]
+    fn should_get_the_color_when_a_ray_hits() {
+        let world = default_world();
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let color = world.color_at(&ray);
+        assert!((color.r - 0.38066).abs() < 0.0001);
+        assert!((color.g - 0.47583).abs() < 0.0001);
+        assert!((color.b - 0.2855).abs() < 0.0001);
+    }
+
+    #[test]
+    fn should_soften_shadows_with_an_area_light() {
+        let mut world = World::new();
+        let mut light = AreaLight::new(
+            Point::new(-1.0, 4.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 2.0),
+            4,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.jitter = false;
+        world.add_light(Box::new(light));
+        world.add_object(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(&Matrix::translation_3d(0.0, 2.0, 0.0) * &Matrix::scaling_3d(0.8, 0.8, 0.8)),
+        )));
+
+        // right below the blocker everything is hidden, far away everything is visible
+        // and in between only part of the light reaches the point
+        let light = &world.lights[0];
+        assert_eq!(
+            light.intensity_at(&Point::new(0.0, 0.0, 0.0), &world, 0.0),
+            0.0
+        );
+        assert_eq!(
+            light.intensity_at(&Point::new(10.0, 0.0, 0.0), &world, 0.0),
+            1.0
+        );
+        let penumbra = light.intensity_at(&Point::new(1.2, 0.0, 0.0), &world, 0.0);
+        assert!(penumbra > 0.0 && penumbra < 1.0);
+    }
+
+    #[test]
+    fn should_shade_with_every_kind_of_light() {
+        let mut world = World::new();
+        world.add_object(Box::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None)));
+        world.add_light(Box::new(DirectionalLight::new(
+            Vector::new(0.0, 0.0, 1.0),
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        world.add_light(Box::new(SpotLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 16.0,
+            PI / 8.0,
+            Color::new(0.5, 0.5, 0.5),
+        )));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        // each light adds 0.05 ambient, 0.45 diffuse and 0.45 specular
+        assert_eq!(world.color_at(&ray), Color::new(1.9, 1.9, 1.9));
+
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(world.color_at(&ray), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn should_cast_shadows_of_moving_objects_at_the_ray_time() {
+        let mut world = World::new();
+        let mut blocker = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        blocker.end_transform = Some(Matrix::translation_3d(10.0, 0.0, 0.0));
+        world.add_object(Box::new(blocker));
+        let point = Point::new(0.0, -5.0, 0.0);
+        let light_position = Point::new(0.0, 5.0, 0.0);
+        assert!(world.is_shadowed(&point, &light_position, 0.0));
+        assert!(!world.is_shadowed(&point, &light_position, 0.5));
+        assert!(world.is_shadowed(
+            &Point::new(10.0, -5.0, 0.0),
+            &Point::new(10.0, 5.0, 0.0),
+            1.0
+        ));
+    }
+
+    #[test]
+    fn should_find_the_emitters_inside_groups() {
+        let glowing = Material {
+            emission: Color::new(1.0, 1.0, 1.0),
+            ..Default::default()
+        };
+        let mut world = default_world();
+        assert_eq!(world.emitters().len(), 0);
+
+        let mut lamp = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        lamp.material = glowing.clone();
+        world.add_object(Box::new(lamp));
+        let panel = Group::mesh(
+            &[
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            glowing,
+            None,
+        );
+        let mut group = Group::new(None);
+        group.add_child(Box::new(panel));
+        world.add_object(Box::new(group));
+        assert_eq!(world.emitters().len(), 3);
+    }
+
+    #[test]
+    fn should_add_the_emission_when_shading() {
+        let mut world = World::new();
+        let mut sign = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, None);
+        sign.material = Material {
+            emission: Color::new(0.5, 0.0, 0.0),
+            ..Default::default()
+        };
+        world.add_object(Box::new(sign));
+        let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(world.color_at(&ray), Color::new(0.5, 0.0, 0.0));
+    }
+}